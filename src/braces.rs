@@ -0,0 +1,157 @@
+//! Brace expansion, which turns `file.{txt,md}` into `file.txt file.md`.
+//!
+//! Braces are expanded in the raw command line, before variables and command substitutions, so that
+//! quoting can still protect them: `'{a,b}'`, `"{a,b}"` and `\{a,b}` are left alone.
+
+use crate::matching_paren;
+
+/// Expands the brace groups in a command line.
+///
+/// # Purpose
+/// This function replaces every word that contains a brace group with one word for each alternative in the
+/// group, keeping the text around the group: `pre{a,b}post` becomes `preapost prebpost`. A group is either a
+/// comma-separated list, `{a,b,c}`, or a range of integers, `{1..3}` or `{3..1}`. Several groups in one word
+/// give every combination, in order. A range of more than `MAX_RANGE_LEN` integers is kept as typed rather
+/// than expanded, so a typo cannot exhaust memory. Braces that hold neither, such as `{}` or `{one}`, are kept as typed,
+/// as are braces inside quotes, after a backslash, or in `${name}` and `$(command)`; the command of a
+/// substitution has its braces expanded when it runs. Nested groups, such as `{a,{b,c}}`, are not supported:
+/// a word containing one is kept as typed.
+///
+/// # Parameters
+/// - `line`: A `&str` representing the command line as typed.
+///
+/// # Returns
+/// This function returns the line with its brace groups expanded, as a `String`. Quoting is left in place for
+/// the tokenizer.
+///
+/// # Examples
+/// ```
+/// use myshell::expand_braces;
+///
+/// assert_eq!(expand_braces("echo file.{txt,md,rs}"), "echo file.txt file.md file.rs");
+/// assert_eq!(expand_braces("touch {a,b}{1..2}"), "touch a1 a2 b1 b2");
+/// assert_eq!(expand_braces("echo '{a,b}' {x}"), "echo '{a,b}' {x}");
+/// ```
+pub fn expand_braces(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            expanded.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let word_len = unquoted_chars(rest)
+            .into_iter()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(rest.len(), |(index, _)| index);
+        expanded.push_str(&expand_word(&rest[..word_len]).join(" "));
+        rest = &rest[word_len..];
+    }
+
+    expanded
+}
+
+/// Expands the first brace group in a word, then the groups in each result.
+fn expand_word(word: &str) -> Vec<String> {
+    let unquoted = unquoted_chars(word);
+
+    for (position, &(open, c)) in unquoted.iter().enumerate() {
+        if c != '{' {
+            continue;
+        }
+        let inside: Vec<(usize, char)> = unquoted[position + 1..].iter().copied().take_while(|&(_, c)| c != '}').collect();
+        let Some(&(close, _)) = unquoted.get(position + 1 + inside.len()) else {
+            break; // No closing brace, so neither this one nor any later one opens a group.
+        };
+        if inside.iter().any(|&(_, c)| c == '{') {
+            return vec![word.to_string()]; // Nested groups are not supported.
+        }
+
+        let body = &word[open + 1..close];
+        let commas: Vec<usize> = inside.iter().filter(|&&(_, c)| c == ',').map(|&(index, _)| index).collect();
+        let alternatives: Vec<String> = if !commas.is_empty() {
+            let mut start = open + 1;
+            let mut alternatives = Vec::new();
+            for comma in commas {
+                alternatives.push(word[start..comma].to_string());
+                start = comma + 1;
+            }
+            alternatives.push(word[start..close].to_string());
+            alternatives
+        } else if let Some(range) = integer_range(body) {
+            range
+        } else {
+            continue; // `{one}` is not a group; look for one further on.
+        };
+
+        let (prefix, suffix) = (&word[..open], &word[close + 1..]);
+        return alternatives
+            .iter()
+            .flat_map(|alternative| expand_word(&format!("{}{}{}", prefix, alternative, suffix)))
+            .collect();
+    }
+
+    vec![word.to_string()]
+}
+
+/// The most integers a range such as `{1..10}` may expand to.
+const MAX_RANGE_LEN: u64 = 10_000;
+
+/// Reads `start..end` as the integers from `start` to `end`, counting down if `end` is smaller, or returns
+/// `None` if there would be more than `MAX_RANGE_LEN` of them.
+fn integer_range(body: &str) -> Option<Vec<String>> {
+    let (start, end) = body.split_once("..")?;
+    let (start, end): (i64, i64) = (start.parse().ok()?, end.parse().ok()?);
+    if start.abs_diff(end) >= MAX_RANGE_LEN {
+        return None;
+    }
+    let numbers: Vec<i64> = if start <= end { (start..=end).collect() } else { (end..=start).rev().collect() };
+    Some(numbers.iter().map(i64::to_string).collect())
+}
+
+/// Lists the characters of `text` that are not quoted, escaped, or inside `${...}` or `$(...)`, with their
+/// byte positions.
+pub(crate) fn unquoted_chars(text: &str) -> Vec<(usize, char)> {
+    let mut unquoted = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let skip_to = match c {
+            '\'' => text[index + 1..].find('\'').map_or(text.len(), |len| index + 1 + len + 1),
+            '"' => {
+                let mut end = text.len();
+                let mut escaped = false;
+                for (offset, c) in text[index + 1..].char_indices() {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => {
+                            end = index + 1 + offset + 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                end
+            }
+            '\\' => index + 1 + text[index + 1..].chars().next().map_or(0, char::len_utf8),
+            '$' if text[index + 1..].starts_with('(') => {
+                matching_paren(&text[index + 2..]).map_or(text.len(), |len| index + 2 + len + 1)
+            }
+            '$' if text[index + 1..].starts_with('{') => {
+                text[index + 2..].find('}').map_or(text.len(), |len| index + 2 + len + 1)
+            }
+            c => {
+                unquoted.push((index, c));
+                continue;
+            }
+        };
+        while chars.peek().is_some_and(|&(next, _)| next < skip_to) {
+            chars.next();
+        }
+    }
+
+    unquoted
+}
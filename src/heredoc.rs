@@ -0,0 +1,207 @@
+//! Here-documents, which feed the lines after a command to its standard input: `cat <<EOF` reads lines up to
+//! one that is just `EOF`.
+//!
+//! The shell reads command lines one at a time, so a here-document is gathered by whoever reads the lines
+//! (`run_loop` or `join_continuations`) and folded into its command line as a here-string (`<<<`), quoted so
+//! that the usual expansion and tokenizing give the command exactly the body.
+
+use std::error::Error;
+
+use crate::matching_paren;
+
+/// A here-document operator found in a command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HereDocument {
+    /// The line that ends the body, with any quoting removed.
+    pub delimiter: String,
+    /// Whether leading tabs are removed from the body and the delimiter line (`<<-`).
+    pub strip_tabs: bool,
+    /// Whether variables and substitutions in the body are expanded, which they are unless the delimiter was
+    /// quoted, as in bash.
+    pub expands: bool,
+    /// The byte range of the operator and its delimiter in the line.
+    range: (usize, usize),
+}
+
+impl HereDocument {
+    /// Finds the first here-document operator, `<<word` or `<<-word`, in a command line. Operators inside quotes
+    /// or expansions, `<<<` here-strings, and `<<` in arithmetic such as `$((1 << 2))` are not here-documents.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::HereDocument;
+    ///
+    /// let heredoc = HereDocument::find("cat <<-'END' | wc -l").unwrap();
+    /// assert_eq!(heredoc.delimiter, "END");
+    /// assert!(heredoc.strip_tabs);
+    /// assert!(!heredoc.expands);
+    ///
+    /// assert!(HereDocument::find("cat <<EOF").unwrap().expands);
+    /// assert_eq!(HereDocument::find("cat <<< word"), None);
+    /// assert_eq!(HereDocument::find("echo '<<EOF' $((1 << 2))"), None);
+    /// ```
+    pub fn find(line: &str) -> Option<HereDocument> {
+        let mut quote = None;
+        let mut index = 0;
+
+        while let Some(c) = line[index..].chars().next() {
+            let rest = &line[index..];
+            let skip = match (quote, c) {
+                (Some(open), c) if c == open => {
+                    quote = None;
+                    1
+                }
+                (Some('"') | None, '\\') => 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
+                (None, '\'' | '"') => {
+                    quote = Some(c);
+                    1
+                }
+                // An unterminated expansion holds the rest of the line.
+                (None, '$') if rest[1..].starts_with('(') => matching_paren(&rest[2..]).map_or(rest.len(), |len| 2 + len + 1),
+                (None, '$') if rest[1..].starts_with('{') => rest.find('}').map_or(rest.len(), |len| len + 1),
+                (None, '<') if rest.starts_with("<<<") => 3,
+                (None, '<') if rest.starts_with("<<") => return Self::parse_operator(line, index),
+                _ => c.len_utf8(),
+            };
+            index += skip;
+        }
+        None
+    }
+
+    /// Reads the operator starting at `start`, returning `None` if no delimiter follows it.
+    fn parse_operator(line: &str, start: usize) -> Option<HereDocument> {
+        let after = &line[start + 2..];
+        let strip_tabs = after.starts_with('-');
+        let after = if strip_tabs { &after[1..] } else { after };
+        let word_start = line.len() - after.trim_start().len();
+        let word_len = word_len(&line[word_start..]);
+        let word = &line[word_start..word_start + word_len];
+
+        let delimiter = crate::tokenize(word).ok()?.pop()?;
+        let expands = !word.contains(['\'', '"', '\\']);
+        Some(HereDocument { delimiter, strip_tabs, expands, range: (start, word_start + word_len) })
+    }
+
+    /// Checks whether a line read after the command line ends the body.
+    pub fn is_delimiter(&self, line: &str) -> bool {
+        self.body_line(line) == self.delimiter
+    }
+
+    /// Returns a line of the body as the command sees it, without its leading tabs for `<<-`.
+    pub fn body_line<'a>(&self, line: &'a str) -> &'a str {
+        if self.strip_tabs {
+            line.trim_start_matches('\t')
+        } else {
+            line
+        }
+    }
+
+    /// Rewrites the command line with the operator replaced by a here-string holding `body`, quoted so that
+    /// expansion and `tokenize` turn it back into the body: in double quotes if the body expands, and in single
+    /// quotes if it does not. An empty body reads from the null device instead, since a here-string always
+    /// holds at least a newline.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::HereDocument;
+    ///
+    /// let line = "cat <<EOF > out.txt";
+    /// let body = vec!["Dear $name,".to_string(), "say \"hi\"".to_string()];
+    ///
+    /// let heredoc = HereDocument::find(line).unwrap();
+    /// assert_eq!(heredoc.rewrite(line, &body), "cat <<< \"Dear $name,\nsay \\\"hi\\\"\" > out.txt");
+    ///
+    /// let heredoc = HereDocument::find("cat <<'EOF'").unwrap();
+    /// assert_eq!(heredoc.rewrite("cat <<'EOF'", &body), "cat <<< 'Dear $name,\nsay \"hi\"'");
+    /// ```
+    pub fn rewrite(&self, line: &str, body: &[String]) -> String {
+        let (start, end) = self.range;
+        let text = body.join("\n");
+        let redirection = if body.is_empty() {
+            "< /dev/null".to_string()
+        } else if self.expands {
+            format!("<<< \"{}\"", escape_for_double_quotes(&text))
+        } else {
+            format!("<<< '{}'", text.replace('\'', "'\\''"))
+        };
+        format!("{}{}{}", &line[..start], redirection, &line[end..])
+    }
+}
+
+/// Measures the delimiter word at the start of `text`, which ends at unquoted whitespace or an operator.
+fn word_len(text: &str) -> usize {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() || ";|&<>".contains(c) => return index,
+            None => {}
+        }
+    }
+    text.len()
+}
+
+/// Escapes body text for double quotes, where a backslash keeps its here-document meaning except before `"`.
+fn escape_for_double_quotes(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            // Before a quote, or at the very end, a backslash is literal text in a here-document.
+            '\\' if matches!(chars.peek(), None | Some('"')) => escaped.push_str("\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Gathers the here-documents of a command line.
+///
+/// # Purpose
+/// This function reads the body of every here-document operator in `line` (see `HereDocument::find`) with
+/// `next_line`, up to each one's delimiter line, and returns the line with each operator rewritten to feed
+/// its body to the command (see `HereDocument::rewrite`). A line without an operator is returned as it is.
+/// If the input ends before a delimiter, a warning is printed and the lines read so far are used, as in bash.
+///
+/// # Parameters
+/// - `line`: A `&str` representing the command line, as typed.
+/// - `next_line`: A closure reading the next line of input, returning `None` at the end of input.
+///
+/// # Returns
+/// This function returns a `Result<String, Box<dyn Error>>` with the rewritten line. It returns an `Err` if
+/// `next_line` does.
+///
+/// # Examples
+/// ```
+/// use myshell::read_here_documents;
+///
+/// let mut lines = ["\thello", "\tEND", "ECHO next"].into_iter().map(String::from);
+/// let line = read_here_documents("cat <<-END", || Ok(lines.next())).unwrap();
+///
+/// assert_eq!(line, "cat <<< \"hello\"");
+/// assert_eq!(lines.next().as_deref(), Some("ECHO next"));
+/// ```
+pub fn read_here_documents<F>(line: &str, mut next_line: F) -> Result<String, Box<dyn Error>>
+where
+    F: FnMut() -> Result<Option<String>, Box<dyn Error>>,
+{
+    let mut line = line.to_string();
+    while let Some(heredoc) = HereDocument::find(&line) {
+        let mut body = Vec::new();
+        loop {
+            match next_line()? {
+                Some(next) if heredoc.is_delimiter(&next) => break,
+                Some(next) => body.push(heredoc.body_line(&next).to_string()),
+                None => {
+                    eprintln!("Warning: here-document ended by the end of input (wanted '{}')", heredoc.delimiter);
+                    break;
+                }
+            }
+        }
+        line = heredoc.rewrite(&line, &body);
+    }
+    Ok(line)
+}
@@ -0,0 +1,8362 @@
+//! MyShell: a small interactive shell with a customizable prompt and user-defined aliases.
+//!
+//! The binary in `main.rs` runs the read-dispatch loop; everything it dispatches to lives here.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use rustyline::{completion::{Completer, FilenameCompleter, Pair}, highlight::Highlighter, hint::Hinter, validate::Validator, Context, Helper};
+mod braces;
+mod heredoc;
+mod parse;
+mod tokenize;
+
+pub use braces::expand_braces;
+pub use heredoc::{read_here_documents, HereDocument};
+pub use parse::{parse, Command};
+pub use tokenize::tokenize;
+
+use std::{cell::RefCell, env, error::Error, process::{self, Child, ExitStatus, Output, Stdio}, collections::{BTreeMap, HashMap}, fs::{self, File}, io::{self, BufReader, BufRead, BufWriter, Write}, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Mutex}, time::{Duration, Instant, SystemTime}};
+
+/// Set by the SIGINT handler whenever Ctrl+C is pressed.
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Describes a built-in command for the `HELP` command.
+pub struct BuiltinHelp {
+    /// The command name as typed at the prompt.
+    pub name: &'static str,
+    /// A one-line description shown in the command list.
+    pub summary: &'static str,
+    /// The detailed usage shown by `HELP <command>`.
+    pub usage: &'static str,
+}
+
+/// Every built-in command, in the order `HELP` lists them.
+pub static BUILTINS: &[BuiltinHelp] = &[
+    BuiltinHelp {
+        name: "HELP",
+        summary: "List the built-in commands or describe one of them.",
+        usage: "HELP [command]\n\
+                With no argument, lists every built-in command. With a command name, prints its usage.",
+    },
+    BuiltinHelp {
+        name: "STOP",
+        summary: "Exit the shell.",
+        usage: "STOP [status]\n\
+                Saves the aliases if autosave is on and exits the shell with the given status, or 0.\n\
+                exit, quit and logout do the same. While background jobs are running, the first STOP\n\
+                at the prompt only warns; a second one ends the jobs and exits.",
+    },
+    BuiltinHelp {
+        name: "SETSHELLNAME",
+        summary: "Change the shell name shown in the prompt.",
+        usage: "SETSHELLNAME <name...>\n\
+                Sets the shell name to the remaining words joined by spaces. The name cannot be empty.",
+    },
+    BuiltinHelp {
+        name: "SETTERMINATOR",
+        summary: "Change the terminator shown after the shell name.",
+        usage: "SETTERMINATOR <terminator...>\n\
+                SETTERMINATOR --color <color>\n\
+                Sets the prompt terminator to the remaining words joined by spaces. Quote it to keep\n\
+                its own spacing, as in SETTERMINATOR \" $ \". The default prompt adds one space after it. Without an argument the current terminator\n\
+                is kept.\n\
+                With --color, shows the terminator in black, red, green, yellow, blue, magenta, cyan\n\
+                or white, or in the terminal's own color again with none. Colors are dropped when\n\
+                NO_COLOR is set or the shell was started with --no-color.",
+    },
+    BuiltinHelp {
+        name: "SETPROMPT",
+        summary: "Change the prompt template.",
+        usage: "SETPROMPT [template...]\n\
+                Sets the prompt template. Quote it, as in SETPROMPT '\\u@\\h \\w$ ', to keep its\n\
+                spaces and backslashes.\n\
+                Placeholders: \\s shell name, \\> terminator, \\w working directory, \\u user,\n\
+                \\h host, \\g git branch, \\t time (HH:MM:SS), \\d date (Tue May 26), \\$? or \\?\n\
+                last exit status, \\G green after success, \\R red after failure, \\e escape (for\n\
+                ANSI colors such as \\e[32m), \\\\ a backslash. Colors are dropped when NO_COLOR is\n\
+                set or the shell was started with --no-color.\n\
+                Without a template the default ('\\s\\> ') is restored.",
+    },
+    BuiltinHelp {
+        name: "SETPROMPT2",
+        summary: "Change the continuation prompt.",
+        usage: "SETPROMPT2 [prompt...]\n\
+                Sets the prompt shown while reading the rest of a command: the line after a trailing\n\
+                backslash, the lines of an IF, WHILE or FOR block, and the body of a here-document.\n\
+                It is shown as typed, without placeholders. Without a prompt the default ('> ') is\n\
+                restored.",
+    },
+    BuiltinHelp {
+        name: "NEWNAME",
+        summary: "Define, delete, or list aliases.",
+        usage: "NEWNAME [alias [command [--desc text]]]\n\
+                With no arguments, lists the aliases. With an alias name, deletes that alias.\n\
+                With an alias name and a command, defines or replaces the alias; --desc notes what\n\
+                it is for, shown after the command in the alias list. Arguments given when the\n\
+                alias runs follow its command.",
+    },
+    BuiltinHelp {
+        name: "ALIAS",
+        summary: "Define or show aliases in bash's name=value form.",
+        usage: "ALIAS [name[=command]...]\n\
+                alias ll='ls -l' defines ll as NEWNAME ll 'ls -l' does; alias is another name for\n\
+                ALIAS. A name without =command shows that alias, and no arguments show them all, as\n\
+                lines such as alias ll='ls -l' that can be pasted back in.",
+    },
+    BuiltinHelp {
+        name: "LISTNEWNAMES",
+        summary: "List the defined aliases.",
+        usage: "LISTNEWNAMES\n\
+                Prints every alias and the command it runs, sorted by name, with its description\n\
+                after a '#' if it has one.",
+    },
+    BuiltinHelp {
+        name: "SEARCHALIAS",
+        summary: "List the aliases whose names contain some text.",
+        usage: "SEARCHALIAS <text>\n\
+                Prints, sorted by name, every alias whose name contains the text, and the command it runs.",
+    },
+    BuiltinHelp {
+        name: "GLOBALALIAS",
+        summary: "Define, delete, or list global aliases.",
+        usage: "GLOBALALIAS [name [text...]]\n\
+                With a name and some text, makes the name stand for the text wherever it appears as a\n\
+                word of its own, so that after GLOBALALIAS G \"| grep\", ls G foo runs ls | grep foo.\n\
+                Quoted words are left alone. With only a name, deletes that global alias; with no\n\
+                arguments, lists them.",
+    },
+    BuiltinHelp {
+        name: "SUFFIXALIAS",
+        summary: "Define, delete, or list suffix aliases.",
+        usage: "SUFFIXALIAS [suffix [command...]]\n\
+                With a suffix and a command, runs files ending in .suffix with that command, so that after\n\
+                SUFFIXALIAS py python, ./script.py runs python ./script.py. Files that are executable\n\
+                themselves, and names that are aliases, run as usual. With only a suffix, deletes its\n\
+                suffix alias; with no arguments, lists them.",
+    },
+    BuiltinHelp {
+        name: "COUNTALIASES",
+        summary: "Show how many aliases are defined out of the limit.",
+        usage: "COUNTALIASES\n\
+                Prints '<defined>/<limit> aliases defined', such as '7/10 aliases defined'.",
+    },
+    BuiltinHelp {
+        name: "MOSTUSED",
+        summary: "List the aliases by how often they have been run.",
+        usage: "MOSTUSED\n\
+                Prints every alias with the number of times it has been run, most used first. The\n\
+                counts are kept in settings files written by SAVESETTINGS.",
+    },
+    BuiltinHelp {
+        name: "SAVENEWNAMES",
+        summary: "Save the aliases to a file.",
+        usage: "SAVENEWNAMES [--format text|json|toml|bash] <file_name>\n\
+                Writes the aliases in the given format, or the one the file's extension names\n\
+                (.json, .toml, .sh or .bash), or else as text. Text is one alias per line as\n\
+                '<alias><tab><command>', with tabs, newlines, and backslashes in the command written\n\
+                as \\t, \\n, and \\\\, for READNEWNAMES. JSON is an object mapping each alias to its\n\
+                command, TOML an [aliases] table that LOADSETTINGS reads, and bash a file of alias\n\
+                commands, as EXPORTALIASES writes.",
+    },
+    BuiltinHelp {
+        name: "READNEWNAMES",
+        summary: "Load aliases from a file.",
+        usage: "READNEWNAMES <file_name>\n\
+                Reads lines written by SAVENEWNAMES, or older '<alias> <command>' lines, stopping\n\
+                once the alias limit is reached. Blank lines and '#' comments are skipped.",
+    },
+    BuiltinHelp {
+        name: "EXPORTALIASES",
+        summary: "Write the aliases as bash alias commands.",
+        usage: "EXPORTALIASES <file_name>\n\
+                Writes one \"alias name='command'\" line per alias, sorted by name, for bash to source.",
+    },
+    BuiltinHelp {
+        name: "IMPORTALIASES",
+        summary: "Load aliases from a bash alias file.",
+        usage: "IMPORTALIASES <file_name>\n\
+                Reads \"alias name=value\" lines, with the value quoted as bash allows, stopping once\n\
+                the alias limit is reached. Other lines are skipped with a warning.",
+    },
+    BuiltinHelp {
+        name: "SAVESETTINGS",
+        summary: "Save the whole shell configuration to a file.",
+        usage: "SAVESETTINGS <file_name>\n\
+                Writes the shell name, terminator, prompt, alias limit, color setting, aliases and\n\
+                shell variables to a TOML file in the same format as ~/.myshellrc.",
+    },
+    BuiltinHelp {
+        name: "LOADSETTINGS",
+        summary: "Load a shell configuration from a file.",
+        usage: "LOADSETTINGS <file_name>\n\
+                Applies the settings in a TOML file written by SAVESETTINGS. Settings missing from the\n\
+                file are left unchanged; aliases and variables are added to the current ones.",
+    },
+    BuiltinHelp {
+        name: "RELOAD",
+        summary: "Re-read the startup configuration file.",
+        usage: "RELOAD\n\
+                Applies ~/.myshellrc again, as at startup, and lists the settings it changed. Aliases\n\
+                and variables are added to the current ones. A file with errors changes nothing.",
+    },
+    BuiltinHelp {
+        name: "EDITCONFIG",
+        summary: "Edit the startup configuration file, then reload it.",
+        usage: "EDITCONFIG\n\
+                Opens ~/.myshellrc in $EDITOR (vi, or notepad on Windows, if it is not set), waits for\n\
+                the editor to close, and then applies the file as RELOAD does.",
+    },
+    BuiltinHelp {
+        name: "UNALIAS",
+        summary: "Remove one or more aliases.",
+        usage: "UNALIAS <alias...>\n\
+                Removes each named alias and reports how many were removed.",
+    },
+    BuiltinHelp {
+        name: "ALIASCLEAR",
+        summary: "Remove every alias.",
+        usage: "ALIASCLEAR\n\
+                Asks for confirmation, then removes all aliases.",
+    },
+    BuiltinHelp {
+        name: "RESET",
+        summary: "Restore the default shell settings.",
+        usage: "RESET [--all]\n\
+                Restores the default shell name, terminator and its color, prompt template,\n\
+                continuation prompt and alias limit. With --all, every alias is removed as well.",
+    },
+    BuiltinHelp {
+        name: "STATUS",
+        summary: "Show the current shell configuration.",
+        usage: "STATUS\n\
+                Prints the shell name, terminator, prompt template, continuation prompt, alias count\n\
+                and limit, last exit status and number of background jobs.",
+    },
+    BuiltinHelp {
+        name: "SETTINGS",
+        summary: "Show the current shell configuration; the same as STATUS.",
+        usage: "SETTINGS\n\
+                See HELP STATUS.",
+    },
+    BuiltinHelp {
+        name: "SETLOG",
+        summary: "Log every command line to a file.",
+        usage: "SETLOG <file_name | off>\n\
+                Appends each command line run from now on, with a timestamp, to the file. SETLOG off\n\
+                stops logging. Without an argument, shows where commands are logged.",
+    },
+    BuiltinHelp {
+        name: "SETOPT",
+        summary: "Show or change shell options.",
+        usage: "SETOPT [option [on | off]]\n\
+                Turns an option on or off, or shows its value. Without arguments, lists every option.\n\
+                errexit: outside the interactive prompt, stop at the first command line that leaves a\n\
+                non-zero status and exit with that status. This covers programs that exit with an\n\
+                error, commands that cannot be started (status 1), failing built-in commands (status\n\
+                1), and syntax errors (status 2). The condition of an IF does not count.\n\
+                xtrace: print each command to standard error, prefixed with '+ ', after expansion\n\
+                and alias lookup and just before it runs.\n\
+                dryrun: print each program, pipeline, or background job, expanded as xtrace shows it,\n\
+                instead of running it, and set $? to 0. Built-in commands and functions still run, so\n\
+                SET and CD take effect for the lines after them, but TIMEOUT and EDITCONFIG are only\n\
+                printed. Command substitutions still run, since their output is part of the line.\n\
+                case_insensitive_builtins: accept built-in command names in any case, so that stop\n\
+                runs STOP. Aliases and programs keep their exact names, but a program named like a\n\
+                built-in command, such as echo, can then no longer be run by that name.\n\
+                hist_ignore_dups: leave a command line out of the history if it repeats the one before.\n\
+                hist_ignore_space: leave command lines typed with a leading space out of the history.\n\
+                verbose: confirm each setting changed, alias defined, or file of aliases or settings\n\
+                loaded. It is on at the prompt and off in a script file. quiet is its opposite:\n\
+                SETOPT quiet on turns verbose off.",
+    },
+    BuiltinHelp {
+        name: "HISTORY",
+        summary: "List previously entered commands.",
+        usage: "HISTORY [count]\n\
+                Lists the commands entered this session, numbered from the oldest. With a count,\n\
+                lists only that many of the most recent commands. Up and Down recall them at the prompt,\n\
+                and Ctrl+R searches back through them for the text typed; Enter runs the match.",
+    },
+    BuiltinHelp {
+        name: "CLEARHISTORY",
+        summary: "Forget the command history.",
+        usage: "CLEARHISTORY [--yes]\n\
+                Asks for confirmation, unless --yes is given, then empties the command history.",
+    },
+    BuiltinHelp {
+        name: "CLEAR",
+        summary: "Clear the terminal screen.",
+        usage: "CLEAR\n\
+                Clears the screen and moves the cursor to the top-left corner.",
+    },
+    BuiltinHelp {
+        name: "ECHO",
+        summary: "Print arguments separated by spaces.",
+        usage: "ECHO [-n] [-e] [args...]\n\
+                Prints the arguments joined by spaces, followed by a newline.\n\
+                -n  Do not print the trailing newline.\n\
+                -e  Interpret the escapes \\n (newline), \\t (tab), and \\\\ (backslash).",
+    },
+    BuiltinHelp {
+        name: "SET",
+        summary: "Set or list shell variables.",
+        usage: "SET [name [value...]]\n\
+                Sets a shell variable to the remaining words joined by spaces. Shell variables expand as $name or ${name} but are not passed to programs.\n\
+                With no arguments, lists every shell variable sorted by name.",
+    },
+    BuiltinHelp {
+        name: "READ",
+        summary: "Read a line of input into a shell variable.",
+        usage: "READ [-p prompt] <name>\n\
+                Reads one line from standard input into the shell variable name, printing the prompt\n\
+                first if -p is given. At the end of input the variable is set empty and READ fails.",
+    },
+    BuiltinHelp {
+        name: "EXPORT",
+        summary: "Set or list environment variables passed to programs.",
+        usage: "EXPORT [name=value | name]...\n\
+                Sets each name=value in the environment passed to programs. A bare name exports the\n\
+                shell variable of that name. With no arguments, lists the environment sorted by name.",
+    },
+    BuiltinHelp {
+        name: "UNSET",
+        summary: "Remove shell or environment variables.",
+        usage: "UNSET <name...>\n\
+                Removes each name from both the shell variables and the environment.",
+    },
+    BuiltinHelp {
+        name: "CD",
+        summary: "Change the current directory.",
+        usage: "CD [directory | -]\n\
+                Changes to the given directory, or to $HOME when no directory is given. CD - returns\n\
+                to the previous directory and prints it.",
+    },
+    BuiltinHelp {
+        name: "PUSHD",
+        summary: "Change directory, remembering the current one.",
+        usage: "PUSHD <directory>\n\
+                Pushes the current directory onto the directory stack and changes to the given one.",
+    },
+    BuiltinHelp {
+        name: "POPD",
+        summary: "Return to the directory on top of the stack.",
+        usage: "POPD\n\
+                Removes the top of the directory stack and changes to it.",
+    },
+    BuiltinHelp {
+        name: "DIRS",
+        summary: "List the directory stack.",
+        usage: "DIRS\n\
+                Lists the current directory followed by the directory stack, most recent first.",
+    },
+    BuiltinHelp {
+        name: "WHICH",
+        summary: "Show what a command name runs.",
+        usage: "WHICH <name...>\n\
+                Reports whether each name is a built-in or an alias, or prints the path of the executable\n\
+                found on $PATH. Fails if any name cannot be found.",
+    },
+    BuiltinHelp {
+        name: "HASH",
+        summary: "Show or reset the remembered program paths.",
+        usage: "HASH [-r | name...]\n\
+                The shell remembers where on $PATH it found each program, so that running it again\n\
+                skips the search. Without arguments, lists the remembered paths. With names, looks\n\
+                them up and remembers them. -r forgets every path, as changing PATH does.",
+    },
+    BuiltinHelp {
+        name: "TYPE",
+        summary: "Describe how the shell interprets a name.",
+        usage: "TYPE <name...>\n\
+                Reports whether each name is a shell built-in, an alias (and its expansion), or an\n\
+                external command (and its path). Fails if any name cannot be found.",
+    },
+    BuiltinHelp {
+        name: "SOURCE",
+        summary: "Run the commands in a file.",
+        usage: "SOURCE <file_name> [args...]\n\
+                Runs each line of the file in this shell, as if it had been typed, so it can change\n\
+                the shell's settings, aliases and variables. IF, WHILE and FOR blocks may span\n\
+                several lines.\n\
+                While it runs, $0 is the file name, $1 to $9 (or ${10} and on) are the arguments,\n\
+                and $# is how many there are.",
+    },
+    BuiltinHelp {
+        name: "SHIFT",
+        summary: "Drop the first positional parameters.",
+        usage: "SHIFT [n]\n\
+                Drops the first n positional parameters, one by default, so that $2 becomes $1 and\n\
+                so on; $0 is kept. Fails if there are fewer than n.",
+    },
+    BuiltinHelp {
+        name: "IF",
+        summary: "Run lines only if a command succeeds.",
+        usage: "IF <command> [args...]\n\
+                THEN\n\
+                <lines>\n\
+                [ELSE\n\
+                <lines>]\n\
+                FI\n\
+                Runs the command, then the lines after THEN if it exited with status 0, or the lines\n\
+                after ELSE otherwise. THEN, ELSE and FI each stand alone on their own line, and blocks\n\
+                may be nested. At the prompt, the shell keeps reading lines until the closing FI.",
+    },
+    BuiltinHelp {
+        name: "WHILE",
+        summary: "Run lines again and again while a command succeeds.",
+        usage: "WHILE <command> [args...]\n\
+                <lines>\n\
+                DONE\n\
+                Runs the command, then the lines up to DONE if it exited with status 0, and repeats\n\
+                until the command fails. DONE stands alone on its own line, and WHILE and IF blocks\n\
+                may be nested in each other. Ctrl+C stops the loop. At the prompt, the shell keeps\n\
+                reading lines until the closing DONE.",
+    },
+    BuiltinHelp {
+        name: "FOR",
+        summary: "Run lines once for each word of a list.",
+        usage: "FOR <name> IN [words...]\n\
+                DO\n\
+                <lines>\n\
+                DONE\n\
+                Expands the words as the arguments of a command are expanded, so FOR n IN {1..3}\n\
+                and FOR f IN $(ls) work, then sets the variable to each word in turn and runs the\n\
+                lines between DO and DONE. DO may also end the FOR line after a ';', and a whole\n\
+                loop fits on one line: FOR n IN 1 2; DO ECHO $n; DONE. An empty list runs nothing.\n\
+                Blocks may be nested, and Ctrl+C stops the loop.",
+    },
+    BuiltinHelp {
+        name: "FUNCTION",
+        summary: "Define, delete, or list functions.",
+        usage: "FUNCTION [name [{ lines }]]\n\
+                FUNCTION name { ECHO hello $1 } defines a function, run by typing its name like a\n\
+                command, with its arguments as $1, $2 and so on. The body may also span lines, from\n\
+                FUNCTION name { to a line holding only }. With only a name, deletes that function;\n\
+                with no arguments, lists them. A function cannot be redirected, piped or run in the\n\
+                background.",
+    },
+    BuiltinHelp {
+        name: "TEST",
+        summary: "Check a condition, for use with IF.",
+        usage: "TEST <expression>   or   [ <expression> ]\n\
+                Exits with status 0 if the expression holds and 1 if not. Expressions:\n\
+                -e path, -f file, -d directory: the path exists / is a file / is a directory\n\
+                -z string, -n string: the string is empty / not empty\n\
+                a = b, a != b: the strings are equal / different\n\
+                string: the string is not empty",
+    },
+    BuiltinHelp {
+        name: "[",
+        summary: "Check a condition; the same as TEST, closed by ].",
+        usage: "[ <expression> ]\n\
+                See HELP TEST.",
+    },
+    BuiltinHelp {
+        name: "TIME",
+        summary: "Run a command and report how long it took.",
+        usage: "TIME <command> [args...]\n\
+                Runs the command exactly as if it had been typed on its own, then prints the\n\
+                elapsed wall-clock time.",
+    },
+    BuiltinHelp {
+        name: "JOBS",
+        summary: "List background jobs.",
+        usage: "JOBS\n\
+                Lists the commands started in the background with a trailing &, with their job\n\
+                numbers, process IDs and whether they are still running.",
+    },
+    BuiltinHelp {
+        name: "KILL",
+        summary: "Send a signal to a job or process.",
+        usage: "KILL [-signal] <%job | pid>...\n\
+                Sends SIGTERM, or the given signal (a number such as -9 or a name such as -KILL or\n\
+                -SIGHUP), to each job number or process ID. On Windows only -9/-KILL of a job works.",
+    },
+    BuiltinHelp {
+        name: "FG",
+        summary: "Wait for a background job in the foreground.",
+        usage: "FG [%job]\n\
+                Waits for the job, the most recent one by default, to finish and sets $? to its exit\n\
+                status. Ctrl+C is passed on to the job while waiting.",
+    },
+    BuiltinHelp {
+        name: "WAIT",
+        summary: "Wait for background jobs to finish.",
+        usage: "WAIT [%job]\n\
+                Waits for every background job, or only the given one, to finish and sets $? to the\n\
+                exit status of the last job waited for. Ctrl+C stops waiting and leaves the jobs running.",
+    },
+    BuiltinHelp {
+        name: "DISOWN",
+        summary: "Stop tracking a background job without ending it.",
+        usage: "DISOWN [%job]\n\
+                Removes the job, the most recent one by default, from the job list and leaves it\n\
+                running. It is no longer listed, waited for, or ended when the shell exits.",
+    },
+    BuiltinHelp {
+        name: "TIMEOUT",
+        summary: "Run a command, killing it if it takes too long.",
+        usage: "TIMEOUT <seconds> <command> [args...]\n\
+                Runs an alias or program and kills it if it is still running after the given number\n\
+                of seconds (fractions allowed). Reports which happened; $? is 124 after a timeout.",
+    },
+    BuiltinHelp {
+        name: "SLEEP",
+        summary: "Pause for a number of seconds.",
+        usage: "SLEEP <seconds>\n\
+                Waits for the given number of seconds (fractions allowed, as in SLEEP 0.5) without\n\
+                running a program. Ctrl+C ends the wait early.",
+    },
+    BuiltinHelp {
+        name: "REPEAT",
+        summary: "Run a command several times.",
+        usage: "REPEAT [-k] <count> <command> [args...]\n\
+                Runs the command count times, stopping at the first run that fails unless -k is\n\
+                given to keep going. A count of 0 does nothing.",
+    },
+];
+
+/// A built-in command, whatever name it was invoked by.
+///
+/// Only names in `BUILTINS` can be made into a `BuiltinKind`, so dispatch on `name()` always finds a handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinKind(&'static str);
+
+impl BuiltinKind {
+    /// Finds the built-in command with this name in `BUILTINS`, matching case exactly.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::BuiltinKind;
+    ///
+    /// assert_eq!(BuiltinKind::from_name("STOP").map(|kind| kind.name()), Some("STOP"));
+    /// assert_eq!(BuiltinKind::from_name("stop"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<BuiltinKind> {
+        BUILTINS.iter().find(|builtin| builtin.name == name).map(|builtin| BuiltinKind(builtin.name))
+    }
+
+    /// The name the command is listed under in `BUILTINS`.
+    pub fn name(self) -> &'static str {
+        self.0
+    }
+}
+
+/// Maps every built-in command's own name to it, the keyword table a new shell starts with. `exit`, `quit`
+/// and `logout` are also names for `STOP`, and `alias` for `ALIAS`, as users of other shells expect.
+///
+/// # Examples
+/// ```
+/// use myshell::{default_keywords, BuiltinKind, BUILTINS};
+///
+/// let keywords = default_keywords();
+///
+/// assert_eq!(keywords.len(), BUILTINS.len() + 4);
+/// assert_eq!(keywords.get("ECHO"), BuiltinKind::from_name("ECHO").as_ref());
+/// assert_eq!(keywords.get("exit"), BuiltinKind::from_name("STOP").as_ref());
+/// ```
+pub fn default_keywords() -> HashMap<String, BuiltinKind> {
+    let mut keywords: HashMap<String, BuiltinKind> =
+        BUILTINS.iter().map(|builtin| (builtin.name.to_string(), BuiltinKind(builtin.name))).collect();
+    for name in ["exit", "quit", "logout"] {
+        keywords.insert(name.to_string(), BuiltinKind("STOP"));
+    }
+    keywords.insert("alias".to_string(), BuiltinKind("ALIAS"));
+    keywords
+}
+
+/// The default prompt template: the shell name followed by the terminator and a space.
+pub const DEFAULT_PROMPT_TEMPLATE: &str = "\\s\\> ";
+
+/// The default welcome banner: the shell name and version, and a pointer to `HELP`.
+pub const DEFAULT_BANNER: &str = "Welcome to \\s (myshell-rs \\v). Type HELP for a list of commands.";
+
+/// The default continuation prompt, shown while reading the rest of a line continued with a trailing
+/// backslash, an `IF` block, or a here-document.
+pub const CONTINUATION_PROMPT: &str = "> ";
+
+/// A user-defined alias.
+///
+/// In a settings file an alias with only a command is written as that command, as in files from before
+/// descriptions and usage counts existed, and one with either as a table holding all three.
+///
+/// # Examples
+/// ```
+/// use myshell::{read_settings_file, write_settings_file, Alias, Settings};
+///
+/// let path = std::env::temp_dir().join("myshell-alias-settings-example.toml");
+/// let mut settings = Settings::default();
+/// settings.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+/// settings.aliases.insert("gs".to_string(), Alias { description: Some("short status".to_string()), usage_count: 3, ..Alias::new("git status") });
+///
+/// write_settings_file(&path, &settings).unwrap();
+/// assert!(std::fs::read_to_string(&path).unwrap().contains("ll = \"ls -l\""));
+/// assert_eq!(read_settings_file(&path).unwrap(), settings);
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredAlias", into = "StoredAlias")]
+pub struct Alias {
+    /// The command line the alias stands for.
+    pub command: String,
+    /// What the alias is for, as given with `NEWNAME <name> <command> --desc <text>`.
+    pub description: Option<String>,
+    /// How many times the alias has been run, as listed by `MOSTUSED`.
+    pub usage_count: u32,
+}
+
+impl Alias {
+    /// Creates an alias for a command, without a description.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::Alias;
+    ///
+    /// let alias = Alias::new("ls -l");
+    /// assert_eq!(alias.command, "ls -l");
+    /// assert_eq!(alias.description, None);
+    /// assert_eq!(alias.usage_count, 0);
+    /// ```
+    pub fn new(command: impl Into<String>) -> Alias {
+        Alias { command: command.into(), description: None, usage_count: 0 }
+    }
+}
+
+/// The form an `Alias` takes in a settings file.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredAlias {
+    /// Just the command.
+    Command(String),
+    /// The command with its description and usage count.
+    Full {
+        command: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        usage_count: u32,
+    },
+}
+
+/// Lets an unused alias leave its usage count out of a settings file.
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+impl From<StoredAlias> for Alias {
+    fn from(stored: StoredAlias) -> Alias {
+        match stored {
+            StoredAlias::Command(command) => Alias::new(command),
+            StoredAlias::Full { command, description, usage_count } => Alias { command, description, usage_count },
+        }
+    }
+}
+
+impl From<Alias> for StoredAlias {
+    fn from(alias: Alias) -> StoredAlias {
+        match alias {
+            Alias { command, description: None, usage_count: 0 } => StoredAlias::Command(command),
+            Alias { command, description, usage_count } => StoredAlias::Full { command, description, usage_count },
+        }
+    }
+}
+
+/// Where the command line running came from in a script run by `SOURCE`, so that errors can name it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptLocation {
+    /// The script's file name, as given to `SOURCE`.
+    pub file: String,
+    /// The line number each of the script's command lines starts on, once continuations are joined.
+    pub line_numbers: Vec<usize>,
+    /// The index in `line_numbers` of the command line running.
+    pub index: usize,
+}
+
+/// The state of a running shell.
+///
+/// # Purpose
+/// This struct holds everything the built-in commands read or change: the prompt settings, the
+/// user-defined aliases, and the exit status of the last command.
+///
+/// # Examples
+/// ```
+/// use myshell::ShellState;
+///
+/// let state = ShellState::default();
+///
+/// assert_eq!(state.shellname, "My Shell");
+/// assert_eq!(state.terminator, ">");
+/// assert_eq!(state.max_aliases, 10);
+/// ```
+#[derive(Debug)]
+pub struct ShellState {
+    /// The name of the shell, shown by the `\s` prompt placeholder.
+    pub shellname: String,
+    /// The prompt terminator, shown by the `\>` prompt placeholder.
+    pub terminator: String,
+    /// The name of the color the terminator is shown in, such as `green`, or `None` for the terminal's own
+    /// color.
+    pub terminator_color: Option<String>,
+    /// The template rendered into the prompt before every line.
+    pub prompt_template: String,
+    /// The continuation prompt, shown as it is before every further line of a command.
+    pub prompt2: String,
+    /// User-defined aliases, mapping alias names to the commands they run.
+    pub aliases: HashMap<String, Alias>,
+    /// The maximum number of aliases read from a file.
+    pub max_aliases: usize,
+    /// Suffix aliases, mapping a file extension such as `py` to the command that runs such files, such as
+    /// `python`.
+    pub suffix_aliases: HashMap<String, String>,
+    /// Global aliases, mapping words such as `G` to the text that replaces them anywhere in a command line, such
+    /// as `| grep`.
+    pub global_aliases: HashMap<String, String>,
+    /// The exit status of the last command, shown by the `\$?` prompt placeholder.
+    pub last_status: i32,
+    /// Whether ANSI color sequences are kept in the rendered prompt.
+    pub color: bool,
+    /// Every command line entered this session, oldest first, as listed by `HISTORY`.
+    pub history: Vec<String>,
+    /// Shell variables set with `SET`, which are expanded in commands but not passed to child processes.
+    pub variables: HashMap<String, String>,
+    /// The environment passed to every child process, changed with `EXPORT` and `UNSET`. It starts as a
+    /// copy of the shell process's environment, which itself is never modified.
+    pub environment: HashMap<String, String>,
+    /// The positional parameters: the name of the running script as `$0`, followed by its arguments as `$1`,
+    /// `$2` and so on. Outside a script `$0` is the name of the shell and there are no arguments.
+    pub positional: Vec<String>,
+    /// User-defined functions, mapping each name to the lines of its body.
+    pub functions: HashMap<String, Vec<String>>,
+    /// How many function calls are running inside each other, to stop runaway recursion.
+    pub function_depth: usize,
+    /// The script and line running, while `SOURCE` runs a file; `None` for lines typed at the prompt.
+    pub location: Option<ScriptLocation>,
+    /// Directories saved by `PUSHD`, with the most recently pushed last.
+    pub dir_stack: Vec<PathBuf>,
+    /// The directory the shell was in before the last successful change of directory, used by `CD -`.
+    pub oldpwd: Option<PathBuf>,
+    /// The startup configuration file, `~/.myshellrc`, if a home directory is known.
+    pub config_path: Option<PathBuf>,
+    /// Whether aliases are loaded from `autosave_path` at startup and saved back there on exit. Off by default
+    /// so a library user never touches the real alias file by accident; the binary turns it on.
+    pub autosave: bool,
+    /// The file aliases are autosaved to. The binary points it at `~/.myshell_aliases` unless the configuration
+    /// says otherwise.
+    pub autosave_path: Option<PathBuf>,
+    /// Commands started in the background with a trailing `&`, oldest first.
+    pub jobs: Vec<Job>,
+    /// The file every command line is appended to, set with `SETLOG`; `None` when logging is off.
+    pub log_path: Option<PathBuf>,
+    /// Whether a failing command line stops a script, set with `SETOPT errexit`.
+    pub errexit: bool,
+    /// Whether commands are being read from a terminal, where `errexit` has no effect.
+    pub interactive: bool,
+    /// Whether each command is printed to standard error before it runs, set with `SETOPT xtrace`.
+    pub xtrace: bool,
+    /// Whether programs are shown instead of run, set with `SETOPT dryrun`. Built-in commands and functions
+    /// still run, so that the lines after them expand as they would.
+    pub dryrun: bool,
+    /// Whether built-in command names are matched in any case, set with `SETOPT case_insensitive_builtins`.
+    pub case_insensitive_builtins: bool,
+    /// Whether a command line that repeats the previous history entry is left out of the history, set with
+    /// `SETOPT hist_ignore_dups`.
+    pub hist_ignore_dups: bool,
+    /// Whether command lines typed with a leading space are left out of the history, set with
+    /// `SETOPT hist_ignore_space`.
+    pub hist_ignore_space: bool,
+    /// Whether built-in commands confirm the settings they change and the aliases they define, as in
+    /// "Shell name set to: ...". It is turned off with `SETOPT quiet on`, and is off while the shell runs a
+    /// script file.
+    pub verbose: bool,
+    /// The paths programs were found at on `PATH`, so that running one again skips the search. It is filled in
+    /// by `command_path` through a shared reference, and emptied by `HASH -r` and whenever `PATH` changes.
+    pub command_paths: RefCell<HashMap<String, PathBuf>>,
+    /// The names built-in commands can be invoked by. It starts as `default_keywords()`, and the `keywords`
+    /// table of a settings file adds to it, so that `exit = "STOP"` makes `exit` run `STOP`.
+    pub keywords: HashMap<String, BuiltinKind>,
+    /// The welcome message printed when an interactive session starts, rendered by `render_banner`. An empty
+    /// banner prints nothing.
+    pub banner: String,
+    /// The line printed when an interactive session ends, with `STOP` or at the end of input. It is empty, printing
+    /// nothing, unless the configuration sets one.
+    pub farewell: String,
+    /// Whether the last attempt to exit was held back because background jobs were running, so that the next
+    /// one goes ahead (see `confirm_exit`).
+    pub exit_warned: bool,
+    /// The status `STOP` asked to exit with, once it has been allowed to. The commands being run stop and the
+    /// caller ends the shell with `shutdown`.
+    pub exit_status: Option<i32>,
+}
+
+impl Default for ShellState {
+    fn default() -> Self {
+        ShellState {
+            shellname: "My Shell".to_string(), // Default shell name
+            terminator: ">".to_string(), // Default terminator
+            terminator_color: None,
+            prompt_template: DEFAULT_PROMPT_TEMPLATE.to_string(),
+            prompt2: CONTINUATION_PROMPT.to_string(),
+            aliases: HashMap::new(),
+            max_aliases: 10,
+            suffix_aliases: HashMap::new(),
+            global_aliases: HashMap::new(),
+            last_status: 0,
+            color: colors_supported(),
+            history: Vec::new(),
+            variables: HashMap::new(),
+            environment: env::vars().collect(),
+            positional: vec![env!("CARGO_PKG_NAME").to_string()],
+            functions: HashMap::new(),
+            function_depth: 0,
+            location: None,
+            dir_stack: Vec::new(),
+            oldpwd: None,
+            config_path: default_config_path(),
+            autosave: false,
+            autosave_path: None,
+            jobs: Vec::new(),
+            log_path: None,
+            errexit: false,
+            interactive: false,
+            xtrace: false,
+            dryrun: false,
+            case_insensitive_builtins: false,
+            hist_ignore_dups: false,
+            hist_ignore_space: false,
+            verbose: true,
+            command_paths: RefCell::new(HashMap::new()),
+            keywords: default_keywords(),
+            banner: DEFAULT_BANNER.to_string(),
+            farewell: String::new(),
+            exit_warned: false,
+            exit_status: None,
+        }
+    }
+}
+
+impl ShellState {
+    /// Names the script line running, as `file:line: `, for the start of an error message. It is empty for
+    /// lines typed at the prompt.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{ScriptLocation, ShellState};
+    ///
+    /// let mut state = ShellState::default();
+    /// assert_eq!(state.error_prefix(), "");
+    ///
+    /// state.location = Some(ScriptLocation { file: "setup.mysh".to_string(), line_numbers: vec![1, 3, 12], index: 2 });
+    /// assert_eq!(state.error_prefix(), "setup.mysh:12: ");
+    /// ```
+    pub fn error_prefix(&self) -> String {
+        match &self.location {
+            Some(location) => match location.line_numbers.get(location.index) {
+                Some(line) => format!("{}:{}: ", location.file, line),
+                None => format!("{}: ", location.file),
+            },
+            None => String::new(),
+        }
+    }
+
+    /// Describes the shell's configuration, as printed by `STATUS`.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::ShellState;
+    ///
+    /// let state = ShellState::default();
+    /// let report = state.status_report();
+    ///
+    /// assert!(report.contains("Shell name:      My Shell\n"));
+    /// assert!(report.contains("Aliases:         0 of 10\n"));
+    /// assert!(report.contains("Last status:     0\n"));
+    /// ```
+    pub fn status_report(&self) -> String {
+        format!(
+            "Shell name:      {}\n\
+             Terminator:      '{}'\n\
+             Prompt template: '{}'\n\
+             Prompt 2:        '{}'\n\
+             Aliases:         {} of {}\n\
+             Last status:     {}\n\
+             Background jobs: {}\n\
+             Exit on error:   {}\n\
+             Trace commands:  {}\n",
+            self.shellname,
+            self.terminator,
+            self.prompt_template,
+            self.prompt2,
+            self.aliases.len(),
+            self.max_aliases,
+            self.last_status,
+            self.jobs.len(),
+            if self.errexit { "on" } else { "off" },
+            if self.xtrace { "on" } else { "off" },
+        )
+    }
+
+    /// Describes how many aliases are defined out of `max_aliases` as `defined/limit`, as printed by
+    /// `COUNTALIASES`.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{Alias, ShellState};
+    ///
+    /// let mut state = ShellState::default();
+    /// state.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+    ///
+    /// assert_eq!(state.alias_usage(), "1/10");
+    /// ```
+    pub fn alias_usage(&self) -> String {
+        format!("{}/{}", self.aliases.len(), self.max_aliases)
+    }
+
+    /// Counts a run of the command `name` towards its alias's `usage_count`, if it is an alias.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{Alias, ShellState};
+    ///
+    /// let mut state = ShellState::default();
+    /// state.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+    ///
+    /// state.count_alias_use("ll");
+    /// state.count_alias_use("ls");
+    /// assert_eq!(state.aliases["ll"].usage_count, 1);
+    /// ```
+    pub fn count_alias_use(&mut self, name: &str) {
+        if let Some(alias) = self.aliases.get_mut(name) {
+            alias.usage_count = alias.usage_count.saturating_add(1);
+        }
+    }
+
+    /// Tells whether a command line, as typed, belongs in the history: it is not blank, it does not start with
+    /// a space while `hist_ignore_space` is on, and it does not repeat the last entry while `hist_ignore_dups`
+    /// is on.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::ShellState;
+    ///
+    /// let mut state = ShellState { hist_ignore_dups: true, hist_ignore_space: true, ..ShellState::default() };
+    /// state.history.push("ls".to_string());
+    ///
+    /// assert!(state.keeps_in_history("pwd"));
+    /// assert!(!state.keeps_in_history("ls "));
+    /// assert!(!state.keeps_in_history(" pwd"));
+    /// assert!(!state.keeps_in_history("   "));
+    /// ```
+    pub fn keeps_in_history(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        let space_prefixed = self.hist_ignore_space && line.starts_with(' ');
+        let repeated = self.hist_ignore_dups && self.history.last().is_some_and(|last| last == trimmed);
+        !trimmed.is_empty() && !space_prefixed && !repeated
+    }
+
+    /// Adds a command line to the history, trimmed, if `keeps_in_history` allows it.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::ShellState;
+    ///
+    /// let mut state = ShellState { hist_ignore_dups: true, ..ShellState::default() };
+    /// for line in ["ls", "ls", "pwd", "ls"] {
+    ///     state.record_history(line);
+    /// }
+    ///
+    /// assert_eq!(state.history, vec!["ls", "pwd", "ls"]);
+    /// ```
+    pub fn record_history(&mut self, line: &str) {
+        if self.keeps_in_history(line) {
+            self.history.push(line.trim().to_string());
+        }
+    }
+
+    /// Finds the command a suffix alias runs a file with: the file's extension must have a suffix alias, and
+    /// the name must be neither an alias nor an executable program.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::ShellState;
+    ///
+    /// let mut state = ShellState::default();
+    /// state.suffix_aliases.insert("py".to_string(), "python".to_string());
+    ///
+    /// assert_eq!(state.suffix_alias_for("./script.py").as_deref(), Some("python"));
+    /// assert_eq!(state.suffix_alias_for("./script.rb"), None);
+    /// assert_eq!(state.suffix_alias_for("notes"), None);
+    /// ```
+    pub fn suffix_alias_for(&self, name: &str) -> Option<String> {
+        let extension = Path::new(name).extension()?.to_str()?;
+        let handler = self.suffix_aliases.get(extension)?;
+        if self.aliases.contains_key(name) || self.command_path(name).is_some() {
+            return None;
+        }
+        Some(handler.clone())
+    }
+
+    /// Resolves the command names of a parsed line: names from the keyword table become built-in commands (see
+    /// `Command::resolve_builtins`), and files with a suffix alias are given to their command (see
+    /// `Command::resolve_suffix_aliases`).
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{parse, tokenize, Command, ShellState};
+    ///
+    /// let mut state = ShellState::default();
+    /// state.suffix_aliases.insert("py".to_string(), "python".to_string());
+    ///
+    /// let command = state.resolve(parse(&tokenize("quit").unwrap()).unwrap());
+    /// assert!(matches!(command, Command::Builtin { ref name, .. } if name == "STOP"));
+    ///
+    /// let command = state.resolve(parse(&tokenize("./script.py").unwrap()).unwrap());
+    /// assert!(matches!(command, Command::External { ref name, .. } if name == "python"));
+    /// ```
+    pub fn resolve(&self, command: Command) -> Command {
+        command
+            .resolve_builtins(&|name| self.builtin_named(name))
+            .resolve_suffix_aliases(&|name| self.suffix_alias_for(name))
+    }
+
+    /// Finds the program a command name runs, remembering the result in `command_paths`.
+    ///
+    /// A name containing a path separator is checked as it is, without searching `PATH` or remembering it. A
+    /// remembered path is used again without searching `PATH`, as long as it still exists.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::ShellState;
+    ///
+    /// let state = ShellState::default();
+    /// let sh = state.command_path("sh").unwrap();
+    ///
+    /// assert!(state.command_paths.borrow().contains_key("sh"));
+    /// assert_eq!(state.command_path("sh"), Some(sh));
+    /// assert_eq!(state.command_path("no-such-command-anywhere"), None);
+    /// ```
+    pub fn command_path(&self, name: &str) -> Option<PathBuf> {
+        if name.contains(std::path::is_separator) {
+            return find_in_path(name, None);
+        }
+        if let Some(path) = self.command_paths.borrow().get(name).filter(|path| is_executable(path)) {
+            return Some(path.clone());
+        }
+
+        let path = find_in_path(name, self.environment.get("PATH").map(String::as_str));
+        match &path {
+            Some(path) => self.command_paths.borrow_mut().insert(name.to_string(), path.clone()),
+            None => self.command_paths.borrow_mut().remove(name),
+        };
+        path
+    }
+
+    /// Empties the command history, returning how many entries were removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::ShellState;
+    ///
+    /// let mut state = ShellState::default();
+    /// state.record_history("ls");
+    ///
+    /// assert_eq!(state.forget_history(), 1);
+    /// assert!(state.history.is_empty());
+    /// ```
+    pub fn forget_history(&mut self) -> usize {
+        let removed = self.history.len();
+        self.history.clear();
+        removed
+    }
+
+    /// Tells whether the commands being run must stop: `STOP` has asked the shell to exit, or `errexit` is on,
+    /// the shell is not interactive, and the last command line failed.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::ShellState;
+    ///
+    /// let mut state = ShellState { errexit: true, last_status: 1, ..ShellState::default() };
+    /// assert!(state.exits_on_error());
+    ///
+    /// state.interactive = true;
+    /// assert!(!state.exits_on_error());
+    ///
+    /// state.exit_status = Some(0);
+    /// assert!(state.exits_on_error());
+    /// ```
+    pub fn exits_on_error(&self) -> bool {
+        self.exit_status.is_some() || (self.errexit && !self.interactive && self.last_status != 0)
+    }
+
+    /// Looks up the built-in command a name invokes in the keyword table, ignoring case if
+    /// `case_insensitive_builtins` is on. An exact match is preferred.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{BuiltinKind, ShellState};
+    ///
+    /// let mut state = ShellState::default();
+    /// state.keywords.insert("prompt".to_string(), BuiltinKind::from_name("SETSHELLNAME").unwrap());
+    ///
+    /// assert_eq!(state.builtin_named("prompt").map(|kind| kind.name()), Some("SETSHELLNAME"));
+    /// assert_eq!(state.builtin_named("echo"), None);
+    ///
+    /// state.case_insensitive_builtins = true;
+    /// assert_eq!(state.builtin_named("echo").map(|kind| kind.name()), Some("ECHO"));
+    /// ```
+    pub fn builtin_named(&self, name: &str) -> Option<BuiltinKind> {
+        if let Some(&kind) = self.keywords.get(name) {
+            return Some(kind);
+        }
+        if !self.case_insensitive_builtins {
+            return None;
+        }
+        self.keywords.iter().find(|(keyword, _)| keyword.eq_ignore_ascii_case(name)).map(|(_, &kind)| kind)
+    }
+}
+
+/// Renders the prompt from the shell's prompt template.
+///
+/// # Purpose
+/// This function expands the placeholders in `state.prompt_template` each time the prompt is drawn. The
+/// supported placeholders are:
+///
+/// - `\s`: the shell name
+/// - `\>`: the terminator, in `state.terminator_color` if one is set
+/// - `\w`: the current working directory, with the home directory shown as `~`
+/// - `\u`: the current user name
+/// - `\h`: the host name, up to the first `.`
+/// - `\g`: the current git branch, or nothing outside a git repository (see `git_branch`)
+/// - `\t`: the current local time as `HH:MM:SS`
+/// - `\d`: the current local date as weekday, month and day, such as `Tue May 26`
+/// - `\$?` or `\?`: the exit status of the last command
+/// - `\G`: the ANSI sequence for green if the last command succeeded, and nothing otherwise
+/// - `\R`: the ANSI sequence for red if the last command failed, and nothing otherwise; `\G\R$\e[0m` shows a `$`
+///   in green or red
+/// - `\e`: the escape character, to start ANSI color sequences such as `\e[32m`
+/// - `\\`: a literal backslash
+///
+/// Any other backslash sequence is kept as typed. When `state.color` is off, every escape sequence is
+/// stripped from the result so dumb terminals only see plain text.
+///
+/// # Parameters
+/// - `state`: A reference to the `ShellState` whose prompt should be rendered.
+///
+/// # Returns
+/// This function returns the rendered prompt as a `String`.
+///
+/// # Examples
+/// ```
+/// use myshell::{render_prompt, ShellState};
+///
+/// let mut state = ShellState::default();
+/// assert_eq!(render_prompt(&state), "My Shell> ");
+///
+/// state.prompt_template = "[\\$?] \\s\\> ".to_string();
+/// state.last_status = 1;
+/// assert_eq!(render_prompt(&state), "[1] My Shell> ");
+///
+/// state.prompt_template = "\\e[32m\\s\\e[0m\\> ".to_string();
+/// state.color = true;
+/// assert_eq!(render_prompt(&state), "\x1b[32mMy Shell\x1b[0m> ");
+/// state.color = false;
+/// assert_eq!(render_prompt(&state), "My Shell> ");
+/// ```
+pub fn render_prompt(state: &ShellState) -> String {
+    render_prompt_at(state, Local::now())
+}
+
+/// Renders the prompt as `render_prompt` does, with `now` as the current time.
+///
+/// # Purpose
+/// This function does the work of `render_prompt`, taking the time shown by the `\t` and `\d` placeholders as
+/// a parameter so that a prompt can be rendered for a fixed clock. `render_prompt` passes the time at which it
+/// is called, so the clock is read afresh on every prompt.
+///
+/// # Parameters
+/// - `state`: A reference to the `ShellState` whose prompt should be rendered.
+/// - `now`: A `DateTime<Local>` representing the time to show.
+///
+/// # Returns
+/// This function returns the rendered prompt as a `String`.
+///
+/// # Examples
+/// ```
+/// use chrono::{Local, TimeZone};
+/// use myshell::{render_prompt_at, ShellState};
+///
+/// let state = ShellState { prompt_template: "\\d \\t\\> ".to_string(), ..ShellState::default() };
+/// let now = Local.with_ymd_and_hms(2024, 5, 28, 9, 5, 7).unwrap();
+///
+/// assert_eq!(render_prompt_at(&state, now), "Tue May 28 09:05:07> ");
+/// ```
+pub fn render_prompt_at(state: &ShellState, now: DateTime<Local>) -> String {
+    let mut prompt = String::new();
+    let mut chars = state.prompt_template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            prompt.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => prompt.push_str(&state.shellname),
+            Some('>') => match state.terminator_color.as_deref().and_then(ansi_color_code) {
+                Some(code) => prompt.push_str(&format!("\x1b[{}m{}\x1b[0m", code, state.terminator)),
+                None => prompt.push_str(&state.terminator),
+            },
+            Some('w') => prompt.push_str(&current_dir_display()),
+            Some('u') => prompt.push_str(&user_name()),
+            Some('h') => prompt.push_str(&host_name()),
+            Some('g') => prompt.push_str(&git_branch(&state.environment)),
+            Some('t') => prompt.push_str(&now.format("%H:%M:%S").to_string()),
+            Some('d') => prompt.push_str(&now.format("%a %b %d").to_string()),
+            Some('$') if chars.peek() == Some(&'?') => {
+                chars.next();
+                prompt.push_str(&state.last_status.to_string());
+            }
+            Some('?') => prompt.push_str(&state.last_status.to_string()),
+            Some('G') if state.last_status == 0 => prompt.push_str("\x1b[32m"),
+            Some('R') if state.last_status != 0 => prompt.push_str("\x1b[31m"),
+            Some('G' | 'R') => {}
+            Some('e') => prompt.push('\x1b'),
+            Some('\\') => prompt.push('\\'),
+            Some(other) => {
+                prompt.push('\\');
+                prompt.push(other);
+            }
+            None => prompt.push('\\'),
+        }
+    }
+
+    if state.color {
+        prompt
+    } else {
+        strip_ansi(&prompt)
+    }
+}
+
+/// Renders the welcome banner.
+///
+/// # Purpose
+/// This function expands the placeholders in `state.banner`: `\s` is the shell name, `\v` the version of
+/// myshell-rs, `\c` the startup configuration file if it exists (or `none`), and `\\` a literal backslash.
+/// Any other backslash sequence is kept as typed.
+///
+/// # Parameters
+/// - `state`: A reference to the `ShellState` holding the banner.
+///
+/// # Returns
+/// This function returns the banner as a `String`, which is empty if the banner is turned off.
+///
+/// # Examples
+/// ```
+/// use myshell::{render_banner, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.banner = "\\s \\v".to_string();
+/// assert_eq!(render_banner(&state), format!("My Shell {}", env!("CARGO_PKG_VERSION")));
+///
+/// state.banner.clear();
+/// assert_eq!(render_banner(&state), "");
+/// ```
+pub fn render_banner(state: &ShellState) -> String {
+    let mut banner = String::new();
+    let mut chars = state.banner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            banner.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => banner.push_str(&state.shellname),
+            Some('v') => banner.push_str(env!("CARGO_PKG_VERSION")),
+            Some('c') => match state.config_path.as_ref().filter(|path| path.exists()) {
+                Some(path) => banner.push_str(&path.display().to_string()),
+                None => banner.push_str("none"),
+            },
+            Some('\\') => banner.push('\\'),
+            Some(other) => {
+                banner.push('\\');
+                banner.push(other);
+            }
+            None => banner.push('\\'),
+        }
+    }
+
+    banner
+}
+
+/// Prints the welcome banner, unless it is empty.
+///
+/// # Purpose
+/// This function is called once when an interactive session starts, before the first prompt. It prints
+/// `render_banner(state)` on a line of its own, or nothing if the banner has been set to an empty string.
+///
+/// # Parameters
+/// - `state`: A reference to the `ShellState` holding the banner.
+///
+/// # Examples
+/// ```
+/// use myshell::{print_banner, ShellState};
+///
+/// print_banner(&ShellState::default());
+/// ```
+pub fn print_banner(state: &ShellState) {
+    let banner = render_banner(state);
+    if !banner.is_empty() {
+        println!("{}", banner);
+    }
+}
+
+/// Looks up the ANSI foreground color code for a color name.
+///
+/// # Purpose
+/// This function maps the names of the eight standard terminal colors, `black`, `red`, `green`, `yellow`,
+/// `blue`, `magenta`, `cyan` and `white`, in any case, to the codes that select them in an ANSI sequence such
+/// as `\x1b[32m`.
+///
+/// # Parameters
+/// - `name`: A `&str` representing the color name.
+///
+/// # Returns
+/// This function returns the color code as an `Option<u8>`, or `None` if the name is not a known color.
+///
+/// # Examples
+/// ```
+/// use myshell::ansi_color_code;
+///
+/// assert_eq!(ansi_color_code("green"), Some(32));
+/// assert_eq!(ansi_color_code("Blue"), Some(34));
+/// assert_eq!(ansi_color_code("mauve"), None);
+/// ```
+pub fn ansi_color_code(name: &str) -> Option<u8> {
+    let colors = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    let index = colors.iter().position(|color| color.eq_ignore_ascii_case(name))?;
+    Some(30 + index as u8)
+}
+
+/// Decides whether the terminal should receive ANSI colors.
+///
+/// # Purpose
+/// This function follows the `NO_COLOR` convention: colors are disabled when `NO_COLOR` is set to a non-empty
+/// value. They are also disabled when `TERM` is `dumb`.
+///
+/// # Returns
+/// This function returns `true` if colors should be used, and `false` otherwise.
+///
+/// # Examples
+/// ```
+/// use myshell::colors_supported;
+///
+/// if !colors_supported() {
+///     println!("Colors are disabled.");
+/// }
+/// ```
+pub fn colors_supported() -> bool {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+    !no_color && !dumb
+}
+
+/// Removes ANSI escape sequences from text.
+///
+/// # Purpose
+/// This function strips control sequences introduced by the escape character, such as the color sequence
+/// `\x1b[32m`, leaving only the text that would be visible on a terminal.
+///
+/// # Parameters
+/// - `text`: A `&str` possibly containing escape sequences.
+///
+/// # Returns
+/// This function returns the text without escape sequences, as a `String`.
+///
+/// # Examples
+/// ```
+/// use myshell::strip_ansi;
+///
+/// assert_eq!(strip_ansi("\x1b[1;31mred\x1b[0m text"), "red text");
+/// ```
+pub fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+        } else if chars.peek() == Some(&'[') {
+            // A control sequence runs until its final byte in the range '@' to '~'.
+            chars.next();
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            chars.next(); // A two-character escape sequence.
+        }
+    }
+
+    plain
+}
+
+/// Measures how many columns text occupies on a terminal.
+///
+/// # Purpose
+/// This function counts the characters of `text` that are actually displayed, treating escape sequences
+/// as zero-width. Line editing relies on this to place the cursor correctly after a colored prompt.
+///
+/// # Parameters
+/// - `text`: A `&str` possibly containing escape sequences.
+///
+/// # Returns
+/// This function returns the number of visible columns as a `usize`.
+///
+/// # Examples
+/// ```
+/// use myshell::visible_width;
+///
+/// assert_eq!(visible_width("\x1b[32muser\x1b[0m:~$ "), 8);
+/// ```
+pub fn visible_width(text: &str) -> usize {
+    strip_ansi(text).chars().count()
+}
+
+/// Describes the current working directory for the prompt.
+///
+/// # Purpose
+/// This function returns the current working directory, replacing a leading home directory with `~`.
+///
+/// # Returns
+/// This function returns the directory as a `String`, or `?` if it cannot be determined.
+///
+/// # Examples
+/// ```
+/// use myshell::current_dir_display;
+///
+/// assert!(!current_dir_display().is_empty());
+/// ```
+pub fn current_dir_display() -> String {
+    match env::current_dir() {
+        Ok(cwd) => tilde_path(&cwd),
+        Err(_) => "?".to_string(),
+    }
+}
+
+/// Abbreviates a path for display.
+///
+/// # Purpose
+/// This function formats a path, replacing a leading home directory with `~` as the prompt and `DIRS` show it.
+///
+/// # Parameters
+/// - `path`: A reference to the `Path` to format.
+///
+/// # Returns
+/// This function returns the formatted path as a `String`.
+///
+/// # Examples
+/// ```
+/// use myshell::tilde_path;
+/// use std::path::Path;
+///
+/// assert_eq!(tilde_path(Path::new("/")), "/");
+/// ```
+pub fn tilde_path(path: &Path) -> String {
+    let home = home_dir();
+    match home.as_deref().and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Looks up the name of the current user.
+///
+/// # Purpose
+/// This function reads the user name from the `USER` environment variable, falling back to `USERNAME`
+/// as set on Windows.
+///
+/// # Returns
+/// This function returns the user name as a `String`, or an empty string if neither variable is set.
+///
+/// # Examples
+/// ```
+/// use myshell::user_name;
+///
+/// println!("Hello, {}!", user_name());
+/// ```
+pub fn user_name() -> String {
+    env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default()
+}
+
+/// The branch last found by `git_branch`, with the directory and `HEAD` modification time it was found for.
+static GIT_BRANCH: Mutex<Option<(PathBuf, Option<SystemTime>, String)>> = Mutex::new(None);
+
+/// Looks up the git branch of the current directory.
+///
+/// # Purpose
+/// This function runs `git rev-parse --abbrev-ref HEAD` with the shell's environment and returns the branch it
+/// prints, for the `\g` prompt placeholder. The answer is cached for the current directory and is only looked up
+/// again after changing directory or after the repository's `.git/HEAD` changes, as it does on `git checkout`,
+/// so drawing the prompt does not start `git` every time.
+///
+/// # Parameters
+/// - `environment`: A reference to the `HashMap<String, String>` environment `git` runs with.
+///
+/// # Returns
+/// This function returns the branch name as a `String`, or an empty string outside a git repository or if `git`
+/// is not installed.
+///
+/// # Examples
+/// ```
+/// use std::env;
+/// use myshell::git_branch;
+///
+/// let environment = env::vars().collect();
+/// println!("On branch '{}'", git_branch(&environment));
+/// ```
+pub fn git_branch(environment: &HashMap<String, String>) -> String {
+    let Ok(cwd) = env::current_dir() else {
+        return String::new();
+    };
+    let head_modified = cwd
+        .ancestors()
+        .map(|dir| dir.join(".git").join("HEAD"))
+        .find(|head| head.is_file())
+        .and_then(|head| fs::metadata(head).and_then(|metadata| metadata.modified()).ok());
+
+    let mut cache = GIT_BRANCH.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((dir, modified, branch)) = cache.as_ref() {
+        if *dir == cwd && *modified == head_modified {
+            return branch.clone();
+        }
+    }
+
+    let branch = match run_command_capture("git", &["rev-parse", "--abbrev-ref", "HEAD"], environment) {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        _ => String::new(),
+    };
+    *cache = Some((cwd, head_modified, branch.clone()));
+    branch
+}
+
+/// Looks up the short host name of this machine.
+///
+/// # Purpose
+/// This function reads the host name from the `HOSTNAME` or `COMPUTERNAME` environment variables, falling
+/// back to the kernel's host name files on Linux. Only the part before the first `.` is returned.
+///
+/// # Returns
+/// This function returns the host name as a `String`, or `localhost` if it cannot be determined.
+///
+/// # Examples
+/// ```
+/// use myshell::host_name;
+///
+/// assert!(!host_name().contains('.'));
+/// ```
+pub fn host_name() -> String {
+    let name = env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .unwrap_or_default();
+
+    match name.trim().split('.').next() {
+        Some(short) if !short.is_empty() => short.to_string(),
+        _ => "localhost".to_string(),
+    }
+}
+
+/// Prints a prompt and reads one line of user input.
+///
+/// # Purpose
+/// This function displays the prompt, flushes it, and reads the next line from standard input. Unlike a plain
+/// empty line, reaching the end of input (Ctrl+D, or a closed pipe) is reported as `None` so the caller can
+/// stop reading instead of looping forever.
+///
+/// # Parameters
+/// - `prompt`: A `&str` containing the prompt to display before reading.
+///
+/// # Returns
+/// This function returns an `io::Result<Option<String>>`. It returns `Ok(Some(line))` with the trailing newline
+/// removed when a line was read, `Ok(None)` at end of input, and an `Err` if writing or reading fails.
+///
+/// # Examples
+/// ```no_run
+/// use myshell::read_input;
+///
+/// if let Some(line) = read_input("My Shell> ").unwrap() {
+///     println!("You typed: {}", line);
+/// }
+/// ```
+pub fn read_input(prompt: &str) -> io::Result<Option<String>> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", prompt)?;
+    stdout.flush()?;
+
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None); // End of input.
+    }
+
+    let trimmed_len = line.trim_end_matches(&['\n', '\r'][..]).len();
+    line.truncate(trimmed_len);
+    Ok(Some(line))
+}
+
+/// Handles a SIGINT (Ctrl+C) delivered to the shell.
+///
+/// # Purpose
+/// This function is installed as the shell's SIGINT handler so that Ctrl+C never terminates the shell.
+/// At the prompt the line editor reads Ctrl+C as a key and discards the current line itself; the signal
+/// only arrives while a foreground child is running, and that child shares the terminal's process group,
+/// so it receives the signal too. The handler just records the interruption in the `INTERRUPTED` flag.
+///
+/// # Returns
+/// This function does not return any value.
+pub fn handle_interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Line-editing support for the interactive prompt.
+///
+/// # Purpose
+/// This struct is the line editor's helper. It completes the command word at the start of the line against
+/// the built-in commands and the alias names it holds, which the main loop refreshes before every prompt.
+/// Any later word is completed as a file path relative to the current working directory.
+#[derive(Default)]
+pub struct ShellHelper {
+    /// The alias names offered as command completions.
+    pub aliases: Vec<String>,
+    /// Completes file and directory names in arguments, appending a separator to directories and
+    /// escaping spaces.
+    filenames: FilenameCompleter,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before_cursor = &line[..pos];
+        let start = before_cursor.len() - before_cursor.trim_start().len();
+        let word = &before_cursor[start..];
+
+        if word.contains(char::is_whitespace) {
+            return self.filenames.complete(line, pos, ctx); // An argument: complete a path.
+        }
+
+        let candidates = complete_command(word, &self.aliases)
+            .into_iter()
+            .map(|name| Pair { display: name.clone(), replacement: name })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Lists the commands that complete a partially typed command word.
+///
+/// # Purpose
+/// This function returns every built-in command and alias whose name starts with `prefix`, sorted and
+/// without duplicates. The line editor completes a single match and lists all of them when there are several.
+///
+/// # Parameters
+/// - `prefix`: A `&str` representing the partially typed command.
+/// - `aliases`: A slice of `String` containing the alias names to consider.
+///
+/// # Returns
+/// This function returns the matching command names as a `Vec<String>`.
+///
+/// # Examples
+/// ```
+/// use myshell::complete_command;
+///
+/// let aliases = vec!["setup".to_string()];
+///
+/// assert_eq!(complete_command("SETS", &aliases), vec!["SETSHELLNAME"]);
+/// assert_eq!(complete_command("SETT", &aliases), vec!["SETTERMINATOR", "SETTINGS"]);
+/// assert_eq!(complete_command("se", &aliases), vec!["setup"]);
+/// ```
+pub fn complete_command(prefix: &str, aliases: &[String]) -> Vec<String> {
+    let mut matches: Vec<String> = BUILTINS
+        .iter()
+        .map(|builtin| builtin.name)
+        .chain(aliases.iter().map(String::as_str))
+        .filter(|name| name.starts_with(prefix))
+        .map(str::to_string)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// Matches and handles user input commands.
+///
+/// # Purpose
+/// This function is responsible for matching and handling user input commands. The words are parsed into
+/// a `Command` (see `parse`), which is then run with `execute`: built-in commands such as setting the shell
+/// name, changing the terminator, and managing aliases are handled by the shell, and anything else is run as
+/// an alias or external command. It records whether the command succeeded in `state.last_status`.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words.
+/// - `state`: A mutable reference to the `ShellState` the command reads and updates.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns `Ok(())` if the command is executed
+/// successfully and returns an `Err` containing an error message if any errors occur during execution.
+///
+/// # Examples
+/// ```
+/// use myshell::{match_inputs, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// let inputs = vec!["SETSHELLNAME", "Custom Shell Name"];
+///
+/// if let Err(e) = match_inputs(&inputs, &mut state) {
+///     eprintln!("Error: {}", e);
+/// }
+/// assert_eq!(state.shellname, "Custom Shell Name");
+/// ```
+pub fn match_inputs(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let tokens: Vec<String> = inputs.iter().map(|input| input.to_string()).collect();
+    match parse(&tokens) {
+        Ok(command) => {
+            let command = state.resolve(command);
+            execute(state, &command)
+        }
+        Err(e) => {
+            state.last_status = 2;
+            Err(e.into())
+        }
+    }
+}
+
+/// Runs a parsed command line.
+///
+/// # Purpose
+/// This function carries out a `Command` built by `parse`. Built-in commands are handled by the shell, and
+/// user-defined functions are run with `run_function` before any alias or program of the same name is looked
+/// for; aliases and external commands are run with `run_command`, with any redirections applied. The commands
+/// of a `Sequence` run in turn; `And` runs its second command only if the first succeeded, and `Or` only
+/// if it failed; a `Pipeline` is run with `run_pipeline`; and a `Background` command is started as a job
+/// with `start_job`. Errors from commands that are not the last to run are printed as they happen. The
+/// exit status of the last command run is left in `state.last_status`. With `state.dryrun` on, every
+/// program, pipeline, and background job is printed to standard output instead, in the words `trace_command`
+/// shows, and succeeds; so do the built-ins that start programs, `TIMEOUT` and `EDITCONFIG`. Other built-in
+/// commands and functions still run.
+///
+/// # Parameters
+/// - `state`: A mutable reference to the `ShellState` the commands read and update.
+/// - `command`: A reference to the `Command` to run.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the last command run is a
+/// built-in that fails, if a built-in's input is redirected or its output files cannot be opened, if a
+/// built-in is put in a pipeline or in the background, or if a pipeline cannot be started. A built-in whose
+/// output is redirected prints its own error to the redirected standard error instead (see
+/// `run_redirected_builtin`).
+///
+/// # Examples
+/// ```
+/// use myshell::{execute, parse, tokenize, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// execute(&mut state, &parse(&tokenize("SET a 1 ; SET b 2").unwrap()).unwrap()).unwrap();
+/// assert_eq!((state.variables["a"].as_str(), state.variables["b"].as_str()), ("1", "2"));
+///
+/// execute(&mut state, &parse(&tokenize("false && SET ran and || SET ran or").unwrap()).unwrap()).unwrap();
+/// assert_eq!(state.variables["ran"], "or");
+///
+/// execute(&mut state, &parse(&tokenize("echo hi | grep -q hi").unwrap()).unwrap()).unwrap();
+/// assert_eq!(state.last_status, 0);
+///
+/// let path = std::env::temp_dir().join("myshell-execute-example");
+/// let line = format!("ECHO hi > {}", path.display());
+/// execute(&mut state, &parse(&tokenize(&line).unwrap()).unwrap()).unwrap();
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), "hi\n");
+/// # std::fs::remove_file(path).unwrap();
+///
+/// assert!(execute(&mut state, &parse(&tokenize("READ name < in.txt").unwrap()).unwrap()).is_err());
+/// assert_eq!(state.last_status, 1);
+/// ```
+pub fn execute(state: &mut ShellState, command: &Command) -> Result<(), Box<dyn Error>> {
+    if state.exit_status.is_some() {
+        return Ok(()); // Nothing after `STOP` runs, not even the rest of its command line.
+    }
+    match command {
+        Command::Builtin { .. } | Command::External { .. } | Command::Redirect { .. } => {
+            let (simple, redirections) = split_redirections(command);
+            let result = match simple {
+                Command::Builtin { name, .. } if state.dryrun && STARTS_PROGRAMS.contains(&name.as_str()) => {
+                    trace_command(simple, state);
+                    print_dry_run(command, state);
+                    return Ok(());
+                }
+                Command::Builtin { name, .. }
+                    if redirections.is_some_and(|redirections| redirections.stdin.is_some() || redirections.stdin_text.is_some()) =>
+                {
+                    Err(format!("The input of the built-in command '{}' cannot be redirected", name).into())
+                }
+                Command::Builtin { name, args } => {
+                    trace_command(simple, state);
+                    return match redirections {
+                        Some(redirections) => run_redirected_builtin(name, args, redirections, state),
+                        None => run_builtin(name, args, state),
+                    };
+                }
+                Command::External { name, .. } if redirections.is_some() && state.functions.contains_key(name) => {
+                    Err(format!("Redirection is not supported for the function '{}'", name).into())
+                }
+                Command::External { name, args } if state.functions.contains_key(name) => {
+                    trace_command(simple, state);
+                    return run_function(name, &words(args), state);
+                }
+                Command::External { .. } if state.dryrun => {
+                    trace_command(simple, state);
+                    print_dry_run(command, state);
+                    return Ok(());
+                }
+                Command::External { name, args } => {
+                    trace_command(simple, state);
+                    state.count_alias_use(name);
+                    let redirections = redirections.cloned().unwrap_or_default();
+                    state.last_status = run_command(name, &words(args), &redirections, state);
+                    return Ok(());
+                }
+                _ => Err("Only a single command can be redirected".into()),
+            };
+            state.last_status = 1;
+            result
+        }
+        Command::Pipeline(_) if state.dryrun => {
+            print_dry_run(command, state);
+            Ok(())
+        }
+        Command::Pipeline(stages) => {
+            for stage in stages {
+                if let (Command::External { name, .. }, _) = split_redirections(stage) {
+                    state.count_alias_use(name);
+                }
+            }
+            let result = run_pipeline(stages, state);
+            state.last_status = *result.as_ref().unwrap_or(&1);
+            result.map(|_| ())
+        }
+        Command::Sequence(commands) => {
+            let Some((last, rest)) = commands.split_last() else {
+                return Ok(()); // An empty line does nothing.
+            };
+            for command in rest {
+                report(&state.error_prefix(), execute(state, command));
+            }
+            execute(state, last)
+        }
+        Command::And(left, right) => {
+            report(&state.error_prefix(), execute(state, left));
+            if state.last_status == 0 { execute(state, right) } else { Ok(()) }
+        }
+        Command::Or(left, right) => {
+            report(&state.error_prefix(), execute(state, left));
+            if state.last_status != 0 { execute(state, right) } else { Ok(()) }
+        }
+        Command::Background(inner) if state.dryrun && matches!(split_redirections(inner).0, Command::External { .. }) => {
+            trace_command(split_redirections(inner).0, state);
+            print_dry_run(command, state);
+            Ok(())
+        }
+        Command::Background(command) => {
+            let (simple, redirections) = split_redirections(command);
+            let result = match simple {
+                Command::External { name, args } => {
+                    trace_command(simple, state);
+                    state.count_alias_use(name);
+                    start_job(name, &words(args), &redirections.cloned().unwrap_or_default(), state)
+                }
+                Command::Builtin { name, .. } => Err(format!("The built-in command '{}' cannot run in the background", name).into()),
+                _ => Err("Only a single command can run in the background".into()),
+            };
+            state.last_status = i32::from(result.is_err());
+            result
+        }
+    }
+}
+
+/// Runs a built-in command with its output redirected.
+///
+/// # Purpose
+/// Built-in commands print straight to the shell's own standard output and error, so for the time the
+/// command runs those streams are pointed at the files its redirections name, and then put back. Programs the
+/// command starts, as `TIMEOUT` does, write to the files too. An error the command returns is printed while
+/// the streams are still redirected, as bash prints it, and the command counts as failed without it being
+/// handed back. On platforms other than Unix, built-in output cannot be redirected.
+///
+/// # Parameters
+/// - `name` and `args`: The built-in command and its arguments, as `run_builtin` takes them.
+/// - `redirections`: A reference to the `Redirections` of its standard output and error.
+/// - `state`: A mutable reference to the `ShellState` the command runs against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err`, without running the command, if
+/// a file cannot be opened or the streams cannot be redirected.
+#[cfg(unix)]
+fn run_redirected_builtin(name: &str, args: &[String], redirections: &Redirections, state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    use std::os::fd::AsRawFd;
+
+    // The null device is opened like any file, since the shell's streams need something to point at.
+    let open = |target: &OutputTarget| match target.open()? {
+        Some(file) => Ok(file),
+        None => File::options().write(true).open("/dev/null"),
+    };
+    let stdout = redirections.stdout.as_ref().map(open).transpose()?;
+    let stderr = match &stdout {
+        Some(file) if redirections.stderr_to_stdout => Some(file.try_clone()?),
+        _ => redirections.stderr.as_ref().map(open).transpose()?,
+    };
+
+    let mut saved = Vec::new();
+    for (fd, file) in [(libc::STDOUT_FILENO, &stdout), (libc::STDERR_FILENO, &stderr)] {
+        let Some(file) = file else { continue };
+        flush_standard_streams();
+        // SAFETY: `dup` and `dup2` only take descriptors, and every one passed is open: the standard streams
+        // and `file`, which outlives the redirection.
+        let copy = unsafe { libc::dup(fd) };
+        if copy >= 0 {
+            saved.push((fd, copy));
+        }
+        if copy < 0 || unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            let error = io::Error::last_os_error();
+            restore_standard_streams(saved);
+            return Err(error.into());
+        }
+    }
+
+    if let Err(e) = run_builtin(name, args, state) {
+        eprintln!("{}Error: {}", state.error_prefix(), e);
+    }
+    flush_standard_streams();
+    restore_standard_streams(saved);
+    Ok(())
+}
+
+/// Refuses to redirect a built-in command where the shell's streams cannot be pointed at files.
+#[cfg(not(unix))]
+fn run_redirected_builtin(name: &str, _: &[String], _: &Redirections, _: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    Err(format!("The output of the built-in command '{}' can only be redirected on Unix", name).into())
+}
+
+/// Writes out anything the shell's standard output and error are holding.
+#[cfg(unix)]
+fn flush_standard_streams() {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+}
+
+/// Puts back standard streams saved by `run_redirected_builtin`, closing the saved copies.
+#[cfg(unix)]
+fn restore_standard_streams(saved: Vec<(libc::c_int, libc::c_int)>) {
+    for (fd, copy) in saved {
+        // SAFETY: `copy` is a descriptor `dup` returned and nothing else has closed.
+        unsafe {
+            libc::dup2(copy, fd);
+            libc::close(copy);
+        }
+    }
+}
+
+/// The built-in commands that start programs themselves, which `dryrun` shows instead of running.
+const STARTS_PROGRAMS: [&str; 2] = ["TIMEOUT", "EDITCONFIG"];
+
+/// Prints what a simple command, pipeline, or background job would run when `dryrun` is on, in the words
+/// `xtrace` shows, and sets `$?` to 0 as if it had succeeded.
+fn print_dry_run(command: &Command, state: &mut ShellState) {
+    let describe = |command: &Command| {
+        let (simple, redirections) = split_redirections(command);
+        let mut line = command_words(simple, state).unwrap_or_default();
+        if let Some(redirections) = redirections {
+            line.push_str(&describe_redirections(redirections));
+        }
+        line
+    };
+    let line = match command {
+        Command::Pipeline(stages) => stages.iter().map(describe).collect::<Vec<_>>().join(" | "),
+        Command::Background(command) => format!("{} &", describe(command)),
+        command => describe(command),
+    };
+    println!("{}", line);
+    state.last_status = 0;
+}
+
+/// Writes redirections back as the operators that give them, each after a space.
+fn describe_redirections(redirections: &Redirections) -> String {
+    let operator = |target: &OutputTarget, plain: &str, append: &str| {
+        format!(" {} {}", if target.append { append } else { plain }, quote_word(&target.path))
+    };
+    let mut text = String::new();
+    if let Some(path) = &redirections.stdin {
+        text.push_str(&format!(" < {}", quote_word(path)));
+    }
+    if let Some(stdin_text) = &redirections.stdin_text {
+        text.push_str(&format!(" <<< {}", quote_word(stdin_text)));
+    }
+    match (&redirections.stdout, redirections.stderr_to_stdout) {
+        (Some(target), true) => text.push_str(&operator(target, "&>", "&>>")),
+        (Some(target), false) => text.push_str(&operator(target, ">", ">>")),
+        (None, _) => {}
+    }
+    if let Some(target) = &redirections.stderr {
+        text.push_str(&operator(target, "2>", "2>>"));
+    }
+    text
+}
+
+/// Prints the error of a command whose failure is not the end of the line, such as the left side of `&&`.
+fn report(prefix: &str, result: Result<(), Box<dyn Error>>) {
+    if let Err(e) = result {
+        eprintln!("{}Error: {}", prefix, e);
+    }
+}
+
+/// Prints a command about to run, when `xtrace` is on.
+///
+/// # Purpose
+/// This function writes a `Builtin` or `External` command to standard error as `+ ` followed by its words,
+/// which have already been expanded. An alias is shown as the command it stands for, followed by its arguments. Words that would not
+/// read back as themselves, such as ones with spaces or quotes, are shown in single quotes.
+///
+/// # Parameters
+/// - `command`: A reference to the `Command` about to run.
+/// - `state`: A reference to the `ShellState` holding the `xtrace` option and the aliases.
+///
+/// # Examples
+/// ```
+/// use myshell::{parse, tokenize, trace_command, ShellState};
+///
+/// let state = ShellState { xtrace: true, ..ShellState::default() };
+///
+/// trace_command(&parse(&tokenize("ECHO 'hello  there'").unwrap()).unwrap(), &state); // + ECHO 'hello  there'
+/// ```
+pub fn trace_command(command: &Command, state: &ShellState) {
+    if !state.xtrace {
+        return;
+    }
+    if let Some(line) = command_words(command, state) {
+        eprintln!("+ {}", line);
+    }
+}
+
+/// Writes a `Builtin` or `External` command as `trace_command` shows it, or returns `None` for any other command.
+fn command_words(command: &Command, state: &ShellState) -> Option<String> {
+    let (Command::Builtin { name, args } | Command::External { name, args }) = command else {
+        return None;
+    };
+
+    Some(match state.aliases.get(name) {
+        // An alias runs its command with the arguments it was given after it.
+        Some(alias_command) if matches!(command, Command::External { .. }) => {
+            std::iter::once(alias_command.command.clone()).chain(args.iter().map(|word| quote_word(word))).collect::<Vec<_>>().join(" ")
+        }
+        _ => std::iter::once(name).chain(args).map(|word| quote_word(word)).collect::<Vec<_>>().join(" "),
+    })
+}
+
+/// Quotes a word so that `tokenize` would read it back unchanged, leaving plain words alone.
+fn quote_word(word: &str) -> String {
+    let plain = !word.is_empty() && !word.chars().any(|c| c.is_whitespace() || "'\"\\$`".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Separates a `Redirect` into the command it wraps and its redirections.
+fn split_redirections(command: &Command) -> (&Command, Option<&Redirections>) {
+    match command {
+        Command::Redirect { command, redirections } => (command, Some(redirections)),
+        command => (command, None),
+    }
+}
+
+/// Borrows owned words as the `&str` slices the command handlers take.
+fn words(args: &[String]) -> Vec<&str> {
+    args.iter().map(String::as_str).collect()
+}
+
+/// Runs a pipeline of external commands.
+///
+/// # Purpose
+/// This function starts every stage of a pipeline at once, connecting each one's standard output to the next
+/// one's standard input, and waits for them all to finish. Each stage is an alias or external command and may
+/// have its own redirections, which take the place of the pipe for the stream they redirect; a stage whose
+/// input pipe has been redirected elsewhere reads from the null device.
+///
+/// # Parameters
+/// - `stages`: A slice of `Command` representing the stages, in order.
+/// - `state`: A reference to the `ShellState` holding the aliases and the environment for the programs.
+///
+/// # Returns
+/// This function returns a `Result<i32, Box<dyn Error>>` with the exit status of the last stage. It returns
+/// an `Err` if a stage is a built-in command, or if a stage cannot be started; the stages already started are
+/// waited for first.
+///
+/// # Examples
+/// ```
+/// use myshell::{parse, run_pipeline, tokenize, Command, ShellState};
+///
+/// let state = ShellState::default();
+/// let Command::Pipeline(stages) = parse(&tokenize("echo hello | grep -q nothing").unwrap()).unwrap() else {
+///     unreachable!()
+/// };
+///
+/// assert_eq!(run_pipeline(&stages, &state).unwrap(), 1);
+/// ```
+pub fn run_pipeline(stages: &[Command], state: &ShellState) -> Result<i32, Box<dyn Error>> {
+    let mut children: Vec<Child> = Vec::new();
+    let mut previous_output = None;
+
+    for (index, stage) in stages.iter().enumerate() {
+        let started = start_stage(stage, index, stages.len(), previous_output.take(), state);
+        match started {
+            Ok(mut child) => {
+                previous_output = child.stdout.take();
+                children.push(child);
+            }
+            Err(e) => {
+                for child in &mut children {
+                    let _ = child.wait();
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    let mut status = 0;
+    for child in &mut children {
+        status = exit_code(child.wait()?);
+    }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        println!(); // Start the next prompt on a fresh line after the echoed ^C.
+    }
+    Ok(status)
+}
+
+/// Starts one stage of a pipeline, reading from the previous stage's output if there is one.
+fn start_stage(stage: &Command, index: usize, count: usize, input: Option<process::ChildStdout>, state: &ShellState) -> Result<Child, Box<dyn Error>> {
+    let (simple, redirections) = split_redirections(stage);
+    let (name, args) = match simple {
+        Command::External { name, args } => (name, words(args)),
+        Command::Builtin { name, .. } => return Err(format!("The built-in command '{}' cannot be used in a pipeline", name).into()),
+        _ => return Err("A pipeline stage must be a single command".into()),
+    };
+    trace_command(simple, state);
+    let words: Vec<&str> = match state.aliases.get(name) {
+        Some(alias_command) => alias_command.command.split_whitespace().chain(args).collect(),
+        None => std::iter::once(name.as_str()).chain(args).collect(),
+    };
+
+    let Some((program, words)) = words.split_first() else {
+        return Err(format!("The alias '{}' has no command", name).into());
+    };
+    let mut process = process::Command::new(program_path(program, state));
+    process.args(words).env_clear().envs(&state.environment);
+    if index > 0 {
+        process.stdin(input.map_or_else(Stdio::null, Stdio::from));
+    }
+    if index + 1 < count {
+        process.stdout(Stdio::piped());
+    }
+    if let Some(redirections) = redirections {
+        redirections.apply(&mut process)?;
+    }
+    let mut child = spawn_process(program, &mut process)?;
+    if let Some(redirections) = redirections {
+        redirections.feed(&mut child);
+    }
+    Ok(child)
+}
+
+/// Runs a built-in command.
+fn run_builtin(name: &str, args: &[String], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let inputs: Vec<&str> = std::iter::once(name).chain(args.iter().map(String::as_str)).collect();
+    let inputs = &inputs[..];
+
+    let result = match inputs.first() {
+        Some(&"HELP") 
+            => { print_help(inputs); Ok(()) }
+        Some(&"STOP") 
+            => stop_shell(inputs, state),
+        Some(&"SETSHELLNAME") 
+            => set_shell_name(inputs, &mut state.shellname, state.verbose),
+        Some(&"SETTERMINATOR") if inputs.get(1) == Some(&"--color") 
+            => set_terminator_color(inputs, &mut state.terminator_color, state.verbose),
+        Some(&"SETTERMINATOR") 
+            => { set_terminator(inputs, &mut state.terminator, state.verbose); Ok(()) }
+        Some(&"SETPROMPT") 
+            => { set_prompt(inputs, &mut state.prompt_template, state.verbose); Ok(()) }
+        Some(&"SETPROMPT2") 
+            => { set_prompt2(inputs, &mut state.prompt2, state.verbose); Ok(()) }
+        Some(&"NEWNAME") 
+            => set_new_name(inputs, &mut state.aliases, state.verbose),
+        Some(&"ALIAS") 
+            => alias_command(inputs, &mut state.aliases, state.verbose),
+        Some(&"SAVESETTINGS") 
+            => save_settings(inputs, state),
+        Some(&"LOADSETTINGS") 
+            => load_settings(inputs, state),
+        Some(&"READNEWNAMES") 
+            => { read_new_names(inputs, &mut state.aliases, state.max_aliases, state.verbose); Ok(()) }
+        Some(&"LISTNEWNAMES") 
+            => { list_new_names(&state.aliases); Ok(()) }
+        Some(&"SEARCHALIAS") 
+            => search_aliases(inputs, &state.aliases),
+        Some(&"GLOBALALIAS") 
+            => set_global_alias(inputs, &mut state.global_aliases, state.verbose),
+        Some(&"SUFFIXALIAS") 
+            => set_suffix_alias(inputs, &mut state.suffix_aliases, state.verbose),
+        Some(&"COUNTALIASES") 
+            => { println!("{} aliases defined", state.alias_usage()); Ok(()) }
+        Some(&"MOSTUSED") 
+            => { print!("{}", format_most_used(&state.aliases)); Ok(()) }
+        Some(&"SAVENEWNAMES") 
+            => save_new_names(inputs, &state.aliases),
+        Some(&"EXPORTALIASES") 
+            => export_aliases(inputs, &state.aliases),
+        Some(&"IMPORTALIASES") 
+            => import_aliases(inputs, &mut state.aliases, state.max_aliases, state.verbose),
+        Some(&"RELOAD") 
+            => reload_config(state),
+        Some(&"EDITCONFIG") 
+            => edit_config(state),
+        Some(&"UNALIAS") 
+            => { unalias(inputs, &mut state.aliases, state.verbose); Ok(()) }
+        Some(&"ALIASCLEAR") 
+            => clear_aliases(&mut state.aliases, state.verbose),
+        Some(&"WHICH") 
+            => which(inputs, state),
+        Some(&"HASH") 
+            => hash_command(inputs, state),
+        Some(&"TYPE") 
+            => type_command(inputs, state),
+        Some(&"SHIFT") 
+            => shift_positional(inputs, &mut state.positional),
+        Some(&"SOURCE") 
+            => match source_file(inputs, state) {
+                Ok(()) => return Ok(()), // The script's last command has set `$?`.
+                Err(e) => Err(e),
+            },
+        Some(&keyword @ ("IF" | "THEN" | "ELSE" | "FI"))  
+            => Err(format!("'{}' may only start a line of an IF block", keyword).into()),
+        Some(&"FUNCTION") 
+            => Err("FUNCTION may only start a line".into()),
+        Some(&keyword @ ("WHILE" | "FOR" | "DONE"))  
+            => Err(format!("'{}' may only start a line of a WHILE or FOR block", keyword).into()),
+        Some(&"DO") 
+            => Err("'DO' may only start a line of a FOR block".into()),
+        Some(&("TEST" | "[")) 
+            => match test_condition(inputs) {
+                Ok(holds) => {
+                    state.last_status = i32::from(!holds); // A false condition is a status, not an error.
+                    return Ok(());
+                }
+                Err(e) => Err(e),
+            },
+        Some(&"TIME") 
+            => match time_command(inputs, state) {
+                Ok(()) => return Ok(()), // The timed command has set `$?`.
+                Err(e) => Err(e),
+            },
+        Some(&"REPEAT") 
+            => repeat_command(inputs, state),
+        Some(&"TIMEOUT") 
+            => match timeout_command(inputs, state) {
+                Ok(status) => {
+                    state.last_status = status;
+                    return Ok(());
+                }
+                Err(e) => Err(e),
+            },
+        Some(&"SLEEP") 
+            => sleep_command(inputs),
+        Some(&"JOBS") 
+            => { list_jobs(&mut state.jobs); Ok(()) },
+        Some(&"KILL") 
+            => kill_command(inputs, &mut state.jobs),
+        Some(&"FG") 
+            => match foreground_job(inputs, &mut state.jobs) {
+                Ok(status) => {
+                    state.last_status = status;
+                    return Ok(());
+                }
+                Err(e) => Err(e),
+            },
+        Some(&"WAIT") 
+            => match wait_jobs(inputs, &mut state.jobs) {
+                Ok(status) => {
+                    state.last_status = status;
+                    return Ok(());
+                }
+                Err(e) => Err(e),
+            },
+        Some(&"DISOWN") 
+            => disown_job(inputs, &mut state.jobs),
+        Some(&"RESET") 
+            => reset_settings(inputs, state),
+        Some(&("STATUS" | "SETTINGS")) 
+            => { print!("{}", state.status_report()); Ok(()) },
+        Some(&"SETLOG") 
+            => { set_log(inputs, &mut state.log_path); Ok(()) },
+        Some(&"SETOPT") 
+            => set_option(inputs, state),
+        Some(&"HISTORY") 
+            => print_history(inputs, &state.history),
+        Some(&"CLEARHISTORY") 
+            => clear_history(inputs, state),
+        Some(&"CLEAR") 
+            => clear_screen(),
+        Some(&"ECHO") 
+            => echo(inputs),
+        Some(&"SET") 
+            => set_variable(inputs, &mut state.variables),
+        Some(&"READ") 
+            => read_variable(inputs, &mut state.variables),
+        Some(&"EXPORT") 
+            => export_variables(inputs, state),
+        Some(&"UNSET") 
+            => unset_variables(inputs, state),
+        Some(&"CD") 
+            => change_directory(inputs, state),
+        Some(&"PUSHD") 
+            => push_directory(inputs, state),
+        Some(&"POPD") 
+            => pop_directory(state),
+        Some(&"DIRS") 
+            => { list_directories(&state.dir_stack); Ok(()) },
+        _ => Err(format!("'{}' is not a built-in command", name).into()),
+    };
+
+    state.last_status = i32::from(result.is_err());
+    result
+}
+
+/// Runs an alias or an external command.
+///
+/// # Purpose
+/// This function runs `command` as an alias if one is defined, or as an external program otherwise, printing
+/// any error that kept it from running. A program that runs and exits with a non-zero status is not an error:
+/// its status is simply returned for `$?`. An unknown command is reported as `myshell: command not found: name`, with a suggestion for the
+/// closest built-in or alias.
+///
+/// # Parameters
+/// - `command`: A `&str` representing the alias or program to run.
+/// - `args`: A slice of `&str` representing the arguments to pass to the program, after any the alias gives.
+/// - `redirections`: A reference to the `Redirections` to apply to the program's streams.
+/// - `state`: A reference to the `ShellState` holding the aliases and the environment for the program.
+///
+/// # Returns
+/// This function returns the command's exit status as an `i32`, or `1` if it could not be run.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_command, Redirections, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// assert_eq!(run_command("sh", &["-c", "exit 3"], &Redirections::default(), &state), 3);
+/// assert_eq!(run_command("no-such-command-anywhere", &[], &Redirections::default(), &state), 1);
+/// ```
+pub fn run_command(command: &str, args: &[&str], redirections: &Redirections, state: &ShellState) -> i32 {
+    if let Some(alias_command) = state.aliases.get(command) {
+        // Execute the alias command if it exists
+        // The alias's own arguments come first, then the ones it was run with.
+        let alias_args: Vec<&str> = alias_command.command.split_whitespace().chain(args.iter().copied()).collect();
+        let Some((program, alias_args)) = alias_args.split_first() else {
+            eprintln!("{}Error executing alias command: the alias '{}' has no command", state.error_prefix(), command);
+            return 1;
+        };
+        return execute_command(&program_path(program, state), alias_args, redirections, &state.environment).unwrap_or_else(|e| {
+            eprintln!("{}Error executing alias command: {}", state.error_prefix(), e);
+            1
+        });
+    }
+
+    execute_command(&program_path(command, state), args, redirections, &state.environment).unwrap_or_else(|e| {
+        if let Some(ShellError::CommandNotFound(_)) = e.downcast_ref::<ShellError>() {
+            eprintln!("{}{}", state.error_prefix(), e);
+            if let Some(suggestion) = suggest_command(command, &state.aliases) {
+                eprintln!("Did you mean '{}'?", suggestion);
+            }
+        } else {
+            eprintln!("{}Error executing command: {}", state.error_prefix(), e);
+        }
+        1
+    })
+}
+
+/// Names the program to start for a command: the path `ShellState::command_path` finds, or the name as typed.
+fn program_path(name: &str, state: &ShellState) -> String {
+    state.command_path(name).map_or_else(|| name.to_string(), |path| path.to_string_lossy().into_owned())
+}
+
+/// Runs one command line.
+///
+/// # Purpose
+/// This function takes a line through every stage the interactive loop does: it logs the line if `SETLOG`
+/// is on, expands it (see `expand_braces` and `expand_line`), splits it into words with `tokenize`, parses
+/// them with `parse`, and runs the result with `execute`, printing any error. The outcome is left in `state.last_status`.
+///
+/// # Parameters
+/// - `line`: A `&str` representing the command line to run.
+/// - `state`: A mutable reference to the `ShellState` the command runs against.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_line, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// run_line("SET greeting hello", &mut state);
+/// run_line("SET copy $greeting", &mut state);
+/// assert_eq!(state.variables["copy"], "hello");
+///
+/// run_line("SET quoted '$greeting' \"$greeting  there\"", &mut state);
+/// assert_eq!(state.variables["quoted"], "$greeting hello  there");
+///
+/// run_line("false", &mut state);
+/// assert_eq!(state.last_status, 1);
+/// ```
+pub fn run_line(line: &str, state: &mut ShellState) {
+    if let Some(path) = &state.log_path {
+        if !line.trim().is_empty() {
+            if let Err(e) = log_command(path, line) {
+                eprintln!("Warning: could not write to the command log {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    let line = expand_global_aliases(line, &state.global_aliases);
+    let line = match expand_line(&expand_braces(&line), state) {
+        Ok(line) => line,
+        Err(e) => {
+            eprintln!("{}Error: {}", state.error_prefix(), e);
+            state.last_status = 1;
+            return;
+        }
+    };
+
+    let command = match tokenize(&line).and_then(|tokens| parse(&tokens)) {
+        Ok(command) => state.resolve(command),
+        Err(e) => {
+            eprintln!("{}{}", state.error_prefix(), e);
+            state.last_status = 2;
+            return;
+        }
+    };
+
+    if let Err(e) = execute(state, &command) {
+        eprintln!("{}Error: {}", state.error_prefix(), e);
+    }
+}
+
+/// Runs a sequence of command lines, such as a script.
+///
+/// # Purpose
+/// This function runs each line in turn with `run_line`, except that an `IF` line and the lines up to its
+/// matching `FI` are run together as one conditional block (see `run_if_block`), and a `WHILE` or `FOR` line
+/// and the lines up to its matching `DONE` as one loop (see `run_while_block` and `run_for_block`). A
+/// `FUNCTION` line, with the lines up to its closing `}` if it spans several, is handled by
+/// `define_function` without being expanded. Pressing Ctrl+C stops the remaining lines from running, as does a failing line when `errexit` is on (see
+/// `ShellState::exits_on_error`). While a script runs, `state.location` is moved along to each line, so that
+/// errors name the line they came from.
+///
+/// # Parameters
+/// - `lines`: A slice of `String` representing the lines to run, with continuations already joined.
+/// - `state`: A mutable reference to the `ShellState` the lines run against.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_lines, ShellState};
+///
+/// let mut state = ShellState::default();
+/// let script: Vec<String> = ["IF false", "THEN", "SET branch then", "ELSE", "SET branch else", "FI"]
+///     .iter()
+///     .map(|line| line.to_string())
+///     .collect();
+///
+/// run_lines(&script, &mut state);
+/// assert_eq!(state.variables["branch"], "else");
+/// ```
+pub fn run_lines(lines: &[String], state: &mut ShellState) {
+    let start = state.location.as_ref().map_or(0, |location| location.index);
+    let mut index = 0;
+    while index < lines.len() && !INTERRUPTED.load(Ordering::SeqCst) {
+        if let Some(location) = &mut state.location {
+            location.index = start + index;
+        }
+        let keyword = lines[index].split_whitespace().next();
+        let Some(keyword @ ("IF" | "WHILE" | "FOR" | "FUNCTION")) = keyword else {
+            run_line(&lines[index], state);
+            index += 1;
+            if state.exits_on_error() {
+                break;
+            }
+            continue;
+        };
+
+        // Find the FI or DONE that closes this block, skipping over nested blocks.
+        let end = (index..lines.len()).find(|&end| block_depth(&lines[index..=end]) == 0);
+        let result = match (end, keyword) {
+            (Some(end), "IF") => run_if_block(&lines[index..=end], state),
+            (Some(end), "WHILE") => run_while_block(&lines[index..=end], state),
+            (Some(end), "FUNCTION") => define_function(&lines[index..=end], &mut state.functions, &state.keywords),
+            (Some(end), _) => run_for_block(&lines[index..=end], state),
+            (None, "IF") => Err("IF without a matching FI".into()),
+            (None, "FUNCTION") => Err("FUNCTION without a closing '}'".into()),
+            (None, keyword) => Err(format!("{} without a matching DONE", keyword).into()),
+        };
+        if let Err(e) = result {
+            eprintln!("{}Error: {}", state.error_prefix(), e);
+            state.last_status = 1;
+        }
+        index = end.map_or(lines.len(), |end| end + 1);
+        if state.exits_on_error() {
+            break;
+        }
+    }
+}
+
+/// Runs the shell's read-dispatch loop.
+///
+/// # Purpose
+/// This function is the body of the interactive shell, independent of where its lines come from. Before each
+/// command it clears any earlier Ctrl+C, reports finished background jobs (see `reap_jobs`), and asks
+/// `read_line` for a line with the rendered prompt. Blank lines are skipped, every other line is added to the
+/// history, and an `IF`, `WHILE` or `FOR` block is read in full, with `state.prompt2`, before any of it runs with
+/// `run_lines`. The body of a here-document is read the same way, straight after the line that starts it
+/// (see `read_here_documents`). The loop ends when `read_line` returns `false` without an interruption, unless `confirm_exit`
+/// holds it back for running jobs, after `STOP`, or when a line fails with `errexit` on; returning `false` after Ctrl+C has
+/// set `INTERRUPTED` just discards the line being read. Every line is read into the same buffer, so reading a
+/// line does not allocate once the buffer has grown to fit the longest one.
+///
+/// # Parameters
+/// - `state`: A mutable reference to the `ShellState` the commands run against.
+/// - `read_line`: A closure given the state, the prompt to show, and an empty buffer to put the next line in,
+///   without its newline. It returns `true` if it read a line and `false` if there was none.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if `read_line` does.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_loop, ShellState};
+///
+/// let mut state = ShellState::default();
+/// let mut lines = vec!["SET greeting hello", "", "ECHO $greeting"].into_iter();
+/// let mut prompts = Vec::new();
+///
+/// run_loop(&mut state, |_, prompt, buffer| {
+///     prompts.push(prompt.to_string());
+///     Ok(lines.next().map(|line| buffer.push_str(line)).is_some())
+/// })
+/// .unwrap();
+///
+/// assert_eq!(state.history, vec!["SET greeting hello", "ECHO $greeting"]);
+/// assert_eq!(prompts.len(), 4); // One more for the end of input.
+/// ```
+pub fn run_loop<F>(state: &mut ShellState, mut read_line: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&ShellState, &str, &mut String) -> Result<bool, Box<dyn Error>>,
+{
+    let mut input = String::new();
+    let mut block: Vec<String> = Vec::new();
+
+    'shell: loop {
+        INTERRUPTED.store(false, Ordering::SeqCst); // Any earlier Ctrl+C has been dealt with.
+        reap_jobs(&mut state.jobs);
+
+        input.clear();
+        match read_line(state, &render_prompt(state), &mut input)? {
+            true => {}
+            false if INTERRUPTED.load(Ordering::SeqCst) => continue, // Ctrl+C discards the line.
+            false if confirm_exit(state) => return Ok(()), // Ctrl+D or the end of piped input.
+            false => continue,
+        }
+        if input.trim().is_empty() {
+            continue; // A blank line does nothing, not even reset `$?`.
+        }
+        if !gather_here_documents(state, &mut read_line, &mut input)? {
+            continue;
+        }
+        let recorded = state.keeps_in_history(&input);
+        state.record_history(&input);
+
+        // An IF, WHILE or FOR block is read in full, up to its closing FI or DONE, before any of it runs.
+        block.clear();
+        block.push(input.trim().to_string());
+        while block_depth(&block) > 0 {
+            input.clear();
+            match read_line(state, &state.prompt2, &mut input)? {
+                true if !gather_here_documents(state, &mut read_line, &mut input)? => continue 'shell,
+                true => {
+                    // The rest of a block goes with its first line, indented or not.
+                    let line = input.trim().to_string();
+                    if recorded && !line.is_empty() {
+                        state.history.push(line.clone());
+                    }
+                    block.push(line);
+                }
+                false if INTERRUPTED.load(Ordering::SeqCst) => continue 'shell,
+                false => {
+                    eprintln!("Error: unexpected end of input in an IF, WHILE, FOR or FUNCTION block");
+                    return Ok(());
+                }
+            }
+        }
+        // A warning about running jobs only lets the very next attempt to exit through.
+        let was_warned = state.exit_warned;
+        run_lines(&block, state);
+        if was_warned {
+            state.exit_warned = false;
+        }
+        if state.exits_on_error() {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads the bodies of any here-documents started on `input`, rewriting it in place, and returns `false` if
+/// Ctrl+C abandoned the line.
+fn gather_here_documents<F>(state: &ShellState, read_line: &mut F, input: &mut String) -> Result<bool, Box<dyn Error>>
+where
+    F: FnMut(&ShellState, &str, &mut String) -> Result<bool, Box<dyn Error>>,
+{
+    if HereDocument::find(input).is_none() {
+        return Ok(true);
+    }
+    let next_line = || -> Result<Option<String>, Box<dyn Error>> {
+        let mut line = String::new();
+        match read_line(state, &state.prompt2, &mut line)? {
+            true => Ok(Some(line)),
+            false if INTERRUPTED.load(Ordering::SeqCst) => Err("interrupted".into()),
+            false => Ok(None),
+        }
+    };
+    match read_here_documents(input, next_line) {
+        Ok(line) => *input = line,
+        Err(_) if INTERRUPTED.load(Ordering::SeqCst) => return Ok(false),
+        Err(e) => return Err(e),
+    }
+    Ok(true)
+}
+
+/// Runs the shell on lines read from any reader.
+///
+/// # Purpose
+/// This function runs the read-dispatch loop (see `run_loop`) over the lines of `reader`, writing each prompt
+/// to `writer` as the interactive shell would show it. A line ending in a backslash is joined with the next
+/// one, as at the prompt. Standard input is read this way when it is not a terminal, and tests can feed the
+/// shell a canned script. The commands themselves still print to the shell's standard output and error.
+///
+/// # Parameters
+/// - `reader`: A `BufRead` the command lines are read from.
+/// - `writer`: A mutable reference to a `Write` the prompts are written to.
+/// - `state`: A mutable reference to the `ShellState` the commands run against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if `reader` or `writer` fails.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_with_input, ShellState, CONTINUATION_PROMPT};
+///
+/// let mut state = ShellState::default();
+/// state.color = false;
+/// let script = "SET first one \\\ntwo\nIF false\nTHEN\nSET branch then\nELSE\nSET branch else\nFI\n";
+/// let mut transcript = Vec::new();
+///
+/// run_with_input(script.as_bytes(), &mut transcript, &mut state).unwrap();
+///
+/// assert_eq!(state.variables["first"], "one two");
+/// assert_eq!(state.variables["branch"], "else");
+/// // One prompt per line, continuation prompts for the joined line and the block, and one at the end of input.
+/// let prompt = "My Shell> ";
+/// let expected = [prompt, CONTINUATION_PROMPT, prompt, &CONTINUATION_PROMPT.repeat(5), prompt].concat();
+/// assert_eq!(String::from_utf8(transcript).unwrap(), expected);
+/// ```
+pub fn run_with_input<R: BufRead, W: Write>(mut reader: R, writer: &mut W, state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    // Appends the next line to `buffer`, without its line ending.
+    let mut next_line = |prompt: &str, buffer: &mut String| -> Result<bool, Box<dyn Error>> {
+        write!(writer, "{}", prompt)?;
+        writer.flush()?;
+        if reader.read_line(buffer)? == 0 {
+            return Ok(false);
+        }
+        if buffer.ends_with('\n') {
+            buffer.pop();
+            if buffer.ends_with('\r') {
+                buffer.pop();
+            }
+        }
+        Ok(true)
+    };
+
+    run_loop(state, |state, prompt, input| {
+        if !next_line(prompt, input)? {
+            return Ok(false);
+        }
+        // A trailing backslash joins the next line onto this one.
+        while let Some(head_len) = strip_continuation(input).map(str::len) {
+            input.truncate(head_len);
+            if !next_line(&state.prompt2, input)? {
+                eprintln!("Error: unexpected end of input after a trailing '\\'");
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    })
+}
+
+/// Counts the IF, WHILE, FOR and FUNCTION blocks left open by some lines.
+///
+/// # Purpose
+/// This function finds how many `IF`, `WHILE` and `FOR` blocks begun in `lines` have not yet been closed by
+/// a `FI` or `DONE`, and how many `FUNCTION` bodies by a line holding only `}`. Only a `FUNCTION` line that
+/// ends in `{` opens a body; one such as `FUNCTION f { ECHO }` holds all of it. A `FOR` line counts as the
+/// lines its `;`s separate, so `FOR n IN 1 2; DO ECHO $n; DONE` is a whole block. The interactive loop uses it to keep reading lines until a block is complete.
+///
+/// # Parameters
+/// - `lines`: A slice of `String` representing the lines read so far.
+///
+/// # Returns
+/// This function returns the number of open blocks as a `usize`.
+///
+/// # Examples
+/// ```
+/// use myshell::block_depth;
+///
+/// let lines: Vec<String> = vec!["IF true".into(), "THEN".into(), "IF false".into(), "FI".into()];
+///
+/// assert_eq!(block_depth(&lines), 1);
+/// assert_eq!(block_depth(&lines[..1]), 1);
+/// assert_eq!(block_depth(&["WHILE true".to_string()]), 1);
+/// assert_eq!(block_depth(&["FUNCTION f {".to_string()]), 1);
+/// assert_eq!(block_depth(&["FUNCTION f { ECHO }".to_string()]), 0);
+/// assert_eq!(block_depth(&["FUNCTION".to_string()]), 0);
+/// assert_eq!(block_depth(&["FOR n IN 1 2; DO".to_string()]), 1);
+/// assert_eq!(block_depth(&["FOR n IN 1 2; DO ECHO $n; DONE".to_string()]), 0);
+/// assert_eq!(block_depth(&[]), 0);
+/// ```
+pub fn block_depth(lines: &[String]) -> usize {
+    lines.iter().flat_map(|line| for_lines(line)).fold(0, |depth, line| match line.split_whitespace().next() {
+        Some("IF" | "WHILE" | "FOR") => depth + 1,
+        Some("FUNCTION") if line.trim_end().ends_with('{') => depth + 1,
+        Some("FI" | "DONE" | "}") => depth.saturating_sub(1),
+        _ => depth,
+    })
+}
+
+/// Splits a `FOR` line at its unquoted `;`s into the lines they stand for, with a `DO` followed by a command
+/// as a `DO` line and a line with the command. Any other line is kept as it is.
+fn for_lines(line: &str) -> Vec<String> {
+    if line.split_whitespace().next() != Some("FOR") {
+        return vec![line.to_string()];
+    }
+    let separators = braces::unquoted_chars(line).into_iter().filter(|&(_, c)| c == ';').map(|(index, _)| index);
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for end in separators.chain([line.len()]) {
+        match line[start..end].trim() {
+            "" => {}
+            segment => match segment.split_once(char::is_whitespace) {
+                Some(("DO", command)) => lines.extend(["DO".to_string(), command.trim().to_string()]),
+                _ => lines.push(segment.to_string()),
+            },
+        }
+        start = end + 1;
+    }
+    lines
+}
+
+/// Runs an IF block.
+///
+/// # Purpose
+/// This function runs a complete conditional block, from its `IF` line to its matching `FI` line. The command
+/// on the `IF` line is run first; if it exits with status `0` the lines after `THEN` are run, and otherwise
+/// the lines after `ELSE`, if there is one. Nested blocks are run by `run_lines`. The block leaves the status
+/// of the last line it ran, or `0` if the chosen branch is empty, so a false condition alone does not fail.
+///
+/// # Parameters
+/// - `block`: A slice of `String` representing the block, whose first line is `IF <command>` and whose last
+///   line is the matching `FI`.
+/// - `state`: A mutable reference to the `ShellState` the block runs against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err`, without running anything, if
+/// the block is malformed: no command after `IF`, no `THEN` line right after it, or more than one `ELSE`.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_if_block, ShellState};
+///
+/// let mut state = ShellState::default();
+/// let block: Vec<String> = ["IF true", "THEN", "SET ran yes", "FI"].iter().map(|l| l.to_string()).collect();
+///
+/// run_if_block(&block, &mut state).unwrap();
+/// assert_eq!(state.variables["ran"], "yes");
+///
+/// let missing_then: Vec<String> = ["IF true", "SET ran no", "FI"].iter().map(|l| l.to_string()).collect();
+/// assert!(run_if_block(&missing_then, &mut state).is_err());
+/// ```
+pub fn run_if_block(block: &[String], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let condition = block[0].trim_start().strip_prefix("IF").unwrap_or_default().trim();
+    if condition.is_empty() {
+        return Err("Usage: IF <command> [args...]".into());
+    }
+    let body = &block[1..block.len() - 1];
+    if body.first().map(|line| line.trim()) != Some("THEN") {
+        return Err("IF must be followed by a line containing only THEN".into());
+    }
+
+    // Split the body at the ELSE belonging to this block, not one inside a nested block.
+    let mut else_index = None;
+    for (index, line) in body.iter().enumerate() {
+        if line.trim() == "ELSE" && block_depth(&body[..index]) == 0 {
+            if else_index.is_some() {
+                return Err("IF block has more than one ELSE".into());
+            }
+            else_index = Some(index);
+        }
+    }
+    let (then_lines, else_lines) = match else_index {
+        Some(index) => (&body[1..index], &body[index + 1..]),
+        None => (&body[1..], &[][..]),
+    };
+
+    run_line(condition, state);
+    let holds = state.last_status == 0;
+    state.last_status = 0; // A branch with no lines leaves a successful status, whatever the condition was.
+    if holds {
+        run_block_lines(then_lines, 2, state);
+    } else {
+        run_block_lines(else_lines, else_index.map_or(0, |index| index + 2), state);
+    }
+    Ok(())
+}
+
+/// Runs a WHILE block.
+///
+/// # Purpose
+/// This function runs a complete loop, from its `WHILE` line to its matching `DONE` line:
+///
+/// ```text
+/// WHILE <command> [args...]
+/// <lines>
+/// DONE
+/// ```
+///
+/// The command on the `WHILE` line is run, and as long as it exits with status `0` the lines in between are
+/// run and the command is run again. Nested blocks are run by `run_lines`. Nothing limits how often the loop
+/// runs, but Ctrl+C stops it, as does a failing line when `errexit` is on. The block leaves the status of the
+/// last line it ran, or `0` if the body never ran, so the final failure of the command does not fail it.
+///
+/// # Parameters
+/// - `block`: A slice of `String` representing the block, whose first line is `WHILE <command>` and whose
+///   last line is the matching `DONE`.
+/// - `state`: A mutable reference to the `ShellState` the block runs against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err`, without running anything, if
+/// there is no command after `WHILE`.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_while_block, ShellState};
+///
+/// let mut state = ShellState::default();
+/// let block: Vec<String> = ["WHILE [ $(($n + 0)) != 3 ]", "SET n $(($n + 1))", "DONE"].iter().map(|l| l.to_string()).collect();
+///
+/// run_while_block(&block, &mut state).unwrap();
+/// assert_eq!(state.variables["n"], "3");
+/// assert_eq!(state.last_status, 0);
+/// ```
+pub fn run_while_block(block: &[String], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let condition = block[0].trim_start().strip_prefix("WHILE").unwrap_or_default().trim();
+    if condition.is_empty() {
+        return Err("Usage: WHILE <command> [args...]".into());
+    }
+    let body = &block[1..block.len() - 1];
+
+    let mut status = 0;
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        run_line(condition, state);
+        if state.last_status != 0 || INTERRUPTED.load(Ordering::SeqCst) {
+            break;
+        }
+        state.last_status = 0; // An empty body leaves a successful status.
+        run_block_lines(body, 1, state);
+        status = state.last_status;
+        if state.exits_on_error() {
+            return Ok(());
+        }
+    }
+    state.last_status = status;
+    Ok(())
+}
+
+/// Runs lines with `run_lines`, with errors in any of them naming the line running now.
+fn run_lines_on_current_line(lines: &[String], state: &mut ShellState) {
+    let line_location = state.location.as_ref().and_then(|location| {
+        let line = *location.line_numbers.get(location.index)?;
+        Some(ScriptLocation { file: location.file.clone(), line_numbers: vec![line; lines.len()], index: 0 })
+    });
+    let outer_location = std::mem::replace(&mut state.location, line_location);
+    run_lines(lines, state);
+    state.location = outer_location;
+}
+
+/// Runs lines from inside a block, which start `offset` lines after its first one, with `run_lines`.
+fn run_block_lines(lines: &[String], offset: usize, state: &mut ShellState) {
+    let start = state.location.as_ref().map(|location| location.index);
+    if let (Some(location), Some(start)) = (&mut state.location, start) {
+        location.index = start + offset;
+    }
+    run_lines(lines, state);
+    if let (Some(location), Some(start)) = (&mut state.location, start) {
+        location.index = start;
+    }
+}
+
+/// Runs a FOR block.
+///
+/// # Purpose
+/// This function runs a complete loop over a list of words, from its `FOR` line to its matching `DONE` line:
+///
+/// ```text
+/// FOR <name> IN [words...]
+/// DO
+/// <lines>
+/// DONE
+/// ```
+///
+/// `DO` may end the `FOR` line after a `;` instead, and the whole loop may be written on one line, as in
+/// `FOR <name> IN [words...]; DO <line>; DONE`; errors in a loop on one line name that line.
+///
+/// The words after `IN` are expanded once, before the loop starts, as the arguments of a command are: brace
+/// groups, variables and substitutions are expanded, and the result is split into words and unquoted by
+/// `tokenize`. For each word in turn the shell variable `name` is set to it and the lines in between are run;
+/// nested blocks are run by `run_lines`. The variable keeps the last word afterwards, as in bash. Ctrl+C
+/// stops the loop, as does a failing line when `errexit` is on. The block leaves the status of the last line
+/// it ran, or `0` if the list is empty.
+///
+/// # Parameters
+/// - `block`: A slice of `String` representing the block, whose first line is `FOR <name> IN [words...]` and
+///   whose last line is the matching `DONE`, or the one line holding the whole loop.
+/// - `state`: A mutable reference to the `ShellState` the block runs against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err`, without running anything, if
+/// the first line is malformed, `DO` does not follow it, anything follows the `DONE` of a loop on one line,
+/// the name is not a valid variable name, or the words cannot be expanded.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_for_block, ShellState};
+///
+/// let mut state = ShellState::default();
+/// let block: Vec<String> = ["FOR n IN {1..3} 'four five'", "DO", "SET seen \"$seen$n,\"", "DONE"].iter().map(|l| l.to_string()).collect();
+///
+/// run_for_block(&block, &mut state).unwrap();
+/// assert_eq!(state.variables["seen"], "1,2,3,four five,");
+/// assert_eq!(state.variables["n"], "four five");
+///
+/// run_for_block(&["FOR n IN a 'b;c'; DO SET last $n; DONE".to_string()], &mut state).unwrap();
+/// assert_eq!(state.variables["last"], "b;c");
+///
+/// let missing_in: Vec<String> = ["FOR n {1..3}", "DO", "DONE"].iter().map(|l| l.to_string()).collect();
+/// assert!(run_for_block(&missing_in, &mut state).is_err());
+///
+/// let missing_do: Vec<String> = ["FOR n IN {1..3}", "DONE"].iter().map(|l| l.to_string()).collect();
+/// assert!(run_for_block(&missing_do, &mut state).is_err());
+/// ```
+pub fn run_for_block(block: &[String], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let header_lines = for_lines(&block[0]);
+    let body_offset = 3usize.saturating_sub(header_lines.len()); // How far into the block the body starts.
+    let lines: Vec<String> = header_lines.into_iter().chain(block[1..].iter().cloned()).collect();
+    let header = lines[0].trim_start().strip_prefix("FOR").unwrap_or_default().trim();
+    let (name, rest) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+    let rest = rest.trim_start();
+    let (keyword, list) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if name.is_empty() || keyword != "IN" {
+        return Err("Usage: FOR <name> IN [words...]".into());
+    }
+    if variable_name_len(name) != name.len() {
+        return Err(format!("'{}' is not a valid variable name", name).into());
+    }
+    if lines.get(1).map(|line| line.trim()) != Some("DO") {
+        return Err("FOR must be followed by DO, on the next line or after a ';'".into());
+    }
+    let end = (2..lines.len()).find(|&end| block_depth(&lines[..=end]) == 0).unwrap_or(lines.len() - 1);
+    if end + 1 < lines.len() {
+        return Err(format!("'{}' cannot follow the DONE of a FOR loop on one line", lines[end + 1]).into());
+    }
+    let words = tokenize(&expand_line(&expand_braces(list), state)?)?;
+    let body = &lines[2..end];
+
+    let mut status = 0;
+    for word in words {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break;
+        }
+        state.variables.insert(name.to_string(), word);
+        state.last_status = 0; // An empty body leaves a successful status.
+        if block.len() == 1 {
+            run_lines_on_current_line(body, state);
+        } else {
+            run_block_lines(body, body_offset, state);
+        }
+        status = state.last_status;
+        if state.exits_on_error() {
+            return Ok(());
+        }
+    }
+    state.last_status = status;
+    Ok(())
+}
+
+/// The deepest that function calls may run inside each other before `run_function` refuses another.
+pub const MAX_FUNCTION_DEPTH: usize = 100;
+
+/// Handles the FUNCTION command.
+///
+/// # Purpose
+/// This function defines, deletes, or lists user-defined functions. It is given the raw lines of the
+/// command, before any expansion, so that a body's variables are expanded each time it runs rather than when
+/// it is defined. A function is defined on one line or across several:
+///
+/// ```text
+/// FUNCTION <name> { <line> }
+/// FUNCTION <name> {
+/// <lines>
+/// }
+/// ```
+///
+/// Defining a function that exists replaces it. `FUNCTION <name>` alone deletes the function, and
+/// `FUNCTION` alone lists every function, sorted by name, with its body.
+///
+/// # Parameters
+/// - `block`: A slice of `String` representing the `FUNCTION` line and, for a body on several lines, the lines
+///   up to the closing `}`.
+/// - `functions`: A mutable reference to the `HashMap<String, Vec<String>>` of functions.
+/// - `keywords`: A reference to the keyword table the dispatcher looks built-in commands up in.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the name is a keyword of
+/// `keywords` in any case, such as `STOP` or `exit`, which would run the built-in instead, or holds anything
+/// but letters, digits, `_`, `-` and `.`, the body is not enclosed in braces, or the function to delete does
+/// not exist.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{default_keywords, define_function};
+///
+/// let mut functions = HashMap::new();
+/// let keywords = default_keywords();
+/// let lines = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect::<Vec<_>>();
+///
+/// define_function(&lines(&["FUNCTION greet { ECHO hello $1 }"]), &mut functions, &keywords).unwrap();
+/// assert_eq!(functions["greet"], ["ECHO hello $1"]);
+///
+/// define_function(&lines(&["FUNCTION twice {", "$1", "$1", "}"]), &mut functions, &keywords).unwrap();
+/// assert_eq!(functions["twice"], ["$1", "$1"]);
+///
+/// define_function(&lines(&["FUNCTION greet"]), &mut functions, &keywords).unwrap();
+/// assert!(!functions.contains_key("greet"));
+/// assert!(define_function(&lines(&["FUNCTION greet"]), &mut functions, &keywords).is_err());
+/// assert!(define_function(&lines(&["FUNCTION broken ECHO"]), &mut functions, &keywords).is_err());
+/// assert!(define_function(&lines(&["FUNCTION exit { ECHO }"]), &mut functions, &keywords).is_err());
+/// assert!(define_function(&lines(&["FUNCTION Alias { ECHO }"]), &mut functions, &keywords).is_err());
+/// ```
+pub fn define_function(block: &[String], functions: &mut HashMap<String, Vec<String>>, keywords: &HashMap<String, BuiltinKind>) -> Result<(), Box<dyn Error>> {
+    let header = block[0].trim_start().strip_prefix("FUNCTION").unwrap_or_default().trim();
+    let (name, rest) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+
+    if name.is_empty() {
+        let mut names: Vec<&String> = functions.keys().collect();
+        names.sort();
+        for name in names {
+            println!("FUNCTION {} {{", name);
+            for line in &functions[name] {
+                println!("    {}", line);
+            }
+            println!("}}");
+        }
+        return Ok(());
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || "_-.".contains(c)) {
+        return Err(format!("FUNCTION: '{}' is not a valid function name", name).into());
+    }
+    if is_keyword(name, keywords) {
+        return Err(format!("FUNCTION: '{}' is a built-in command", name).into());
+    }
+    if rest.is_empty() {
+        return match functions.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(format!("FUNCTION: no such function '{}'", name).into()),
+        };
+    }
+
+    let Some(opened) = rest.trim().strip_prefix('{') else {
+        return Err("Usage: FUNCTION <name> { <lines> }".into());
+    };
+    let body: Vec<String> = match opened.strip_suffix('}') {
+        Some(line) if block.len() == 1 => [line.trim()].into_iter().filter(|line| !line.is_empty()).map(String::from).collect(),
+        _ if opened.trim().is_empty() && block.len() > 1 => block[1..block.len() - 1].iter().map(|line| line.trim().to_string()).collect(),
+        _ => return Err("Usage: FUNCTION <name> { <lines> }".into()),
+    };
+    functions.insert(name.to_string(), body);
+    Ok(())
+}
+
+/// Runs a user-defined function.
+///
+/// # Purpose
+/// This function runs the body of the function `name` with `run_lines`, as if its lines had been typed, with
+/// `name` and `args` as the positional parameters `$0`, `$1`, `$2` and so on. The caller's positional
+/// parameters are restored when the body ends, and `$?` is left as the status of its last line, or `0` for
+/// an empty body. A function may call itself, up to `MAX_FUNCTION_DEPTH` calls deep.
+///
+/// # Parameters
+/// - `name`: A `&str` representing the name of the function.
+/// - `args`: A slice of `&str` representing the arguments it is called with.
+/// - `state`: A mutable reference to the `ShellState` holding the functions.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if there is no such function,
+/// or the calls are nested too deeply. Errors from the lines of the body are printed as they happen.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_function, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.functions.insert("remember".to_string(), vec!["SET last $1".to_string()]);
+///
+/// run_function("remember", &["this"], &mut state).unwrap();
+/// assert_eq!(state.variables["last"], "this");
+/// assert_eq!(state.positional, ["myshell-rs"]);
+/// ```
+pub fn run_function(name: &str, args: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let body = state.functions.get(name).cloned().ok_or_else(|| format!("{}: no such function", name))?;
+    if state.function_depth >= MAX_FUNCTION_DEPTH {
+        state.last_status = 1;
+        return Err(format!("{}: functions nested more than {} deep", name, MAX_FUNCTION_DEPTH).into());
+    }
+
+    let arguments = std::iter::once(name).chain(args.iter().copied()).map(String::from).collect();
+    let caller_positional = std::mem::replace(&mut state.positional, arguments);
+    state.function_depth += 1;
+    state.last_status = 0;
+    // Errors in the body name the line of the call, since the body's own lines may be far away or typed.
+    run_lines_on_current_line(&body, state);
+    state.function_depth -= 1;
+    state.positional = caller_positional;
+    Ok(())
+}
+
+/// Handles the TEST and `[` commands.
+///
+/// # Purpose
+/// This function evaluates a condition for `IF`. When called as `[`, the last word must be a closing `]`,
+/// which is dropped before evaluating. The expression is one of:
+///
+/// - nothing: false;
+/// - `string`: true if the string is not empty;
+/// - `-e path`, `-f path`, `-d path`: true if the path exists, is a regular file, or is a directory;
+/// - `-z string`, `-n string`: true if the string is empty, or not empty;
+/// - `a = b`, `a != b`: true if the strings are equal, or different.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`TEST` or `[`) and the rest is the expression.
+///
+/// # Returns
+/// This function returns a `Result<bool, Box<dyn Error>>` telling whether the condition holds. It returns
+/// an `Err` if the closing `]` is missing or the expression is not understood.
+///
+/// # Examples
+/// ```
+/// use myshell::test_condition;
+///
+/// assert!(test_condition(&["TEST", "-d", "/"]).unwrap());
+/// assert!(!test_condition(&["TEST", "-f", "/"]).unwrap());
+/// assert!(test_condition(&["[", "a", "!=", "b", "]"]).unwrap());
+/// assert!(test_condition(&["[", "-z", "", "]"]).unwrap());
+/// assert!(test_condition(&["[", "a", "=", "a"]).is_err()); // Missing `]`.
+/// assert!(test_condition(&["TEST", "-q", "x"]).is_err());
+/// ```
+pub fn test_condition(inputs: &[&str]) -> Result<bool, Box<dyn Error>> {
+    let expression = match inputs.split_first() {
+        Some((&"[", rest)) => match rest.split_last() {
+            Some((&"]", expression)) => expression,
+            _ => return Err("[: missing closing ']'".into()),
+        },
+        Some((_, rest)) => rest,
+        None => &[],
+    };
+
+    match *expression {
+        [] => Ok(false),
+        [string] => Ok(!string.is_empty()),
+        ["-e", path] => Ok(Path::new(path).exists()),
+        ["-f", path] => Ok(Path::new(path).is_file()),
+        ["-d", path] => Ok(Path::new(path).is_dir()),
+        ["-z", string] => Ok(string.is_empty()),
+        ["-n", string] => Ok(!string.is_empty()),
+        [a, "=", b] => Ok(a == b),
+        [a, "!=", b] => Ok(a != b),
+        _ => Err(format!("TEST: unknown expression '{}'", expression.join(" ")).into()),
+    }
+}
+
+/// Joins lines continued with a trailing backslash.
+///
+/// # Purpose
+/// This function applies `strip_continuation` to a whole file's worth of lines, joining each line that ends
+/// in an unescaped backslash onto the one after it, just as the interactive loop does. The bodies of
+/// here-documents are folded into the lines that start them (see `read_here_documents`).
+///
+/// # Parameters
+/// - `lines`: An iterator of `String` representing the raw lines.
+///
+/// # Returns
+/// This function returns the joined lines as a `Vec<String>`. A backslash on the last line is dropped.
+///
+/// # Examples
+/// ```
+/// use myshell::join_continuations;
+///
+/// let lines = ["ECHO one \\", "two", "ECHO three"].iter().map(|l| l.to_string());
+///
+/// assert_eq!(join_continuations(lines), vec!["ECHO one two", "ECHO three"]);
+///
+/// let lines = ["cat <<EOF", "body", "EOF", "ECHO after"].iter().map(|l| l.to_string());
+/// assert_eq!(join_continuations(lines), vec!["cat <<< \"body\"", "ECHO after"]);
+/// ```
+pub fn join_continuations(lines: impl IntoIterator<Item = String>) -> Vec<String> {
+    join_numbered_continuations(lines).into_iter().map(|(_, line)| line).collect()
+}
+
+/// Joins continuation lines as `join_continuations` does, pairing each joined line with the number, counting
+/// from 1, of the line it starts on.
+fn join_numbered_continuations(lines: impl IntoIterator<Item = String>) -> Vec<(usize, String)> {
+    let mut joined = Vec::new();
+    let mut pending = String::new();
+    let mut first_number = None;
+    let mut lines = lines.into_iter().zip(1..);
+    while let Some((line, number)) = lines.next() {
+        first_number.get_or_insert(number);
+        match strip_continuation(&line) {
+            Some(head) => pending.push_str(head),
+            None => {
+                pending.push_str(&line);
+                let line = read_here_documents(&std::mem::take(&mut pending), || Ok(lines.next().map(|(line, _)| line)))
+                    .expect("reading lines from memory cannot fail");
+                joined.push((first_number.take().unwrap_or(number), line));
+            }
+        }
+    }
+    if let Some(number) = first_number.filter(|_| !pending.is_empty()) {
+        joined.push((number, pending));
+    }
+    joined
+}
+
+/// Handles the SOURCE command.
+///
+/// # Purpose
+/// This function reads a file of commands and runs them in the current shell with `run_lines`, so that the
+/// file can define aliases and variables or change settings. Lines continued with a trailing backslash are
+/// joined first, and `$?` is left as the status of the file's last command. While the file runs, its name
+/// and the words after it are the positional parameters `$0`, `$1`, `$2` and so on, with their count as
+/// `$#`; the caller's positional parameters are restored when it ends. Errors from the file's commands start
+/// with its name and the line they came from, as in `setup.mysh:12: `.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SOURCE`), the second word is the name of the file to run, and the rest are
+///   its arguments.
+/// - `state`: A mutable reference to the `ShellState` the commands run against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no file name is given or the
+/// file cannot be read. Errors from the commands in the file are printed as they happen.
+///
+/// # Examples
+/// ```
+/// use myshell::{source_file, ShellState};
+///
+/// let path = std::env::temp_dir().join("myshell-source-example");
+/// std::fs::write(&path, "SETSHELLNAME Scripted\nIF true\nTHEN\nSET sourced \"$1 of $#\"\nFI\n").unwrap();
+///
+/// let mut state = ShellState::default();
+/// source_file(&["SOURCE", path.to_str().unwrap(), "first", "second"], &mut state).unwrap();
+///
+/// assert_eq!(state.shellname, "Scripted");
+/// assert_eq!(state.variables["sourced"], "first of 2");
+/// assert_eq!(state.positional, vec!["myshell-rs"]);
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn source_file(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() < 2 {
+        return Err("Usage: SOURCE <file_name> [args...]".into());
+    }
+
+    let text = fs::read_to_string(inputs[1]).map_err(|e| format!("{}: {}", inputs[1], e))?;
+    state.last_status = 0; // An empty file succeeds.
+    let (line_numbers, lines): (Vec<usize>, Vec<String>) = join_numbered_continuations(text.lines().map(String::from)).into_iter().unzip();
+    let location = ScriptLocation { file: inputs[1].to_string(), line_numbers, index: 0 };
+    let caller_location = state.location.replace(location);
+    let caller_positional = std::mem::replace(&mut state.positional, inputs[1..].iter().map(|arg| arg.to_string()).collect());
+    run_lines(&lines, state);
+    state.positional = caller_positional;
+    state.location = caller_location;
+    Ok(())
+}
+
+/// Handles the SHIFT command.
+///
+/// # Purpose
+/// This function drops the first `n` positional parameters, one by default, renumbering the rest so that
+/// `$n+1` becomes `$1`, as in bash. The script name `$0` is kept, and `$#` goes down by `n`. Scripts use it to
+/// work through their arguments one at a time.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SHIFT`) and the optional second word is the number to drop.
+/// - `positional`: A mutable reference to the `Vec<String>` of positional parameters, starting with `$0`.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err`, leaving the parameters alone,
+/// if `n` is not a number or there are fewer than `n` parameters.
+///
+/// # Examples
+/// ```
+/// use myshell::shift_positional;
+///
+/// let mut positional = ["build.sh", "-v", "--out", "dist"].map(String::from).to_vec();
+///
+/// shift_positional(&["SHIFT"], &mut positional).unwrap();
+/// assert_eq!(positional, ["build.sh", "--out", "dist"]);
+///
+/// shift_positional(&["SHIFT", "2"], &mut positional).unwrap();
+/// assert_eq!(positional, ["build.sh"]);
+///
+/// assert!(shift_positional(&["SHIFT"], &mut positional).is_err());
+/// ```
+pub fn shift_positional(inputs: &[&str], positional: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
+    let count: usize = match inputs {
+        [_] => 1,
+        [_, count] => count.parse().map_err(|_| format!("SHIFT: '{}' is not a number", count))?,
+        _ => return Err("Usage: SHIFT [n]".into()),
+    };
+
+    let available = positional.len().saturating_sub(1);
+    if count > available {
+        return Err(format!("SHIFT: cannot shift {} of {} positional parameters", count, available).into());
+    }
+    if count > 0 {
+        positional.drain(1..=count);
+    }
+    Ok(())
+}
+
+/// Prints help for the built-in commands.
+///
+/// # Purpose
+/// This function handles the `HELP` command. With no argument it lists every built-in command from the
+/// `BUILTINS` table alongside its one-line summary. With a command name it prints that command's detailed usage.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`HELP`) and the second word (if present) is the command to describe.
+///
+/// # Returns
+/// This function does not return any value. It prints the requested help, or a message if the command is unknown.
+///
+/// # Examples
+/// ```
+/// use myshell::print_help;
+///
+/// print_help(&["HELP"]);
+/// print_help(&["HELP", "NEWNAME"]);
+/// ```
+pub fn print_help(inputs: &[&str]) {
+    match inputs.get(1) {
+        None => {
+            let width = BUILTINS.iter().map(|builtin| builtin.name.len()).max().unwrap_or(0);
+            println!("Built-in commands:");
+            for builtin in BUILTINS {
+                println!("  {:<width$}  {}", builtin.name, builtin.summary, width = width);
+            }
+            println!("Type 'HELP <command>' for details on a command.");
+            println!("Built-in output can be redirected with >, >>, 2> and &>, but not input with < or <<<.");
+        }
+        Some(name) => match BUILTINS.iter().find(|builtin| builtin.name == *name) {
+            Some(builtin) => println!("{}", builtin.usage),
+            None => println!("No built-in command named '{}'.", name),
+        },
+    }
+}
+
+/// Handles the HISTORY command.
+///
+/// # Purpose
+/// This function lists the commands entered this session, each numbered by its position in the history.
+/// An optional count limits the listing to the most recent entries.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`HISTORY`) and the second word (if present) is how many entries to show.
+/// - `history`: A slice of `String` containing the history, oldest first.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the count is not a number.
+///
+/// # Examples
+/// ```
+/// use myshell::print_history;
+///
+/// let history = vec!["ls".to_string(), "HELP".to_string()];
+///
+/// print_history(&["HISTORY"], &history).unwrap();
+/// print_history(&["HISTORY", "1"], &history).unwrap();
+/// assert!(print_history(&["HISTORY", "many"], &history).is_err());
+/// ```
+pub fn print_history(inputs: &[&str], history: &[String]) -> Result<(), Box<dyn Error>> {
+    let count = match inputs.get(1) {
+        Some(count) => count
+            .parse::<usize>()
+            .map_err(|_| format!("HISTORY count must be a number, got '{}'", count))?,
+        None => history.len(),
+    };
+
+    let start = history.len().saturating_sub(count);
+    for (number, entry) in history.iter().enumerate().skip(start) {
+        println!("{:>5}  {}", number + 1, entry);
+    }
+    Ok(())
+}
+
+/// Handles the CLEAR command.
+///
+/// # Purpose
+/// This function clears the terminal screen and moves the cursor home by writing the ANSI sequence
+/// `ESC[2J ESC[H`. On Windows, where older consoles do not understand it, `cls` is run instead.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if writing to the terminal or
+/// running `cls` fails.
+///
+/// # Examples
+/// ```
+/// use myshell::clear_screen;
+///
+/// clear_screen().unwrap();
+/// ```
+pub fn clear_screen() -> Result<(), Box<dyn Error>> {
+    if cfg!(windows) {
+        process::Command::new("cmd").args(["/C", "cls"]).status()?;
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b[2J\x1b[H")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Handles the ECHO command.
+///
+/// # Purpose
+/// This function prints its arguments as formatted by `format_echo`, so that output is the same on every
+/// platform regardless of which external `echo` is installed.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`ECHO`) and the rest are flags and words to print.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if writing to standard output fails.
+///
+/// # Examples
+/// ```
+/// use myshell::echo;
+///
+/// echo(&["ECHO", "-e", "one\\ttwo"]).unwrap();
+/// ```
+pub fn echo(inputs: &[&str]) -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", format_echo(&inputs[1..]))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Formats the output of the ECHO command.
+///
+/// # Purpose
+/// This function joins the words to print with single spaces and appends a newline. Leading flag words are
+/// recognized until the first word that is not one: `-n` drops the trailing newline, `-e` interprets the
+/// escapes `\n`, `\t`, and `\\`, and the two can be combined as `-ne` or `-en`.
+///
+/// # Parameters
+/// - `args`: A slice of `&str` containing the flags and the words to print.
+///
+/// # Returns
+/// This function returns the text to print as a `String`.
+///
+/// # Examples
+/// ```
+/// use myshell::format_echo;
+///
+/// assert_eq!(format_echo(&["hello", "world"]), "hello world\n");
+/// assert_eq!(format_echo(&["-n", "no", "newline"]), "no newline");
+/// assert_eq!(format_echo(&["-e", "a\\nb\\tc\\\\d"]), "a\nb\tc\\d\n");
+/// assert_eq!(format_echo(&["a\\nb"]), "a\\nb\n");
+/// assert_eq!(format_echo(&["-ne", "x\\n"]), "x\n");
+/// assert_eq!(format_echo(&["-x", "kept"]), "-x kept\n");
+/// ```
+pub fn format_echo(args: &[&str]) -> String {
+    let mut newline = true;
+    let mut escapes = false;
+
+    let mut words = args;
+    while let Some((flag, rest)) = words.split_first() {
+        let letters = flag.strip_prefix('-').unwrap_or_default();
+        if letters.is_empty() || !letters.chars().all(|c| c == 'n' || c == 'e') {
+            break;
+        }
+        newline &= !letters.contains('n');
+        escapes |= letters.contains('e');
+        words = rest;
+    }
+
+    let mut text = words.join(" ");
+    if escapes {
+        text = interpret_escapes(&text);
+    }
+    if newline {
+        text.push('\n');
+    }
+    text
+}
+
+/// Interprets backslash escapes for `ECHO -e`.
+///
+/// # Purpose
+/// This function replaces `\n` with a newline, `\t` with a tab, and `\\` with a single backslash. Any other
+/// backslash is kept as typed.
+///
+/// # Parameters
+/// - `text`: A `&str` possibly containing escapes.
+///
+/// # Returns
+/// This function returns the text with escapes interpreted, as a `String`.
+///
+/// # Examples
+/// ```
+/// use myshell::interpret_escapes;
+///
+/// assert_eq!(interpret_escapes("tab\\there"), "tab\there");
+/// assert_eq!(interpret_escapes("unknown \\q"), "unknown \\q");
+/// ```
+pub fn interpret_escapes(text: &str) -> String {
+    let mut interpreted = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            interpreted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => interpreted.push('\n'),
+            Some('t') => interpreted.push('\t'),
+            Some('\\') => interpreted.push('\\'),
+            Some(other) => {
+                interpreted.push('\\');
+                interpreted.push(other);
+            }
+            None => interpreted.push('\\'),
+        }
+    }
+
+    interpreted
+}
+
+/// Handles the SET command.
+///
+/// # Purpose
+/// This function sets a shell variable, or lists every shell variable sorted by name when called without
+/// arguments. The value is the rest of the line joined by spaces; quote it to keep runs of spaces.
+/// Shell variables are expanded in later commands but are not part of the environment of child processes.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SET`), the second is the variable name, and the rest form the value.
+/// - `variables`: A mutable reference to a `HashMap<String, String>` containing the shell variables.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the name is not a valid
+/// variable name.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::set_variable;
+///
+/// let mut variables = HashMap::new();
+///
+/// set_variable(&["SET", "greeting", "hello", "there"], &mut variables).unwrap();
+/// assert_eq!(variables.get("greeting"), Some(&"hello there".to_string()));
+///
+/// assert!(set_variable(&["SET", "not-a-name", "x"], &mut variables).is_err());
+/// ```
+pub fn set_variable(inputs: &[&str], variables: &mut HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+    let Some(&name) = inputs.get(1) else {
+        let mut names: Vec<&String> = variables.keys().collect();
+        names.sort();
+        for name in names {
+            println!("{}={}", name, variables[name]);
+        }
+        return Ok(());
+    };
+
+    if name.is_empty() || variable_name_len(name) != name.len() {
+        return Err(format!("'{}' is not a valid variable name", name).into());
+    }
+
+    let value = inputs[2..].join(" ");
+    variables.insert(name.to_string(), value);
+    Ok(())
+}
+
+/// Handles the READ command.
+///
+/// # Purpose
+/// This function reads one line from standard input and stores it, without its newline, in a shell variable.
+/// With `-p`, the words between the flag and the variable name are printed as a prompt first, followed by a
+/// space. At the end of input the variable is set to the empty string and an error is returned,
+/// so that scripts can tell the input has run out.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`READ`), followed by an optional `-p prompt`, and the last word is the
+///   variable name.
+/// - `variables`: A mutable reference to a `HashMap<String, String>` containing the shell variables.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the variable name is missing
+/// or invalid, standard input cannot be read, or the input has ended.
+///
+/// # Examples
+/// ```no_run
+/// use std::collections::HashMap;
+/// use myshell::read_variable;
+///
+/// let mut variables = HashMap::new();
+///
+/// if read_variable(&["READ", "-p", "Your name:", "name"], &mut variables).is_ok() {
+///     println!("Hello, {}", variables["name"]);
+/// }
+/// ```
+pub fn read_variable(inputs: &[&str], variables: &mut HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+    let usage = "Usage: READ [-p prompt] <name>";
+    let (prompt, name) = match inputs {
+        [_, name] => (String::new(), *name),
+        [_, "-p", prompt @ .., name] if !prompt.is_empty() => (format!("{} ", prompt.join(" ")), *name),
+        _ => return Err(usage.into()),
+    };
+    if name.is_empty() || variable_name_len(name) != name.len() {
+        return Err(format!("'{}' is not a valid variable name", name).into());
+    }
+
+    match read_input(&prompt)? {
+        Some(line) => {
+            variables.insert(name.to_string(), line);
+            Ok(())
+        }
+        None => {
+            variables.insert(name.to_string(), String::new());
+            Err("READ: end of input".into())
+        }
+    }
+}
+
+/// Handles the EXPORT command.
+///
+/// # Purpose
+/// This function adds variables to the environment passed to child processes. Each argument is either
+/// `name=value`, or a bare `name` that exports the shell variable of that name. Only the shell's own
+/// environment map changes; the environment of the shell process is left alone. With no arguments, the
+/// environment is listed sorted by name.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`EXPORT`) and the rest are the variables to export.
+/// - `state`: A mutable reference to the `ShellState` holding the shell variables and environment.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if a name is invalid or a bare
+/// name is not a shell variable.
+///
+/// # Examples
+/// ```
+/// use myshell::{export_variables, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.variables.insert("greeting".to_string(), "hello".to_string());
+///
+/// export_variables(&["EXPORT", "MYSHELL_EXAMPLE=1", "greeting"], &mut state).unwrap();
+///
+/// assert_eq!(state.environment.get("MYSHELL_EXAMPLE"), Some(&"1".to_string()));
+/// assert_eq!(state.environment.get("greeting"), Some(&"hello".to_string()));
+/// assert_eq!(std::env::var("MYSHELL_EXAMPLE").ok(), None); // The host process is untouched.
+/// assert!(export_variables(&["EXPORT", "never_set"], &mut state).is_err());
+/// ```
+pub fn export_variables(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() < 2 {
+        let mut names: Vec<&String> = state.environment.keys().collect();
+        names.sort();
+        for name in names {
+            println!("{}={}", name, state.environment[name]);
+        }
+        return Ok(());
+    }
+
+    for &argument in &inputs[1..] {
+        let (name, value) = match argument.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => {
+                let value = state
+                    .variables
+                    .get(argument)
+                    .ok_or_else(|| format!("'{}' is not a shell variable; use EXPORT {}=value", argument, argument))?;
+                (argument, value.clone())
+            }
+        };
+        if name.is_empty() || variable_name_len(name) != name.len() {
+            return Err(format!("'{}' is not a valid variable name", name).into());
+        }
+        if name == "PATH" {
+            state.command_paths.borrow_mut().clear(); // Programs may now be found elsewhere.
+        }
+        state.environment.insert(name.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Handles the UNSET command.
+///
+/// # Purpose
+/// This function removes each named variable from both the shell variables and the environment passed to child
+/// processes. Names that are not set are ignored, as in bash.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`UNSET`) and the rest are the variables to remove.
+/// - `state`: A mutable reference to the `ShellState` holding the shell variables and environment.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no name is given.
+///
+/// # Examples
+/// ```
+/// use myshell::{unset_variables, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.environment.insert("MYSHELL_EXAMPLE".to_string(), "1".to_string());
+///
+/// unset_variables(&["UNSET", "MYSHELL_EXAMPLE"], &mut state).unwrap();
+///
+/// assert_eq!(state.environment.get("MYSHELL_EXAMPLE"), None);
+/// ```
+pub fn unset_variables(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() < 2 {
+        return Err("Usage: UNSET <name...>".into());
+    }
+
+    for &name in &inputs[1..] {
+        state.variables.remove(name);
+        state.environment.remove(name);
+    }
+    if inputs[1..].contains(&"PATH") {
+        state.command_paths.borrow_mut().clear();
+    }
+    Ok(())
+}
+
+/// Handles the CD command.
+///
+/// # Purpose
+/// This function changes the shell's current directory to the one given, or to `$HOME` when no directory
+/// is given. Relative paths are resolved against the current directory. `CD -` swaps back to the previous
+/// directory and prints it.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`CD`) and the second is the directory to change to.
+/// - `state`: A mutable reference to the `ShellState` whose environment supplies `$HOME` and records `$PWD`.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the directory cannot be
+/// entered, `$HOME` is not set, or `CD -` is used before any change of directory.
+///
+/// # Examples
+/// ```
+/// use myshell::{change_directory, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// let start = std::env::current_dir().unwrap();
+///
+/// assert!(change_directory(&["CD", "-"], &mut state).is_err());
+/// change_directory(&["CD", "/"], &mut state).unwrap();
+/// assert_eq!(std::env::current_dir().unwrap(), std::path::Path::new("/"));
+/// assert!(change_directory(&["CD", "/no/such/directory"], &mut state).is_err());
+///
+/// change_directory(&["CD", "-"], &mut state).unwrap();
+/// assert_eq!(std::env::current_dir().unwrap(), start);
+/// assert_eq!(state.oldpwd.as_deref(), Some(std::path::Path::new("/")));
+/// ```
+pub fn change_directory(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    match inputs.get(1) {
+        Some(&"-") => {
+            let previous = state.oldpwd.clone().ok_or("CD: no previous directory")?;
+            enter_directory(&previous, state)?;
+            println!("{}", tilde_path(&previous));
+            Ok(())
+        }
+        Some(directory) => enter_directory(Path::new(directory), state),
+        None => {
+            let home = state.environment.get("HOME").map(PathBuf::from).ok_or("CD: HOME is not set")?;
+            enter_directory(&home, state)
+        }
+    }
+}
+
+/// Makes a directory the shell's current directory.
+///
+/// # Purpose
+/// This function is shared by `CD`, `PUSHD` and `POPD`. It changes the process's working directory and keeps
+/// `$PWD` in the shell's environment in step, so that child processes see where they were started. The
+/// directory it leaves is remembered for `CD -` and exported as `$OLDPWD`.
+///
+/// # Parameters
+/// - `target`: A reference to the `Path` to change to.
+/// - `state`: A mutable reference to the `ShellState` whose environment records `$PWD`.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` naming the directory if it
+/// cannot be entered.
+///
+/// # Examples
+/// ```
+/// use myshell::{enter_directory, ShellState};
+/// use std::path::Path;
+///
+/// let mut state = ShellState::default();
+///
+/// let start = std::env::current_dir().unwrap();
+///
+/// enter_directory(Path::new("/"), &mut state).unwrap();
+/// assert_eq!(state.environment.get("PWD"), Some(&"/".to_string()));
+/// assert_eq!(state.oldpwd, Some(start));
+/// ```
+pub fn enter_directory(target: &Path, state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let previous = env::current_dir().ok();
+    env::set_current_dir(target).map_err(|e| format!("{}: {}", target.display(), e))?;
+    let cwd = env::current_dir()?;
+    state.environment.insert("PWD".to_string(), cwd.display().to_string());
+    if let Some(previous) = previous {
+        state.environment.insert("OLDPWD".to_string(), previous.display().to_string());
+        state.oldpwd = Some(previous);
+    }
+    Ok(())
+}
+
+/// Handles the PUSHD command.
+///
+/// # Purpose
+/// This function changes to the given directory and, only if that succeeds, pushes the directory the shell
+/// was in onto the directory stack so that `POPD` can return to it.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`PUSHD`) and the second is the directory to change to.
+/// - `state`: A mutable reference to the `ShellState` holding the directory stack.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no directory is given or it
+/// cannot be entered, in which case the stack is unchanged.
+///
+/// # Examples
+/// ```
+/// use myshell::{push_directory, ShellState};
+///
+/// let mut state = ShellState::default();
+/// let start = std::env::current_dir().unwrap();
+///
+/// push_directory(&["PUSHD", "/"], &mut state).unwrap();
+/// assert_eq!(state.dir_stack, vec![start]);
+/// assert!(push_directory(&["PUSHD", "/no/such/directory"], &mut state).is_err());
+/// assert_eq!(state.dir_stack.len(), 1);
+/// ```
+pub fn push_directory(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let Some(directory) = inputs.get(1) else {
+        return Err("Usage: PUSHD <directory>".into());
+    };
+
+    let previous = env::current_dir()?;
+    enter_directory(Path::new(directory), state)?;
+    state.dir_stack.push(previous);
+    list_directories(&state.dir_stack);
+    Ok(())
+}
+
+/// Handles the POPD command.
+///
+/// # Purpose
+/// This function removes the directory on top of the directory stack and changes to it. If the directory
+/// can no longer be entered it is still removed, so a stale entry cannot block the rest of the stack.
+///
+/// # Parameters
+/// - `state`: A mutable reference to the `ShellState` holding the directory stack.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the stack is empty or the
+/// directory cannot be entered.
+///
+/// # Examples
+/// ```
+/// use myshell::{pop_directory, push_directory, ShellState};
+///
+/// let mut state = ShellState::default();
+/// let start = std::env::current_dir().unwrap();
+///
+/// assert!(pop_directory(&mut state).is_err());
+/// push_directory(&["PUSHD", "/"], &mut state).unwrap();
+/// pop_directory(&mut state).unwrap();
+/// assert_eq!(std::env::current_dir().unwrap(), start);
+/// assert!(state.dir_stack.is_empty());
+/// ```
+pub fn pop_directory(state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let directory = state.dir_stack.pop().ok_or("POPD: directory stack empty")?;
+    enter_directory(&directory, state)?;
+    list_directories(&state.dir_stack);
+    Ok(())
+}
+
+/// Handles the DIRS command.
+///
+/// # Purpose
+/// This function prints the current directory followed by the directory stack, most recently pushed first,
+/// on a single line separated by spaces, as bash's `dirs` does.
+///
+/// # Parameters
+/// - `dir_stack`: A slice of `PathBuf` representing the directory stack, with the most recently pushed last.
+///
+/// # Examples
+/// ```
+/// use myshell::list_directories;
+/// use std::path::PathBuf;
+///
+/// list_directories(&[PathBuf::from("/tmp"), PathBuf::from("/usr")]);
+/// ```
+pub fn list_directories(dir_stack: &[PathBuf]) {
+    let mut entries = vec![current_dir_display()];
+    entries.extend(dir_stack.iter().rev().map(|dir| tilde_path(dir)));
+    println!("{}", entries.join(" "));
+}
+
+/// Handles the TIME command.
+///
+/// # Purpose
+/// This function strips the leading `TIME` word, dispatches the rest of the line through `match_inputs`
+/// exactly as if it had been typed on its own, and prints the wall-clock time the command took.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`TIME`) and the rest is the command line to time.
+/// - `state`: A mutable reference to the `ShellState` the timed command runs against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>` with the result of the timed command, whose exit
+/// status is left in `state.last_status`. The elapsed time is printed even if the command fails.
+///
+/// # Examples
+/// ```
+/// use myshell::{time_command, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// time_command(&["TIME", "HELP"], &mut state).unwrap();
+/// time_command(&["TIME", "false"], &mut state).unwrap();
+/// assert_eq!(state.last_status, 1);
+/// ```
+pub fn time_command(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() < 2 {
+        println!("Usage: TIME <command> [args...]");
+        return Ok(());
+    }
+
+    let start = Instant::now();
+    let result = match_inputs(&inputs[1..], state);
+    println!("real {}", format_duration(start.elapsed()));
+    result
+}
+
+/// Formats a duration for display.
+///
+/// # Purpose
+/// This function renders a duration in a human-readable form, using milliseconds below one second
+/// and seconds otherwise.
+///
+/// # Parameters
+/// - `duration`: A `Duration` to format.
+///
+/// # Returns
+/// This function returns the formatted duration as a `String`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use myshell::format_duration;
+///
+/// assert_eq!(format_duration(Duration::from_micros(12_345)), "12.345ms");
+/// assert_eq!(format_duration(Duration::from_millis(1_500)), "1.500s");
+/// ```
+pub fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+    } else {
+        format!("{:.3}s", duration.as_secs_f64())
+    }
+}
+
+/// Handles the SLEEP command.
+///
+/// # Purpose
+/// This function pauses the shell for a number of seconds, which may have a fractional part, so that scripts
+/// can wait without an external `sleep` program, which Windows does not have. The wait is made in short
+/// slices so that Ctrl+C, which sets `INTERRUPTED`, can cut it short.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SLEEP`) and the second the number of seconds.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the argument is missing, or is
+/// not a non-negative number.
+///
+/// # Examples
+/// ```
+/// use std::time::{Duration, Instant};
+/// use myshell::sleep_command;
+///
+/// let start = Instant::now();
+/// sleep_command(&["SLEEP", "0.2"]).unwrap();
+/// assert!(start.elapsed() >= Duration::from_millis(200));
+///
+/// assert!(sleep_command(&["SLEEP", "-1"]).is_err());
+/// assert!(sleep_command(&["SLEEP", "soon"]).is_err());
+/// assert!(sleep_command(&["SLEEP"]).is_err());
+/// ```
+pub fn sleep_command(inputs: &[&str]) -> Result<(), Box<dyn Error>> {
+    let [_, seconds] = inputs else {
+        return Err("Usage: SLEEP <seconds>".into());
+    };
+    let duration = seconds
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("SLEEP: '{}' is not a number of seconds", seconds))?;
+
+    let end = Instant::now() + duration;
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let left = end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        std::thread::sleep(left.min(Duration::from_millis(50)));
+    }
+    Ok(())
+}
+
+/// The `$?` of a command killed by `TIMEOUT`, the same code GNU `timeout` uses.
+pub const TIMEOUT_STATUS: i32 = 124;
+
+/// Handles the TIMEOUT command.
+///
+/// # Purpose
+/// This function runs an alias or external command with a time limit. The command is spawned and polled
+/// until it exits; if it is still running when the limit passes it is killed. Either way, a line on
+/// standard error reports whether it completed or was killed.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`TIMEOUT`), the second the limit in seconds, and the rest the command line.
+/// - `state`: A reference to the `ShellState` holding the aliases and the environment for the command.
+///
+/// # Returns
+/// This function returns a `Result<i32, Box<dyn Error>>` with the command's exit status, or `TIMEOUT_STATUS`
+/// if it was killed. It returns an `Err` if the limit is not a non-negative number, the command is a built-in,
+/// or it cannot be started.
+///
+/// # Examples
+/// ```
+/// use myshell::{timeout_command, ShellState, TIMEOUT_STATUS};
+///
+/// let state = ShellState::default();
+///
+/// assert_eq!(timeout_command(&["TIMEOUT", "5", "true"], &state).unwrap(), 0);
+/// assert_eq!(timeout_command(&["TIMEOUT", "0.1", "sleep", "5"], &state).unwrap(), TIMEOUT_STATUS);
+/// assert!(timeout_command(&["TIMEOUT", "soon", "true"], &state).is_err());
+/// assert!(timeout_command(&["TIMEOUT", "5", "HELP"], &state).is_err());
+/// ```
+pub fn timeout_command(inputs: &[&str], state: &ShellState) -> Result<i32, Box<dyn Error>> {
+    let [_, seconds, command, args @ ..] = inputs else {
+        return Err("Usage: TIMEOUT <seconds> <command> [args...]".into());
+    };
+    let limit = seconds
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("TIMEOUT: '{}' is not a number of seconds", seconds))?;
+    if BUILTINS.iter().any(|builtin| builtin.name == *command) {
+        return Err(format!("TIMEOUT: cannot time out the built-in command '{}'", command).into());
+    }
+
+    let words: Vec<&str> = match state.aliases.get(*command) {
+        Some(alias_command) => alias_command.command.split_whitespace().chain(args.iter().copied()).collect(),
+        None => std::iter::once(*command).chain(args.iter().copied()).collect(),
+    };
+    let Some((program, words)) = words.split_first() else {
+        return Err(format!("TIMEOUT: the alias '{}' has no command", command).into());
+    };
+    let mut process = process::Command::new(program_path(program, state));
+    process.args(words).env_clear().envs(&state.environment);
+    let mut child = spawn_process(program, &mut process)?;
+
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            eprintln!("TIMEOUT: '{}' completed in {}", command, format_duration(start.elapsed()));
+            return Ok(exit_code(status));
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= limit {
+            child.kill()?;
+            child.wait()?;
+            eprintln!("TIMEOUT: '{}' killed after {}", command, format_duration(limit));
+            return Ok(TIMEOUT_STATUS);
+        }
+        std::thread::sleep((limit - elapsed).min(Duration::from_millis(10)));
+    }
+}
+
+/// Handles the REPEAT command.
+///
+/// # Purpose
+/// This function runs the rest of the line through `match_inputs` the given number of times, exactly as if it
+/// had been typed that many times. A run fails if it returns an error or leaves a non-zero `$?`; by default
+/// the first failure stops the loop, while `-k` keeps going. Pressing Ctrl+C always stops the loop.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`REPEAT`), followed by an optional `-k`, the count, and the command line.
+/// - `state`: A mutable reference to the `ShellState` the repeated command runs against.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the count is not a
+/// non-negative integer, no command is given, or a run fails without `-k`.
+///
+/// # Examples
+/// ```
+/// use myshell::{repeat_command, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// repeat_command(&["REPEAT", "2", "SET", "x", "1"], &mut state).unwrap();
+/// repeat_command(&["REPEAT", "0", "false"], &mut state).unwrap();
+/// assert!(repeat_command(&["REPEAT", "3", "false"], &mut state).is_err());
+/// assert!(repeat_command(&["REPEAT", "-k", "3", "false"], &mut state).is_ok());
+/// assert!(repeat_command(&["REPEAT", "-1", "true"], &mut state).is_err());
+/// ```
+pub fn repeat_command(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let keep_going = inputs.get(1) == Some(&"-k");
+    let rest = &inputs[if keep_going { 2 } else { 1 }..];
+    if rest.len() < 2 {
+        return Err("Usage: REPEAT [-k] <count> <command> [args...]".into());
+    }
+    let count: u32 = rest[0]
+        .parse()
+        .map_err(|_| format!("REPEAT: '{}' is not a non-negative integer", rest[0]))?;
+
+    for run in 1..=count {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Err(format!("REPEAT: interrupted before run {} of {}", run, count).into());
+        }
+        let result = match_inputs(&rest[1..], state);
+        let failed = result.is_err() || state.last_status != 0;
+        if failed && !keep_going {
+            // A failing built-in's own error is reported first; an external command has printed its own.
+            result?;
+            return Err(format!("REPEAT: run {} of {} failed, stopping", run, count).into());
+        }
+        if let Err(e) = result {
+            eprintln!("{}Error: {}", state.error_prefix(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Handles the STOP command, and `exit`, `quit` and `logout`.
+///
+/// # Purpose
+/// This function asks the shell to exit with the status given as the argument, or `0` without one, by setting
+/// `state.exit_status`. The commands being run stop there, and whoever is running them ends the shell through
+/// `shutdown`. If background jobs are still running in an interactive session, the first `STOP` only warns
+/// about them (see `confirm_exit`) and a second one exits.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`STOP`) and the optional second word is the exit status.
+/// - `state`: A mutable reference to the `ShellState` being shut down.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the status is not an integer or
+/// there is more than one argument, and `Ok(())` otherwise, whether or not exiting was held back.
+///
+/// # Examples
+/// ```
+/// use myshell::{stop_shell, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// assert!(stop_shell(&["STOP", "soon"], &mut state).is_err());
+/// assert!(stop_shell(&["STOP", "1", "2"], &mut state).is_err());
+/// assert_eq!(state.exit_status, None);
+///
+/// stop_shell(&["STOP", "3"], &mut state).unwrap();
+/// assert_eq!(state.exit_status, Some(3));
+/// ```
+pub fn stop_shell(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let status = match inputs.get(1..) {
+        Some([]) | None => 0,
+        Some([status]) => status.parse().map_err(|_| format!("STOP: '{}' is not a valid exit status", status))?,
+        _ => return Err("Usage: STOP [status]".into()),
+    };
+
+    if confirm_exit(state) {
+        state.exit_status = Some(status);
+    }
+    Ok(())
+}
+
+/// Decides whether the shell may exit while background jobs are running.
+///
+/// # Purpose
+/// This function guards both ways out of an interactive session, `STOP` and the end of input, against
+/// orphaning background jobs. If jobs are still running and the previous attempt to exit was not already held
+/// back, it warns about them, sets `state.exit_warned`, and refuses. Trying again straight away, with `STOP` or
+/// Ctrl+D, ends the remaining jobs and allows the exit. The interactive loop clears `state.exit_warned` after any
+/// other command line. Scripts and `-c` command lines are never held back.
+///
+/// # Parameters
+/// - `state`: A mutable reference to the `ShellState` holding the job list.
+///
+/// # Returns
+/// This function returns `true` if the shell may exit now, and `false` if it printed a warning instead.
+///
+/// # Examples
+/// ```
+/// use myshell::{confirm_exit, start_job, Redirections, ShellState};
+///
+/// let mut state = ShellState { interactive: true, ..ShellState::default() };
+/// assert!(confirm_exit(&mut state));
+///
+/// start_job("sleep", &["10"], &Redirections::default(), &mut state).unwrap();
+/// assert!(!confirm_exit(&mut state));
+/// assert!(confirm_exit(&mut state));
+/// assert!(state.jobs.is_empty());
+/// ```
+pub fn confirm_exit(state: &mut ShellState) -> bool {
+    if !state.interactive {
+        return true;
+    }
+    reap_jobs(&mut state.jobs);
+    if state.jobs.is_empty() {
+        return true;
+    }
+
+    if state.exit_warned {
+        for job in &mut state.jobs {
+            // A job that has already exited cannot be killed, and is waited for all the same.
+            let _ = job.child.kill();
+            let _ = job.child.wait();
+        }
+        state.jobs.clear();
+        return true;
+    }
+    match state.jobs.len() {
+        1 => eprintln!("There is a running job. Exit again to end it and exit anyway."),
+        count => eprintln!("There are {} running jobs. Exit again to end them and exit anyway.", count),
+    }
+    state.exit_warned = true;
+    false
+}
+
+/// Ends the shell.
+///
+/// # Purpose
+/// This function does the work of ending the shell once it has started running commands, after `STOP` or at
+/// the end of input. It saves the aliases if autosave is on (see `autosave_aliases`) and prints
+/// `state.farewell` if the session is interactive and a farewell is set. The process itself is left for the
+/// caller to exit with the status handed back.
+///
+/// # Parameters
+/// - `state`: A reference to the `ShellState` being shut down.
+/// - `status`: An `i32` representing the exit status of the process.
+///
+/// # Returns
+/// This function returns `status`, for the caller to pass to `std::process::exit`.
+///
+/// # Examples
+/// ```
+/// use myshell::{shutdown, ShellState};
+///
+/// let state = ShellState { farewell: "Goodbye!".to_string(), ..ShellState::default() };
+/// assert_eq!(shutdown(&state, 3), 3);
+/// ```
+pub fn shutdown(state: &ShellState, status: i32) -> i32 {
+    if let Err(e) = autosave_aliases(state) {
+        eprintln!("Error saving aliases: {}", e);
+    }
+    if state.interactive && !state.farewell.is_empty() {
+        println!("{}", state.farewell);
+    }
+    status
+}
+
+/// Handles the RESET command.
+///
+/// # Purpose
+/// This function restores the shell name, terminator and its color, prompt template, continuation prompt and
+/// alias limit to the values a new shell starts with, copying them from `ShellState::default()`. Aliases are
+/// kept unless `--all` is given.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`RESET`) and the optional second word is `--all`.
+/// - `state`: A mutable reference to the `ShellState` to reset.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` for any argument other than
+/// `--all`.
+///
+/// # Examples
+/// ```
+/// use myshell::{reset_settings, Alias, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.shellname = "Experiment".to_string();
+/// state.max_aliases = 99;
+/// state.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// reset_settings(&["RESET"], &mut state).unwrap();
+/// assert_eq!(state.status_report(), {
+///     let mut expected = ShellState::default();
+///     expected.aliases = state.aliases.clone();
+///     expected.status_report()
+/// });
+///
+/// reset_settings(&["RESET", "--all"], &mut state).unwrap();
+/// assert_eq!(state.status_report(), ShellState::default().status_report());
+/// ```
+pub fn reset_settings(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let all = match inputs.get(1..) {
+        Some([]) | None => false,
+        Some(["--all"]) => true,
+        _ => return Err("Usage: RESET [--all]".into()),
+    };
+
+    let defaults = ShellState::default();
+    state.shellname = defaults.shellname;
+    state.terminator = defaults.terminator;
+    state.terminator_color = defaults.terminator_color;
+    state.prompt_template = defaults.prompt_template;
+    state.prompt2 = defaults.prompt2;
+    state.max_aliases = defaults.max_aliases;
+    if all {
+        state.aliases.clear();
+    }
+    if state.verbose {
+        println!("{} reset to the defaults.", if all { "Settings and aliases" } else { "Settings" });
+    }
+    Ok(())
+}
+
+/// Handles the SETLOG command.
+///
+/// # Purpose
+/// This function turns the command log on, pointing it at the named file, or off with `SETLOG off`. With no
+/// argument it reports where commands are being logged.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SETLOG`) and the second word is the log file or `off`.
+/// - `log_path`: A mutable reference to the `Option<PathBuf>` holding the current log file.
+///
+/// # Returns
+/// This function does not return any value. It updates `log_path` in-place and prints the new setting.
+///
+/// # Examples
+/// ```
+/// use myshell::set_log;
+/// use std::path::PathBuf;
+///
+/// let mut log_path = None;
+///
+/// set_log(&["SETLOG", "commands.log"], &mut log_path);
+/// assert_eq!(log_path, Some(PathBuf::from("commands.log")));
+///
+/// set_log(&["SETLOG", "off"], &mut log_path);
+/// assert_eq!(log_path, None);
+/// ```
+pub fn set_log(inputs: &[&str], log_path: &mut Option<PathBuf>) {
+    match inputs.get(1) {
+        Some(&"off") => {
+            *log_path = None;
+            println!("Command logging is off.");
+        }
+        Some(file_name) => {
+            *log_path = Some(PathBuf::from(file_name));
+            println!("Logging commands to: {}", file_name);
+        }
+        None => match log_path {
+            Some(path) => println!("Logging commands to: {}", path.display()),
+            None => println!("Command logging is off."),
+        },
+    }
+}
+
+/// Handles the SETOPT command.
+///
+/// # Purpose
+/// This function turns a shell option on or off, prints its value when no new value is given, or lists every
+/// option with its value when called without arguments. The options are `errexit` (see
+/// `ShellState::exits_on_error`), `xtrace` (see `trace_command`), `dryrun` (see `execute`), `case_insensitive_builtins` (see
+/// `ShellState::builtin_named`), `hist_ignore_dups` and `hist_ignore_space` (see
+/// `ShellState::keeps_in_history`), and `verbose` (see `ShellState::verbose`), which can also be set as its
+/// opposite, `quiet`.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SETOPT`), the second is the option name, and the third is `on` or `off`.
+/// - `state`: A mutable reference to the `ShellState` holding the options.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` for an unknown option or a value
+/// other than `on` or `off`.
+///
+/// # Examples
+/// ```
+/// use myshell::{set_option, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// set_option(&["SETOPT", "errexit", "on"], &mut state).unwrap();
+/// assert!(state.errexit);
+///
+/// set_option(&["SETOPT", "quiet", "on"], &mut state).unwrap();
+/// assert!(!state.verbose);
+///
+/// assert!(set_option(&["SETOPT", "errexit", "maybe"], &mut state).is_err());
+/// assert!(set_option(&["SETOPT", "nosuchoption"], &mut state).is_err());
+/// ```
+pub fn set_option(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let mut options = [
+        ("errexit", &mut state.errexit),
+        ("xtrace", &mut state.xtrace),
+        ("dryrun", &mut state.dryrun),
+        ("case_insensitive_builtins", &mut state.case_insensitive_builtins),
+        ("hist_ignore_dups", &mut state.hist_ignore_dups),
+        ("hist_ignore_space", &mut state.hist_ignore_space),
+        ("verbose", &mut state.verbose),
+    ];
+    let Some(&name) = inputs.get(1) else {
+        for (name, value) in &options {
+            println!("{} {}", name, if **value { "on" } else { "off" });
+        }
+        return Ok(());
+    };
+
+    // `quiet` is `verbose` the other way round.
+    let (option, inverted) = if name == "quiet" { ("verbose", true) } else { (name, false) };
+    let (_, value) = options
+        .iter_mut()
+        .find(|(known, _)| *known == option)
+        .ok_or_else(|| format!("Unknown option '{}'", name))?;
+    match inputs.get(2..) {
+        Some([]) => println!("{} {}", name, if **value != inverted { "on" } else { "off" }),
+        Some(["on"]) => **value = !inverted,
+        Some(["off"]) => **value = inverted,
+        _ => return Err("Usage: SETOPT [option [on | off]]".into()),
+    }
+    Ok(())
+}
+
+/// Sets the name of the shell.
+///
+/// # Purpose
+/// This function sets the name of the shell to a new value based on user input. It collects and joins
+/// the input words into a single string, trims surrounding whitespace, and updates the `shellname`
+/// reference accordingly. An empty name is rejected and the previous name is kept.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SETSHELLNAME`) and the rest are the words for the new shell name.
+/// - `shellname`: A mutable reference to a `String` containing the current name of the shell.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It updates the `shellname` reference in-place and
+/// returns an `Err` if the new name would be empty.
+///
+/// # Examples
+/// ```
+/// use myshell::set_shell_name;
+///
+/// let mut shellname = "My Shell".to_string();
+/// let inputs = vec!["SETSHELLNAME", "Custom Shell Name"];
+///
+/// set_shell_name(&inputs, &mut shellname, true).unwrap();
+///
+/// assert_eq!(shellname, "Custom Shell Name");
+///
+/// assert!(set_shell_name(&["SETSHELLNAME"], &mut shellname, true).is_err());
+/// assert_eq!(shellname, "Custom Shell Name");
+/// ```
+pub fn set_shell_name(inputs: &[&str], shellname: &mut String, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let new_name = inputs
+        .iter()
+        .skip(1)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err("Usage: SETSHELLNAME <name...> (the name cannot be empty)".into());
+    }
+
+    *shellname = new_name.to_string();
+    if verbose {
+        println!("Shell name set to: {}", shellname);
+    }
+    Ok(())
+}
+
+/// Sets the terminator for the shell.
+///
+/// # Purpose
+/// This function sets the terminator for the shell based on user input. If a new terminator is provided,
+/// it updates the `terminator` reference with the new value. If no terminator is specified in the input,
+/// it keeps the current terminator as is. The terminator is the rest of the line joined by spaces; a quoted
+/// word can give it leading and trailing spaces. The
+/// `\>` prompt placeholder inserts it exactly as given, before the space the default template adds.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SETTERMINATOR`) and the rest (if present) form the new terminator.
+/// - `terminator`: A mutable reference to a `String` containing the current terminator for the shell.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function does not return any value. It updates the `terminator` reference in-place and prints
+/// a message indicating the new terminator value.
+///
+/// # Examples
+/// ```
+/// use myshell::set_terminator;
+///
+/// let mut terminator = ">".to_string();
+/// let inputs = vec!["SETTERMINATOR", "<"];
+///
+/// set_terminator(&inputs, &mut terminator, true);
+///
+/// assert_eq!(terminator, "<");
+///
+/// set_terminator(&["SETTERMINATOR", " $ "], &mut terminator, true);
+/// assert_eq!(terminator, " $ ");
+///
+/// set_terminator(&["SETTERMINATOR"], &mut terminator, true);
+/// assert_eq!(terminator, " $ ");
+/// ```
+pub fn set_terminator(inputs: &[&str], terminator: &mut String, verbose: bool) {
+    if inputs.len() > 1 {
+        *terminator = inputs[1..].join(" ");
+        if verbose {
+            println!("Terminator set to: '{}'", terminator);
+        }
+    } else {
+        println!("No terminator specified. Using the default terminator: {}", terminator);
+    }
+}
+
+/// Sets the color the prompt terminator is shown in.
+///
+/// # Purpose
+/// This function handles `SETTERMINATOR --color`. The color is one of the names known to `ansi_color_code`,
+/// such as `red` or `green`, and is stored in lowercase; `none` shows the terminator in the terminal's own
+/// color again. The `\>` prompt placeholder wraps the terminator in the color and a reset, which are dropped
+/// with the prompt's other colors when colors are off.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first words are `SETTERMINATOR --color` and the third is the color name.
+/// - `terminator_color`: A mutable reference to the `Option<String>` holding the current color name.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no color is given, or the
+/// color is not known.
+///
+/// # Examples
+/// ```
+/// use myshell::set_terminator_color;
+///
+/// let mut terminator_color = None;
+///
+/// set_terminator_color(&["SETTERMINATOR", "--color", "Green"], &mut terminator_color, true).unwrap();
+/// assert_eq!(terminator_color.as_deref(), Some("green"));
+///
+/// assert!(set_terminator_color(&["SETTERMINATOR", "--color", "mauve"], &mut terminator_color, true).is_err());
+/// assert_eq!(terminator_color.as_deref(), Some("green"));
+///
+/// set_terminator_color(&["SETTERMINATOR", "--color", "none"], &mut terminator_color, true).unwrap();
+/// assert_eq!(terminator_color, None);
+/// ```
+pub fn set_terminator_color(inputs: &[&str], terminator_color: &mut Option<String>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let [_, _, color] = inputs else {
+        return Err("Usage: SETTERMINATOR --color <color>".into());
+    };
+
+    if color.eq_ignore_ascii_case("none") {
+        *terminator_color = None;
+        if verbose {
+            println!("Terminator color cleared.");
+        }
+    } else if ansi_color_code(color).is_some() {
+        *terminator_color = Some(color.to_ascii_lowercase());
+        if verbose {
+            println!("Terminator color set to: {}", color.to_ascii_lowercase());
+        }
+    } else {
+        return Err(format!(
+            "Unknown color '{}' (expected black, red, green, yellow, blue, magenta, cyan, white or none)",
+            color
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Sets the prompt template.
+///
+/// # Purpose
+/// This function handles the `SETPROMPT` command. The remaining words are joined by spaces to form the new
+/// prompt template; a quoted word can keep leading and trailing spaces. See `render_prompt` for the supported placeholders. Without a template the default is restored.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SETPROMPT`) and the rest form the new template.
+/// - `prompt_template`: A mutable reference to a `String` containing the current prompt template.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function does not return any value. It updates the `prompt_template` reference in-place and prints
+/// the new template.
+///
+/// # Examples
+/// ```
+/// use myshell::set_prompt;
+///
+/// let mut prompt_template = "\\s\\> ".to_string();
+/// set_prompt(&["SETPROMPT", "\\u@\\h \\w$ "], &mut prompt_template, true);
+///
+/// assert_eq!(prompt_template, "\\u@\\h \\w$ ");
+/// ```
+pub fn set_prompt(inputs: &[&str], prompt_template: &mut String, verbose: bool) {
+    if inputs.len() < 2 {
+        *prompt_template = DEFAULT_PROMPT_TEMPLATE.to_string();
+        if verbose {
+            println!("Prompt reset to the default: {}", prompt_template);
+        }
+        return;
+    }
+
+    *prompt_template = inputs[1..].join(" ");
+    if verbose {
+        println!("Prompt set to: {}", prompt_template);
+    }
+}
+
+/// Sets the continuation prompt.
+///
+/// # Purpose
+/// This function handles the `SETPROMPT2` command. The remaining words are joined by spaces to form the new
+/// continuation prompt, which is shown exactly as typed while the shell reads the further lines of a
+/// command; a quoted word can keep its spaces. Without a prompt the default, `CONTINUATION_PROMPT`, is
+/// restored.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SETPROMPT2`) and the rest form the new prompt.
+/// - `prompt2`: A mutable reference to a `String` containing the current continuation prompt.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function does not return any value. It updates the `prompt2` reference in-place and prints the new
+/// prompt.
+///
+/// # Examples
+/// ```
+/// use myshell::{set_prompt2, CONTINUATION_PROMPT};
+///
+/// let mut prompt2 = CONTINUATION_PROMPT.to_string();
+/// set_prompt2(&["SETPROMPT2", "... "], &mut prompt2, true);
+/// assert_eq!(prompt2, "... ");
+///
+/// set_prompt2(&["SETPROMPT2"], &mut prompt2, true);
+/// assert_eq!(prompt2, "> ");
+/// ```
+pub fn set_prompt2(inputs: &[&str], prompt2: &mut String, verbose: bool) {
+    if inputs.len() < 2 {
+        *prompt2 = CONTINUATION_PROMPT.to_string();
+        if verbose {
+            println!("Continuation prompt reset to the default: '{}'", prompt2);
+        }
+        return;
+    }
+
+    *prompt2 = inputs[1..].join(" ");
+    if verbose {
+        println!("Continuation prompt set to: '{}'", prompt2);
+    }
+}
+
+/// Manages the alias list.
+///
+/// # Purpose
+/// This function manages the alias list based on user input. It can perform three different operations:
+///
+/// 1. If no arguments are provided, it prints the current alias list, as `LISTNEWNAMES` does (see
+///    `format_aliases`).
+/// 2. If one argument is provided, it deletes the alias with the given name if it exists.
+/// 3. If two arguments are provided, it defines or updates an alias with the first argument as the new alias name
+///    and the second argument as the command associated with the alias. They may be followed by `--desc` and a
+///    description of the alias.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`NEWNAME`) and the rest are arguments.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It manages the `aliases` reference in-place and prints
+/// messages to indicate the result of the operation, and returns an `Err` if the new alias name is invalid.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::set_new_name;
+///
+/// let mut aliases = HashMap::new();
+/// let inputs = vec!["NEWNAME", "myalias", "mycommand"];
+///
+/// set_new_name(&inputs, &mut aliases, true).unwrap();
+///
+/// assert_eq!(aliases["myalias"].command, "mycommand");
+///
+/// set_new_name(&["NEWNAME", "ll", "ls -l", "--desc", "long listing"], &mut aliases, true).unwrap();
+/// assert_eq!(aliases["ll"].description.as_deref(), Some("long listing"));
+///
+/// assert!(set_new_name(&["NEWNAME", "STOP", "ls"], &mut aliases, true).is_err());
+/// assert!(!aliases.contains_key("STOP"));
+///
+/// assert!(set_new_name(&["NEWNAME", "blank", "  "], &mut aliases, true).is_err());
+/// assert!(!aliases.contains_key("blank"));
+/// ```
+pub fn set_new_name(inputs: &[&str], aliases: &mut HashMap<String, Alias>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    if inputs.len() == 1 {
+        // No arguments provided, print the alias list
+        print!("{}", format_aliases(aliases));
+    } else if inputs.len() == 2 {
+        // Delete the alias if it exists
+        remove_aliases(&inputs[1..], aliases, verbose);
+    } else if inputs.len() == 3 || (inputs.len() == 5 && inputs[3] == "--desc") {
+        // Create or update an alias
+        define_alias(inputs[1], inputs[2], inputs.get(4).copied(), aliases, verbose)?;
+    } else {
+        println!("Invalid usage of NEWNAME command.");
+    }
+
+    Ok(())
+}
+
+/// Defines or replaces an alias after checking its name and command, as `NEWNAME` and `ALIAS` do.
+fn define_alias(name: &str, command: &str, description: Option<&str>, aliases: &mut HashMap<String, Alias>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    validate_alias_name(name)?;
+    if command.trim().is_empty() {
+        return Err(format!("The command for alias '{}' cannot be empty.", name).into());
+    }
+    let description = description.map(str::to_string);
+    // A redefined alias keeps counting from where it was.
+    let usage_count = aliases.get(name).map_or(0, |alias| alias.usage_count);
+    aliases.insert(name.to_string(), Alias { command: command.to_string(), description, usage_count });
+    if verbose {
+        println!("Alias '{}' defined for '{}'.", name, command);
+    }
+    Ok(())
+}
+
+/// Handles the ALIAS command, also run as `alias`.
+///
+/// # Purpose
+/// This function accepts bash's `alias` syntax, so that alias definitions copied from a `.bashrc` work as they
+/// are. Each argument of the form `name=command` defines or replaces an alias, as `NEWNAME name command`
+/// does; the tokenizer has already removed any quotes, so `alias ll='ls -l'` and `alias "ll=ls -l"` are the
+/// same. An argument without `=` prints that alias, and no arguments print every alias, sorted by name. Aliases
+/// are printed as `alias name='command'`, which reads back as the same alias.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`ALIAS` or `alias`) and the rest are definitions or names.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` naming the first definition
+/// with an invalid name or an empty command, or the names that are not aliases, after handling every other
+/// argument.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{alias_command, tokenize};
+///
+/// let mut aliases = HashMap::new();
+/// let words = tokenize("alias ll='ls -l' gs=\"git status\"").unwrap();
+/// let inputs: Vec<&str> = words.iter().map(String::as_str).collect();
+///
+/// alias_command(&inputs, &mut aliases, true).unwrap();
+/// assert_eq!(aliases["ll"].command, "ls -l");
+/// assert_eq!(aliases["gs"].command, "git status");
+///
+/// assert!(alias_command(&["alias", "ll"], &mut aliases, true).is_ok());
+/// assert!(alias_command(&["alias", "nope"], &mut aliases, true).is_err());
+/// assert!(alias_command(&["alias", "empty="], &mut aliases, true).is_err());
+/// ```
+pub fn alias_command(inputs: &[&str], aliases: &mut HashMap<String, Alias>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    if inputs.len() == 1 {
+        let mut names: Vec<&String> = aliases.keys().collect();
+        names.sort();
+        for name in names {
+            println!("{}", format_bash_alias(name, &aliases[name]));
+        }
+        return Ok(());
+    }
+
+    let mut error: Option<Box<dyn Error>> = None;
+    let mut missing = Vec::new();
+    for argument in &inputs[1..] {
+        let result = match argument.split_once('=') {
+            Some((name, command)) => define_alias(name, command, None, aliases, verbose),
+            None => {
+                match aliases.get(*argument) {
+                    Some(alias) => println!("{}", format_bash_alias(argument, alias)),
+                    None => missing.push(*argument),
+                }
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            error.get_or_insert(e);
+        }
+    }
+
+    match error {
+        Some(e) => Err(e),
+        None if !missing.is_empty() => Err(format!("ALIAS: {}: not found", missing.join(", ")).into()),
+        None => Ok(()),
+    }
+}
+
+/// Formats an alias as bash's `alias` prints it, with the command in single quotes.
+fn format_bash_alias(name: &str, alias: &Alias) -> String {
+    format!("alias {}='{}'", name, alias.command.replace('\'', "'\\''"))
+}
+
+/// Handles the GLOBALALIAS command.
+///
+/// # Purpose
+/// This function manages the global aliases, which stand for some text wherever they appear as a word, as
+/// zsh's `alias -g` does (see `expand_global_aliases`). With a name and some text it defines or replaces the
+/// global alias, joining the words of the text with spaces. With only a name it deletes that global alias,
+/// and with no arguments it lists them, sorted.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`GLOBALALIAS`), the second is the name, and the rest are the text.
+/// - `global_aliases`: A mutable reference to the `HashMap<String, String>` of global aliases.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the name is not a valid alias
+/// name (see `validate_alias_name`), or if there is no global alias to delete.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::set_global_alias;
+///
+/// let mut global_aliases = HashMap::new();
+///
+/// set_global_alias(&["GLOBALALIAS", "G", "| grep"], &mut global_aliases, true).unwrap();
+/// assert_eq!(global_aliases.get("G"), Some(&"| grep".to_string()));
+///
+/// set_global_alias(&["GLOBALALIAS", "G"], &mut global_aliases, true).unwrap();
+/// assert!(global_aliases.is_empty());
+/// assert!(set_global_alias(&["GLOBALALIAS", "G"], &mut global_aliases, true).is_err());
+/// assert!(set_global_alias(&["GLOBALALIAS", "a|b", "x"], &mut global_aliases, true).is_err());
+/// ```
+pub fn set_global_alias(inputs: &[&str], global_aliases: &mut HashMap<String, String>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let Some(&name) = inputs.get(1) else {
+        let mut sorted: Vec<(&String, &String)> = global_aliases.iter().collect();
+        sorted.sort();
+        println!("Global aliases:");
+        for (name, text) in sorted {
+            println!("{} - {}", name, text);
+        }
+        return Ok(());
+    };
+
+    if inputs.len() == 2 {
+        global_aliases.remove(name).ok_or_else(|| format!("No global alias named '{}'", name))?;
+        if verbose {
+            println!("Global alias '{}' removed.", name);
+        }
+    } else {
+        validate_alias_name(name)?;
+        let text = inputs[2..].join(" ");
+        if verbose {
+            println!("Global alias '{}' defined for '{}'.", name, text);
+        }
+        global_aliases.insert(name.to_string(), text);
+    }
+    Ok(())
+}
+
+/// Replaces the global aliases in a command line.
+///
+/// # Purpose
+/// This function is the expansion pass for global aliases, run on the line as typed before any other
+/// expansion so that the text of an alias can hold operators such as `|`. Every word that is exactly the name
+/// of a global alias is replaced by the alias's text, wherever it is in the line. A word that is quoted or
+/// escaped in any way, or that is part of `$(...)` or `${...}`, is never replaced, so `echo "G"` and
+/// `echo \G` print `G`. The replacement text is not searched for further global aliases. A `GLOBALALIAS`
+/// command line is left alone, so that global aliases can be redefined and deleted by name.
+///
+/// # Parameters
+/// - `line`: A `&str` representing the command line as typed.
+/// - `global_aliases`: A reference to the `HashMap<String, String>` of global aliases.
+///
+/// # Returns
+/// This function returns the line with its global aliases replaced, as a `String`.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::expand_global_aliases;
+///
+/// let mut global_aliases = HashMap::new();
+/// global_aliases.insert("G".to_string(), "| grep".to_string());
+///
+/// assert_eq!(expand_global_aliases("ls G foo", &global_aliases), "ls | grep foo");
+/// assert_eq!(expand_global_aliases("GLOBALALIAS G", &global_aliases), "GLOBALALIAS G");
+/// assert_eq!(expand_global_aliases("echo 'G' \"a G b\" \\G xG", &global_aliases), "echo 'G' \"a G b\" \\G xG");
+/// ```
+pub fn expand_global_aliases(line: &str, global_aliases: &HashMap<String, String>) -> String {
+    if global_aliases.is_empty() || line.split_whitespace().next() == Some("GLOBALALIAS") {
+        return line.to_string();
+    }
+
+    let mut expanded = String::with_capacity(line.len());
+    let mut word_start = 0;
+    let boundaries = braces::unquoted_chars(line)
+        .into_iter()
+        .filter(|(_, c)| c.is_whitespace())
+        .map(|(index, c)| (index, index + c.len_utf8()))
+        .chain(std::iter::once((line.len(), line.len())));
+    for (space_start, space_end) in boundaries {
+        let word = &line[word_start..space_start];
+        expanded.push_str(global_aliases.get(word).map_or(word, String::as_str));
+        expanded.push_str(&line[space_start..space_end]);
+        word_start = space_end;
+    }
+    expanded
+}
+
+/// Handles the SUFFIXALIAS command.
+///
+/// # Purpose
+/// This function manages the suffix aliases, which run files by their extension, as zsh's `alias -s` does.
+/// With a suffix and a command it defines or replaces the suffix alias, so that typing a file name ending in
+/// `.suffix` runs the command with the file name as its first argument (see `ShellState::suffix_alias_for`).
+/// With only a suffix it deletes that suffix alias, and with no arguments it lists them, sorted. A leading
+/// `.` on the suffix is ignored.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SUFFIXALIAS`), the second is the suffix, and the rest are the command.
+/// - `suffix_aliases`: A mutable reference to the `HashMap<String, String>` of suffix aliases.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the suffix is empty or contains
+/// a `.` or path separator, or if there is no suffix alias to delete.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::set_suffix_alias;
+///
+/// let mut suffix_aliases = HashMap::new();
+///
+/// set_suffix_alias(&["SUFFIXALIAS", ".py", "python3", "-u"], &mut suffix_aliases, true).unwrap();
+/// assert_eq!(suffix_aliases.get("py"), Some(&"python3 -u".to_string()));
+///
+/// set_suffix_alias(&["SUFFIXALIAS", "py"], &mut suffix_aliases, true).unwrap();
+/// assert!(suffix_aliases.is_empty());
+/// assert!(set_suffix_alias(&["SUFFIXALIAS", "py"], &mut suffix_aliases, true).is_err());
+/// assert!(set_suffix_alias(&["SUFFIXALIAS", "tar.gz", "tar", "xzf"], &mut suffix_aliases, true).is_err());
+/// ```
+pub fn set_suffix_alias(inputs: &[&str], suffix_aliases: &mut HashMap<String, String>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let Some(suffix) = inputs.get(1).map(|suffix| suffix.strip_prefix('.').unwrap_or(suffix)) else {
+        let mut sorted: Vec<(&String, &String)> = suffix_aliases.iter().collect();
+        sorted.sort();
+        println!("Suffix aliases:");
+        for (suffix, command) in sorted {
+            println!(".{} - {}", suffix, command);
+        }
+        return Ok(());
+    };
+    if suffix.is_empty() || suffix.contains('.') || suffix.contains(std::path::is_separator) {
+        return Err(format!("'{}' is not a valid suffix", inputs[1]).into());
+    }
+
+    if inputs.len() == 2 {
+        suffix_aliases.remove(suffix).ok_or_else(|| format!("No suffix alias for '.{}'", suffix))?;
+        if verbose {
+            println!("Suffix alias for '.{}' removed.", suffix);
+        }
+    } else {
+        let command = inputs[2..].join(" ");
+        if verbose {
+            println!("Files ending in '.{}' now run with '{}'.", suffix, command);
+        }
+        suffix_aliases.insert(suffix.to_string(), command);
+    }
+    Ok(())
+}
+
+/// Checks that a name can be used as an alias.
+///
+/// # Purpose
+/// This function rejects alias names that could never be typed or that would hide a built-in command:
+/// empty names, names containing whitespace or the shell operators `|`, `&`, `>`, `<`, and `;`, and names
+/// matching any keyword that runs a built-in command (see `default_keywords`), such as `STOP`, `exit` or
+/// `alias`, in any case, since `SETOPT case_insensitive_builtins` makes those run the built-in too.
+///
+/// # Parameters
+/// - `name`: A `&str` representing the proposed alias name.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns `Ok(())` if the name is valid and returns
+/// an `Err` explaining why it was rejected otherwise.
+///
+/// # Examples
+/// ```
+/// use myshell::validate_alias_name;
+///
+/// assert!(validate_alias_name("ll").is_ok());
+/// assert!(validate_alias_name("").is_err());
+/// assert!(validate_alias_name("my alias").is_err());
+/// assert!(validate_alias_name("tab\tname").is_err());
+/// assert!(validate_alias_name("a|b").is_err());
+/// assert!(validate_alias_name("a&b").is_err());
+/// assert!(validate_alias_name("a>b").is_err());
+/// assert!(validate_alias_name("a<b").is_err());
+/// assert!(validate_alias_name("a;b").is_err());
+/// assert!(validate_alias_name("STOP").is_err());
+/// assert!(validate_alias_name("NEWNAME").is_err());
+/// assert!(validate_alias_name("exit").is_err());
+/// assert!(validate_alias_name("Alias").is_err());
+/// assert!(validate_alias_name("newName").is_err());
+/// ```
+pub fn validate_alias_name(name: &str) -> Result<(), Box<dyn Error>> {
+    const OPERATORS: [char; 5] = ['|', '&', '>', '<', ';'];
+
+    if name.is_empty() {
+        Err("Alias name cannot be empty.".into())
+    } else if name.chars().any(char::is_whitespace) {
+        Err(format!("Alias name '{}' cannot contain whitespace.", name).into())
+    } else if let Some(operator) = name.chars().find(|c| OPERATORS.contains(c)) {
+        Err(format!("Alias name '{}' cannot contain the operator '{}'.", name, operator).into())
+    } else if is_keyword(name, &default_keywords()) {
+        Err(format!("Alias name '{}' is a built-in command.", name).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Tells whether a name is one of `keywords` in any case, and so may run a built-in command instead of
+/// anything the user defines under it (see `ShellState::builtin_named`).
+fn is_keyword(name: &str, keywords: &HashMap<String, BuiltinKind>) -> bool {
+    keywords.keys().any(|keyword| keyword.eq_ignore_ascii_case(name))
+}
+
+/// Removes the named aliases.
+///
+/// # Purpose
+/// This function removes each named alias from the `aliases` map, printing whether each one was deleted
+/// or did not exist. It is the shared removal logic behind `UNALIAS` and the single-argument form of `NEWNAME`.
+///
+/// # Parameters
+/// - `names`: A slice of `&str` containing the alias names to remove.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `verbose`: A `bool` telling whether to confirm each deletion; `SETOPT quiet on` turns it off. Names that
+///   are not aliases are reported either way.
+///
+/// # Returns
+/// This function returns the number of aliases that were actually removed, as a `usize`.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{remove_aliases, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// assert_eq!(remove_aliases(&["ll", "missing"], &mut aliases, true), 1);
+/// assert!(aliases.is_empty());
+/// ```
+pub fn remove_aliases(names: &[&str], aliases: &mut HashMap<String, Alias>, verbose: bool) -> usize {
+    let mut removed = 0;
+    for &name in names {
+        if aliases.remove(name).is_some() {
+            if verbose {
+                println!("Alias '{}' deleted.", name);
+            }
+            removed += 1;
+        } else {
+            println!("Alias '{}' does not exist.", name);
+        }
+    }
+    removed
+}
+
+/// Handles the UNALIAS command.
+///
+/// # Purpose
+/// This function removes every alias named after the `UNALIAS` command and reports how many were removed.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`UNALIAS`) and the rest are the aliases to remove.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `verbose`: A `bool` telling whether to print the confirmations; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function does not return any value. It updates the `aliases` map in-place and prints the result.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{unalias, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// unalias(&["UNALIAS", "ll"], &mut aliases, true);
+///
+/// assert!(aliases.is_empty());
+/// ```
+pub fn unalias(inputs: &[&str], aliases: &mut HashMap<String, Alias>, verbose: bool) {
+    if inputs.len() < 2 {
+        println!("Usage: UNALIAS <alias...>");
+        return;
+    }
+
+    let removed = remove_aliases(&inputs[1..], aliases, verbose);
+    if verbose {
+        println!("{} alias{} removed.", removed, if removed == 1 { "" } else { "es" });
+    }
+}
+
+/// Handles the CLEARHISTORY command.
+///
+/// # Purpose
+/// This function empties the command history with `ShellState::forget_history`, so that neither `HISTORY` nor
+/// Up and Down at the prompt recall earlier commands. It asks the user to confirm first unless `--yes` is given;
+/// anything other than `y` or `yes`, including the end of input, leaves the history untouched. The history is
+/// only kept in memory, so there is no file to truncate.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`CLEARHISTORY`) and the optional second word is `--yes`.
+/// - `state`: A mutable reference to the `ShellState` holding the history.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` for any argument other than
+/// `--yes`, or if the confirmation cannot be read.
+///
+/// # Examples
+/// ```
+/// use myshell::{clear_history, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.record_history("ls");
+///
+/// clear_history(&["CLEARHISTORY", "--yes"], &mut state).unwrap();
+/// assert!(state.history.is_empty());
+/// assert!(clear_history(&["CLEARHISTORY", "--no"], &mut state).is_err());
+/// ```
+pub fn clear_history(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let confirmed = match inputs.get(1..) {
+        Some([]) | None => false,
+        Some(["--yes"]) => true,
+        _ => return Err("Usage: CLEARHISTORY [--yes]".into()),
+    };
+
+    if !confirmed {
+        let answer = read_input(&format!("Clear all {} history entries? [y/N] ", state.history.len()))?.unwrap_or_default();
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("History left as it was.");
+            return Ok(());
+        }
+    }
+
+    let removed = state.forget_history();
+    println!("{} history entr{} removed.", removed, if removed == 1 { "y" } else { "ies" });
+    Ok(())
+}
+
+/// Handles the ALIASCLEAR command.
+///
+/// # Purpose
+/// This function asks the user to confirm, then removes every alias and reports how many were removed.
+/// Anything other than `y` or `yes`, including the end of input, leaves the aliases untouched.
+///
+/// # Parameters
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `verbose`: A `bool` telling whether to report what was removed; `SETOPT quiet on` turns it off. The
+///   question is asked either way.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the confirmation cannot be read.
+///
+/// # Examples
+/// ```no_run
+/// use std::collections::HashMap;
+/// use myshell::{clear_aliases, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// clear_aliases(&mut aliases, true).unwrap(); // Asks for confirmation on stdin.
+/// ```
+pub fn clear_aliases(aliases: &mut HashMap<String, Alias>, verbose: bool) -> Result<(), Box<dyn Error>> {
+    if aliases.is_empty() {
+        if verbose {
+            println!("No aliases to remove.");
+        }
+        return Ok(());
+    }
+
+    let answer = read_input(&format!("Remove all {} aliases? [y/N] ", aliases.len()))?.unwrap_or_default();
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        println!("No aliases removed.");
+        return Ok(());
+    }
+
+    let removed = aliases.len();
+    aliases.clear();
+    if verbose {
+        println!("{} alias{} removed.", removed, if removed == 1 { "" } else { "es" });
+    }
+    Ok(())
+}
+
+/// Reads aliases from a file and populates the alias list.
+///
+/// # Purpose
+/// This function reads aliases from a specified file and populates the `aliases` map with the aliases
+/// found in the file, up to the specified maximum number of aliases (`max_aliases`).
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`READNEWNAMES`) and the second word is the name of the file to read
+///   aliases from.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `max_aliases`: An `usize` indicating the maximum number of aliases allowed.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function does not return any value. It populates the `aliases` map with aliases read from the file,
+/// prints how many were loaded, and handles any errors that may occur during file reading.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{read_new_names, read_aliases_from_file};
+///
+/// let mut aliases = HashMap::new();
+/// let inputs = vec!["READNEWNAMES", "aliases.txt"];
+/// let max_aliases = 10;
+///
+/// read_new_names(&inputs, &mut aliases, max_aliases, true);
+/// ```
+pub fn read_new_names(inputs: &[&str], aliases: &mut HashMap<String, Alias>, max_aliases: usize, verbose: bool) {
+    if inputs.len() != 2 {
+        println!("Usage: READNEWNAMES <file_name>");
+        return;
+    }
+
+    let file_name = inputs[1];
+    match read_aliases_from_file(file_name, aliases, max_aliases) {
+        Ok(loaded) if verbose => println!("Loaded {} alias{} from {}.", loaded, if loaded == 1 { "" } else { "es" }, file_name),
+        Ok(_) => {}
+        Err(e) => eprintln!("Error reading aliases from file: {}", e),
+    }
+}
+
+/// Lists all the aliases that have been defined.
+///
+/// # Purpose
+/// This function lists all the aliases that have been defined and stored in the `aliases` map, sorted by name
+/// (see `format_aliases`).
+///
+/// # Parameters
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+///
+/// # Returns
+/// This function does not return any value. It prints the list of aliases.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{list_new_names, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("mycd".to_string(), Alias::new("cd"));
+/// aliases.insert("mycopy".to_string(), Alias::new("cp"));
+///
+/// list_new_names(&aliases);
+/// ```
+pub fn list_new_names(aliases: &HashMap<String, Alias>) {
+    print!("{}", format_aliases(aliases));
+}
+
+/// Formats the alias list.
+///
+/// # Purpose
+/// This function renders the alias list that `LISTNEWNAMES` and `NEWNAME` without arguments print: an
+/// `Aliases:` heading, then one `name - command` line per alias, sorted by name.
+///
+/// # Parameters
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+///
+/// # Returns
+/// This function returns the listing as a `String`, ending in a newline.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{format_aliases, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("ll".to_string(), Alias::new("ls -l"));
+/// aliases.insert("gst".to_string(), Alias::new("git status"));
+///
+/// assert_eq!(format_aliases(&aliases), "Aliases:\ngst - git status\nll - ls -l\n");
+/// assert_eq!(format_aliases(&HashMap::new()), "Aliases:\n");
+/// ```
+pub fn format_aliases(aliases: &HashMap<String, Alias>) -> String {
+    let mut listing = String::from("Aliases:\n");
+    for (name, alias) in find_aliases(aliases, "") {
+        listing.push_str(&format_alias(name, alias));
+        listing.push('\n');
+    }
+    listing
+}
+
+/// Formats the aliases by how often they have been run.
+///
+/// # Purpose
+/// This function renders the listing printed by `MOSTUSED`: one line per alias with its `usage_count` and then
+/// the alias as `LISTNEWNAMES` shows it, most used first. Aliases used equally often are sorted by name.
+///
+/// # Parameters
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+///
+/// # Returns
+/// This function returns the listing as a `String`, with each line ending in a newline, or
+/// `No aliases defined.` if there are none.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{format_most_used, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("ll".to_string(), Alias { usage_count: 2, ..Alias::new("ls -l") });
+/// aliases.insert("gs".to_string(), Alias { usage_count: 7, ..Alias::new("git status") });
+/// aliases.insert("la".to_string(), Alias { usage_count: 2, ..Alias::new("ls -A") });
+///
+/// assert_eq!(format_most_used(&aliases), "    7  gs - git status\n    2  la - ls -A\n    2  ll - ls -l\n");
+/// assert_eq!(format_most_used(&HashMap::new()), "No aliases defined.\n");
+/// ```
+pub fn format_most_used(aliases: &HashMap<String, Alias>) -> String {
+    let mut sorted = find_aliases(aliases, "");
+    if sorted.is_empty() {
+        return "No aliases defined.\n".to_string();
+    }
+    // The sort is stable, so aliases used equally often stay in name order.
+    sorted.sort_by_key(|(_, alias)| std::cmp::Reverse(alias.usage_count));
+    let mut listing = String::new();
+    for (name, alias) in sorted {
+        listing.push_str(&format!("{:>5}  {}\n", alias.usage_count, format_alias(name, alias)));
+    }
+    listing
+}
+
+/// Formats one alias as a line of the alias list, with its description after a `#` if it has one.
+fn format_alias(name: &str, alias: &Alias) -> String {
+    match &alias.description {
+        Some(description) => format!("{} - {}  # {}", name, alias.command, description),
+        None => format!("{} - {}", name, alias.command),
+    }
+}
+
+/// Handles the SEARCHALIAS command.
+///
+/// # Purpose
+/// This function prints the aliases whose names contain the given text, sorted by name, in the same form as
+/// `LISTNEWNAMES`. It prints `No matching aliases.` if there are none (see `find_aliases`).
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SEARCHALIAS`) and the second word is the text to look for.
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` unless exactly one text is given.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{search_aliases, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("gst".to_string(), Alias::new("git status"));
+///
+/// search_aliases(&["SEARCHALIAS", "gs"], &aliases).unwrap();
+/// assert!(search_aliases(&["SEARCHALIAS"], &aliases).is_err());
+/// ```
+pub fn search_aliases(inputs: &[&str], aliases: &HashMap<String, Alias>) -> Result<(), Box<dyn Error>> {
+    let [_, text] = inputs else {
+        return Err("Usage: SEARCHALIAS <text>".into());
+    };
+
+    let matches = find_aliases(aliases, text);
+    if matches.is_empty() {
+        println!("No matching aliases.");
+    }
+    for (name, alias) in matches {
+        println!("{}", format_alias(name, alias));
+    }
+    Ok(())
+}
+
+/// Finds the aliases whose names contain some text.
+///
+/// # Parameters
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `text`: A `&str` representing the text to look for in the alias names.
+///
+/// # Returns
+/// This function returns the names of the matching aliases and the aliases themselves, sorted by name, as a
+/// `Vec<(&str, &Alias)>`.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{find_aliases, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("gst".to_string(), Alias::new("git status"));
+/// aliases.insert("gco".to_string(), Alias::new("git checkout"));
+/// aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// let commands = |text| find_aliases(&aliases, text).into_iter().map(|(name, alias)| (name, alias.command.as_str())).collect::<Vec<_>>();
+/// assert_eq!(commands("g"), vec![("gco", "git checkout"), ("gst", "git status")]);
+/// assert_eq!(commands("st"), vec![("gst", "git status")]);
+/// assert!(find_aliases(&aliases, "xyz").is_empty());
+/// ```
+pub fn find_aliases<'a>(aliases: &'a HashMap<String, Alias>, text: &str) -> Vec<(&'a str, &'a Alias)> {
+    let mut matches: Vec<(&str, &Alias)> = aliases
+        .iter()
+        .filter(|(name, _)| name.contains(text))
+        .map(|(name, alias)| (name.as_str(), alias))
+        .collect();
+    matches.sort_by_key(|&(name, _)| name);
+    matches
+}
+
+/// Handles the SAVENEWNAMES command.
+///
+/// # Purpose
+/// This function handles the SAVENEWNAMES command, which saves the aliases stored in the `aliases` map to a file.
+/// `--format` picks the format: `text`, the tab-separated lines `save_aliases_to_file` writes for
+/// `READNEWNAMES`; `json`, an object mapping each alias name to its command; `toml`, an `[aliases]` table
+/// as in a settings file, with descriptions and usage counts; or `bash`, the `alias` commands
+/// `EXPORTALIASES` writes. Without `--format` the extension of the file decides (`.json`, `.toml`, `.sh` or
+/// `.bash`), and any other file is saved as text.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SAVENEWNAMES`), followed by the name of the file to save aliases to and,
+///   before or after it, an optional `--format` and format name.
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no file name is given, the
+/// format is unknown, or the file cannot be written.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{save_new_names, Alias};
+///
+/// let path = std::env::temp_dir().join("myshell-save-new-names-example.json");
+/// let path = path.to_str().unwrap();
+/// let mut aliases = HashMap::new();
+/// aliases.insert("say".to_string(), Alias::new("echo \"hi\""));
+///
+/// save_new_names(&["SAVENEWNAMES", path], &aliases).unwrap();
+/// assert_eq!(std::fs::read_to_string(path).unwrap(), "{\n  \"say\": \"echo \\\"hi\\\"\"\n}\n");
+///
+/// save_new_names(&["SAVENEWNAMES", "--format", "bash", path], &aliases).unwrap();
+/// assert_eq!(std::fs::read_to_string(path).unwrap(), "alias say='echo \"hi\"'\n");
+///
+/// assert!(save_new_names(&["SAVENEWNAMES", "--format", "yaml", path], &aliases).is_err());
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn save_new_names(inputs: &[&str], aliases: &HashMap<String, Alias>) -> Result<(), Box<dyn Error>> {
+    const USAGE: &str = "Usage: SAVENEWNAMES [--format text|json|toml|bash] <file_name>";
+    let (file_name, format) = match inputs {
+        [_, file_name] => (*file_name, AliasFormat::from_extension(file_name)),
+        [_, "--format", format, file_name] | [_, file_name, "--format", format] => {
+            let format = AliasFormat::from_name(format).ok_or_else(|| {
+                format!("SAVENEWNAMES: unknown format '{}' (expected text, json, toml or bash)", format)
+            })?;
+            (*file_name, format)
+        }
+        _ => return Err(USAGE.into()),
+    };
+
+    match format {
+        AliasFormat::Text => save_aliases_to_file(file_name, aliases)?,
+        AliasFormat::Json => write_file_atomically(Path::new(file_name), |writer| writer.write_all(aliases_as_json(aliases).as_bytes()))?,
+        AliasFormat::Toml => {
+            let text = aliases_as_toml(aliases)?;
+            write_file_atomically(Path::new(file_name), |writer| writer.write_all(text.as_bytes()))?
+        }
+        AliasFormat::Bash => write_file_atomically(Path::new(file_name), |writer| writer.write_all(aliases_as_bash(aliases).as_bytes()))?,
+    }
+    println!("Aliases saved to file: {}", file_name);
+    Ok(())
+}
+
+/// The formats `SAVENEWNAMES` can write aliases in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AliasFormat {
+    Text,
+    Json,
+    Toml,
+    Bash,
+}
+
+impl AliasFormat {
+    /// Reads a format name given with `--format`, in any case.
+    fn from_name(name: &str) -> Option<AliasFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Some(AliasFormat::Text),
+            "json" => Some(AliasFormat::Json),
+            "toml" => Some(AliasFormat::Toml),
+            "bash" | "sh" => Some(AliasFormat::Bash),
+            _ => None,
+        }
+    }
+
+    /// Picks the format a file's extension names, or text for any other file.
+    fn from_extension(file_name: &str) -> AliasFormat {
+        match Path::new(file_name).extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension != "txt" => AliasFormat::from_name(extension).unwrap_or(AliasFormat::Text),
+            _ => AliasFormat::Text,
+        }
+    }
+}
+
+/// Writes the aliases as a JSON object mapping each name to its command, sorted by name.
+fn aliases_as_json(aliases: &HashMap<String, Alias>) -> String {
+    let quote = |text: &str| {
+        let mut quoted = String::from('"');
+        for c in text.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    };
+    let entries: Vec<String> =
+        find_aliases(aliases, "").into_iter().map(|(name, alias)| format!("  {}: {}", quote(name), quote(&alias.command))).collect();
+    if entries.is_empty() {
+        "{}\n".to_string()
+    } else {
+        format!("{{\n{}\n}}\n", entries.join(",\n"))
+    }
+}
+
+/// Writes the aliases as the `[aliases]` table of a settings file.
+fn aliases_as_toml(aliases: &HashMap<String, Alias>) -> Result<String, Box<dyn Error>> {
+    let settings = Settings { aliases: aliases.iter().map(|(name, alias)| (name.clone(), alias.clone())).collect(), ..Settings::default() };
+    Ok(toml::to_string(&settings)?)
+}
+
+/// Writes the aliases as bash `alias` commands, one per line and sorted by name.
+fn aliases_as_bash(aliases: &HashMap<String, Alias>) -> String {
+    find_aliases(aliases, "").into_iter().map(|(name, alias)| format_bash_alias(name, alias) + "\n").collect()
+}
+
+/// Handles the EXPORTALIASES command.
+///
+/// # Purpose
+/// This function writes the aliases to a file as bash `alias` commands, one per line and sorted by name, so the
+/// file can be sourced from a bash setup. Each command is wrapped in single quotes, with any single quote inside
+/// it written as `'\''`, so bash defines exactly the same command. Unlike `SAVENEWNAMES`, the file is meant for
+/// bash rather than for `READNEWNAMES`.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`EXPORTALIASES`) and the second word is the name of the file to write.
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no file name is given or the
+/// file cannot be written.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{export_aliases, Alias};
+///
+/// let path = std::env::temp_dir().join("myshell-export-aliases-example.sh");
+/// let mut aliases = HashMap::new();
+/// aliases.insert("ll".to_string(), Alias::new("ls -l"));
+/// aliases.insert("say".to_string(), Alias::new("echo it's"));
+///
+/// export_aliases(&["EXPORTALIASES", &path.to_string_lossy()], &aliases).unwrap();
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), "alias ll='ls -l'\nalias say='echo it'\\''s'\n");
+/// assert!(export_aliases(&["EXPORTALIASES"], &aliases).is_err());
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn export_aliases(inputs: &[&str], aliases: &HashMap<String, Alias>) -> Result<(), Box<dyn Error>> {
+    let [_, file_name] = inputs else {
+        return Err("Usage: EXPORTALIASES <file_name>".into());
+    };
+
+    fs::write(file_name, aliases_as_bash(aliases))?;
+
+    println!("Aliases exported to file: {}", file_name);
+    Ok(())
+}
+
+/// Handles the IMPORTALIASES command.
+///
+/// # Purpose
+/// This function loads the aliases declared in a bash file, such as one written by `EXPORTALIASES` or an
+/// existing `~/.bash_aliases`, with `read_bash_aliases_from_file`, and prints how many were loaded.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`IMPORTALIASES`) and the second word is the name of the file to read.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` containing user-defined aliases.
+/// - `max_aliases`: An `usize` indicating the maximum number of aliases allowed.
+/// - `verbose`: A `bool` telling whether to print a confirmation; `SETOPT quiet on` turns it off.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no file name is given or the
+/// file cannot be read.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{export_aliases, import_aliases, Alias};
+///
+/// let path = std::env::temp_dir().join("myshell-import-aliases-example.sh");
+/// let mut aliases = HashMap::new();
+/// aliases.insert("say".to_string(), Alias::new("echo it's  \"here\""));
+/// export_aliases(&["EXPORTALIASES", &path.to_string_lossy()], &aliases).unwrap();
+///
+/// let mut imported = HashMap::new();
+/// import_aliases(&["IMPORTALIASES", &path.to_string_lossy()], &mut imported, 10, true).unwrap();
+/// assert_eq!(imported, aliases);
+/// assert!(import_aliases(&["IMPORTALIASES"], &mut imported, 10, true).is_err());
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn import_aliases(inputs: &[&str], aliases: &mut HashMap<String, Alias>, max_aliases: usize, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let [_, file_name] = inputs else {
+        return Err("Usage: IMPORTALIASES <file_name>".into());
+    };
+
+    let loaded = read_bash_aliases_from_file(file_name, aliases, max_aliases)?;
+    if verbose {
+        println!("Imported {} alias{} from {}.", loaded, if loaded == 1 { "" } else { "es" }, file_name);
+    }
+    Ok(())
+}
+
+/// Handles the SAVESETTINGS command.
+///
+/// # Purpose
+/// This function writes the shell's configuration, everything `Settings` covers, to the named file as TOML.
+/// The file can be restored with `LOADSETTINGS` or used as `~/.myshellrc`.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`SAVESETTINGS`) and the second word is the name of the file to write.
+/// - `state`: A reference to the `ShellState` whose configuration is saved.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no file name is given or the
+/// file cannot be written.
+///
+/// # Examples
+/// ```no_run
+/// use myshell::{save_settings, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// save_settings(&["SAVESETTINGS", "settings.toml"], &state).unwrap();
+/// ```
+pub fn save_settings(inputs: &[&str], state: &ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() != 2 {
+        return Err("Usage: SAVESETTINGS <file_name>".into());
+    }
+
+    write_settings_file(Path::new(inputs[1]), &Settings::from_state(state))?;
+    println!("Settings saved to file: {}", inputs[1]);
+    Ok(())
+}
+
+/// Handles the LOADSETTINGS command.
+///
+/// # Purpose
+/// This function reads a TOML settings file, as written by `SAVESETTINGS`, and applies it to the shell.
+/// The whole file is read before anything is applied, so a malformed file leaves the shell unchanged.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`LOADSETTINGS`) and the second word is the name of the file to read.
+/// - `state`: A mutable reference to the `ShellState` the settings are applied to.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no file name is given or the
+/// file cannot be read or parsed.
+///
+/// # Examples
+/// ```
+/// use myshell::{load_settings, ShellState};
+///
+/// let path = std::env::temp_dir().join("myshell-load-settings-example.toml");
+/// std::fs::write(&path, "shellname = \"Mine\"\n[aliases]\nll = \"ls -l\"\n").unwrap();
+///
+/// let mut state = ShellState::default();
+/// load_settings(&["LOADSETTINGS", path.to_str().unwrap()], &mut state).unwrap();
+///
+/// assert_eq!(state.shellname, "Mine");
+/// assert_eq!(state.terminator, ">");
+/// assert_eq!(state.aliases["ll"].command, "ls -l");
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn load_settings(inputs: &[&str], state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() != 2 {
+        return Err("Usage: LOADSETTINGS <file_name>".into());
+    }
+
+    read_settings_file(Path::new(inputs[1]))?.apply_to(state);
+    if state.verbose {
+        println!("Settings loaded from file: {}", inputs[1]);
+    }
+    Ok(())
+}
+
+/// Handles the RELOAD command.
+///
+/// # Purpose
+/// This function reads the startup configuration file at `state.config_path` again and applies it to the
+/// running shell, as `LOADSETTINGS` would, so that edits to `~/.myshellrc` take effect without a restart. It
+/// then prints each setting that changed with its old and new value, and for the aliases, suffix aliases,
+/// variables, and keywords how many entries were added or changed. The file is read in full before anything
+/// is applied, so a file with errors leaves the session as it was.
+///
+/// # Parameters
+/// - `state`: A mutable reference to the `ShellState` the configuration is applied to.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no configuration path is
+/// known, the file does not exist, or it cannot be read or parsed.
+///
+/// # Examples
+/// ```
+/// use myshell::{reload_config, ShellState};
+///
+/// let path = std::env::temp_dir().join("myshell-reload-example.toml");
+/// std::fs::write(&path, "shellname = \"Reloaded\"\n[aliases]\nll = \"ls -l\"\n").unwrap();
+///
+/// let mut state = ShellState { config_path: Some(path.clone()), ..ShellState::default() };
+/// reload_config(&mut state).unwrap(); // Prints the shell name change and "aliases: 1 added".
+/// assert_eq!(state.shellname, "Reloaded");
+/// assert_eq!(state.aliases["ll"].command, "ls -l");
+///
+/// std::fs::write(&path, "shellname = \"Broken").unwrap();
+/// assert!(reload_config(&mut state).is_err());
+/// assert_eq!(state.shellname, "Reloaded");
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn reload_config(state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let path = state.config_path.clone().ok_or("RELOAD: no configuration file is known, as no home directory is set")?;
+    if !path.exists() {
+        return Err(format!("RELOAD: {} does not exist", path.display()).into());
+    }
+
+    let settings = read_settings_file(&path)?;
+    let before = Settings::from_state(state);
+    settings.apply_to(state);
+    let changes = settings_changes(&before, &Settings::from_state(state))?;
+
+    if changes.is_empty() {
+        println!("Reloaded {}: nothing changed.", path.display());
+    } else {
+        println!("Reloaded {}:", path.display());
+        for change in changes {
+            println!("  {}", change);
+        }
+    }
+    Ok(())
+}
+
+/// The editor `EDITCONFIG` opens when `EDITOR` is not set.
+const DEFAULT_EDITOR: &str = if cfg!(windows) { "notepad" } else { "vi" };
+
+/// Handles the EDITCONFIG command.
+///
+/// # Purpose
+/// This function opens the startup configuration file at `state.config_path` in the editor named by the
+/// `EDITOR` variable, which may include arguments such as `code --wait`, or in `vi` (`notepad` on Windows) if
+/// it is not set. The editor runs in the foreground with the shell's environment; once it exits successfully,
+/// the file is applied with `reload_config`.
+///
+/// # Parameters
+/// - `state`: A mutable reference to the `ShellState` the edited configuration is applied to.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if no configuration path is
+/// known, the editor cannot be started or fails, or the edited file cannot be applied.
+///
+/// # Examples
+/// ```
+/// use myshell::{edit_config, ShellState};
+///
+/// let path = std::env::temp_dir().join("myshell-edit-config-example.toml");
+/// let mut state = ShellState { config_path: Some(path.clone()), ..ShellState::default() };
+/// // An "editor" that writes the file instead of opening it.
+/// state.variables.insert("EDITOR".to_string(), "sh -c 'echo shellname = \\\"Edited\\\" > \"$0\"'".to_string());
+///
+/// edit_config(&mut state).unwrap();
+/// assert_eq!(state.shellname, "Edited");
+///
+/// state.variables.insert("EDITOR".to_string(), "false".to_string());
+/// assert!(edit_config(&mut state).is_err());
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn edit_config(state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let path = state.config_path.clone().ok_or("EDITCONFIG: no configuration file is known, as no home directory is set")?;
+    let editor = lookup_variable("EDITOR", state).filter(|editor| !editor.trim().is_empty());
+    let editor = editor.as_deref().unwrap_or(DEFAULT_EDITOR);
+    let words = tokenize(editor)?;
+    let Some((program, args)) = words.split_first() else {
+        return Err("EDITCONFIG: EDITOR names no program".into());
+    };
+
+    let mut process = process::Command::new(program_path(program, state));
+    process.args(args).arg(&path).env_clear().envs(&state.environment);
+    let status = exit_code(spawn_process(program, &mut process)?.wait()?);
+    if status != 0 {
+        return Err(format!("EDITCONFIG: {} exited with status {}; not reloading", program, status).into());
+    }
+    reload_config(state)
+}
+
+/// Describes how two sets of settings differ, one line per setting, with tables summarized by how many
+/// entries were added or changed.
+fn settings_changes(before: &Settings, after: &Settings) -> Result<Vec<String>, Box<dyn Error>> {
+    let before = toml::Table::try_from(before)?;
+    let after = toml::Table::try_from(after)?;
+    let mut changes = Vec::new();
+
+    for (key, new) in &after {
+        let old = before.get(key);
+        if old == Some(new) {
+            continue;
+        }
+        match (old.and_then(toml::Value::as_table), new.as_table()) {
+            (old_table, Some(new_table)) => {
+                let added = new_table.keys().filter(|name| old_table.is_none_or(|table| !table.contains_key(*name))).count();
+                let changed = new_table
+                    .iter()
+                    .filter(|&(name, value)| old_table.and_then(|table| table.get(name)).is_some_and(|old| old != value))
+                    .count();
+                changes.push(format!("{}: {} added, {} changed", key, added, changed));
+            }
+            _ => match old {
+                Some(old) => changes.push(format!("{}: {} -> {}", key, old, new)),
+                None => changes.push(format!("{}: {}", key, new)),
+            },
+        }
+    }
+    Ok(changes)
+}
+
+/// An error the shell reports in its own words rather than the operating system's.
+#[derive(Debug)]
+pub enum ShellError {
+    /// No program with this name could be found to run.
+    CommandNotFound(String),
+    /// Any other failure to start or talk to a program.
+    Io(io::Error),
+    /// A command line that could not be split into words, such as one with an unterminated quote.
+    Parse(String),
+}
+
+impl std::fmt::Display for ShellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShellError::CommandNotFound(name) => write!(f, "myshell: command not found: {}", name),
+            ShellError::Io(e) => write!(f, "{}", e),
+            ShellError::Parse(message) => write!(f, "myshell: syntax error: {}", message),
+        }
+    }
+}
+
+impl Error for ShellError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ShellError::CommandNotFound(_) | ShellError::Parse(_) => None,
+            ShellError::Io(e) => Some(e),
+        }
+    }
+}
+
+/// What a command name resolves to, in the order the shell checks them.
+#[derive(Debug, PartialEq)]
+pub enum CommandKind {
+    /// A command handled by the shell itself.
+    Builtin,
+    /// A user-defined function.
+    Function,
+    /// A user-defined alias, with the command it expands to.
+    Alias(String),
+    /// An executable found on `$PATH`.
+    External(PathBuf),
+}
+
+/// Classifies a command name.
+///
+/// # Purpose
+/// This function determines what running `name` would do by checking the keyword table of built-in command
+/// names first (see `ShellState::builtin_named`), then the functions and the aliases map, and finally scanning
+/// `$PATH`, mirroring the order used when dispatching commands.
+///
+/// # Parameters
+/// - `name`: A `&str` representing the command name to classify.
+/// - `state`: A reference to the `ShellState` holding the aliases and the shell's `PATH`.
+///
+/// # Returns
+/// This function returns an `Option<CommandKind>` describing the name, or `None` if it is not a built-in,
+/// a function, an alias, or an executable on `$PATH`.
+///
+/// # Examples
+/// ```
+/// use myshell::{classify_command, Alias, CommandKind, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// assert_eq!(classify_command("STOP", &state), Some(CommandKind::Builtin));
+/// assert_eq!(classify_command("exit", &state), Some(CommandKind::Builtin));
+/// assert_eq!(classify_command("ll", &state), Some(CommandKind::Alias("ls -l".to_string())));
+///
+/// state.functions.insert("ll".to_string(), vec!["ls -la".to_string()]);
+/// assert_eq!(classify_command("ll", &state), Some(CommandKind::Function));
+/// assert_eq!(classify_command("no-such-command-anywhere", &state), None);
+/// ```
+pub fn classify_command(name: &str, state: &ShellState) -> Option<CommandKind> {
+    if state.builtin_named(name).is_some() {
+        Some(CommandKind::Builtin)
+    } else if state.functions.contains_key(name) {
+        Some(CommandKind::Function)
+    } else if let Some(alias) = state.aliases.get(name) {
+        Some(CommandKind::Alias(alias.command.clone()))
+    } else {
+        state.command_path(name).map(CommandKind::External)
+    }
+}
+
+/// Handles the HASH command.
+///
+/// # Purpose
+/// This function shows or changes the paths the shell remembers programs by (see `ShellState::command_path`).
+/// Without arguments it lists each remembered name and path, sorted by name; with names it looks each one up
+/// and remembers it; and `HASH -r` forgets every path, so that the next run of each program searches `PATH`
+/// again.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`HASH`), followed by `-r` or the names to look up.
+/// - `state`: A reference to the `ShellState` holding the remembered paths.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if a name is not found on `PATH`,
+/// after looking up every name.
+///
+/// # Examples
+/// ```
+/// use myshell::{hash_command, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// hash_command(&["HASH", "sh"], &state).unwrap();
+/// assert!(state.command_paths.borrow().contains_key("sh"));
+///
+/// hash_command(&["HASH", "-r"], &state).unwrap();
+/// assert!(state.command_paths.borrow().is_empty());
+/// assert!(hash_command(&["HASH", "no-such-command-anywhere"], &state).is_err());
+/// ```
+pub fn hash_command(inputs: &[&str], state: &ShellState) -> Result<(), Box<dyn Error>> {
+    match inputs.get(1..) {
+        Some([]) | None => {
+            let paths = state.command_paths.borrow();
+            let mut sorted: Vec<(&String, &PathBuf)> = paths.iter().collect();
+            sorted.sort();
+            for (name, path) in sorted {
+                println!("{}\t{}", name, path.display());
+            }
+            Ok(())
+        }
+        Some(["-r"]) => {
+            state.command_paths.borrow_mut().clear();
+            Ok(())
+        }
+        Some(names) => {
+            let missing: Vec<&str> = names.iter().copied().filter(|name| state.command_path(name).is_none()).collect();
+            if missing.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("HASH: {} not found", missing.join(", ")).into())
+            }
+        }
+    }
+}
+
+/// Handles the WHICH command.
+///
+/// # Purpose
+/// This function reports what each given name would run: a built-in command, an alias (with the command it
+/// expands to), or the absolute path of the executable found by scanning `$PATH`.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`WHICH`) and the rest are the names to look up.
+/// - `state`: A reference to the `ShellState` holding the aliases and the shell's `PATH`.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns `Ok(())` if every name was found and returns
+/// an `Err` naming the commands that could not be found.
+///
+/// # Examples
+/// ```
+/// use myshell::{which, Alias, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// assert!(which(&["WHICH", "ll", "STOP"], &state).is_ok());
+/// assert!(which(&["WHICH", "no-such-command-anywhere"], &state).is_err());
+/// ```
+pub fn which(inputs: &[&str], state: &ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() < 2 {
+        println!("Usage: WHICH <name...>");
+        return Ok(());
+    }
+
+    let mut missing = Vec::new();
+    for &name in &inputs[1..] {
+        match classify_command(name, state) {
+            Some(CommandKind::Builtin) => println!("{}: shell built-in command", name),
+            Some(CommandKind::Function) => println!("{}: shell function", name),
+            Some(CommandKind::Alias(command)) => println!("{}: aliased to {}", name, command),
+            Some(CommandKind::External(path)) => println!("{}", path.display()),
+            None => missing.push(name),
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} not found", missing.join(", ")).into())
+    }
+}
+
+/// Handles the TYPE command.
+///
+/// # Purpose
+/// This function describes how the shell would interpret each given name, similar to `type` in bash: as a
+/// shell built-in, as a function, as an alias (showing its expansion), or as an external command (showing its
+/// path).
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`TYPE`) and the rest are the names to describe.
+/// - `state`: A reference to the `ShellState` holding the aliases and the shell's `PATH`.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns `Ok(())` if every name could be classified
+/// and returns an `Err` naming the ones that could not.
+///
+/// # Examples
+/// ```
+/// use myshell::{type_command, Alias, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+///
+/// assert!(type_command(&["TYPE", "ll", "NEWNAME"], &state).is_ok());
+/// assert!(type_command(&["TYPE", "no-such-command-anywhere"], &state).is_err());
+/// ```
+pub fn type_command(inputs: &[&str], state: &ShellState) -> Result<(), Box<dyn Error>> {
+    if inputs.len() < 2 {
+        println!("Usage: TYPE <name...>");
+        return Ok(());
+    }
+
+    let mut missing = Vec::new();
+    for &name in &inputs[1..] {
+        match classify_command(name, state) {
+            Some(CommandKind::Builtin) => println!("{} is a shell built-in", name),
+            Some(CommandKind::Function) => println!("{} is a shell function", name),
+            Some(CommandKind::Alias(command)) => println!("{} is aliased to '{}'", name, command),
+            Some(CommandKind::External(path)) => println!("{} is {}", name, path.display()),
+            None => missing.push(name),
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} not found", missing.join(", ")).into())
+    }
+}
+
+/// Locates an executable by scanning `$PATH`.
+///
+/// # Purpose
+/// This function searches each directory listed in `search_path`, a `PATH`-style list, in order, for an
+/// executable file named `name`. A name containing a path separator is checked directly instead, just as
+/// the operating system would when running it. On Windows the extensions listed in `PATHEXT` are also tried.
+///
+/// # Parameters
+/// - `name`: A `&str` representing the command name to look for.
+/// - `search_path`: An `Option<&str>` with the directories to search, usually the shell's `PATH`.
+///
+/// # Returns
+/// This function returns an `Option<PathBuf>` with the path of the first matching executable, or `None`
+/// if no executable with that name exists.
+///
+/// # Examples
+/// ```
+/// use myshell::find_in_path;
+///
+/// if let Some(path) = find_in_path("ls", Some("/usr/bin:/bin")) {
+///     println!("ls is {}", path.display());
+/// }
+/// assert_eq!(find_in_path("no-such-command-anywhere", Some("/usr/bin:/bin")), None);
+/// assert_eq!(find_in_path("ls", None), None);
+/// ```
+pub fn find_in_path(name: &str, search_path: Option<&str>) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+
+    if name.contains(std::path::is_separator) {
+        let path = PathBuf::from(name);
+        return is_executable(&path).then_some(path);
+    }
+
+    let extensions: Vec<String> = if cfg!(windows) {
+        env::var("PATHEXT")
+            .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+            .split(';')
+            .map(str::to_string)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    env::split_paths(search_path?).find_map(|dir| {
+        let candidate = dir.join(name);
+        if is_executable(&candidate) {
+            return Some(candidate);
+        }
+        extensions
+            .iter()
+            .map(|extension| dir.join(format!("{}{}", name, extension)))
+            .find(|candidate| is_executable(candidate))
+    })
+}
+
+/// Checks whether a path is an executable file.
+///
+/// # Purpose
+/// This function reports whether `path` refers to a regular file that can be executed. On Unix this means
+/// at least one execute permission bit is set; elsewhere any regular file counts.
+///
+/// # Parameters
+/// - `path`: A `&Path` representing the file to check.
+///
+/// # Returns
+/// This function returns `true` if the path is an executable file, and `false` otherwise.
+///
+/// # Examples
+/// ```
+/// use std::path::Path;
+/// use myshell::is_executable;
+///
+/// assert!(!is_executable(Path::new("/no/such/file")));
+/// ```
+pub fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    if !metadata.is_file() {
+        return false;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        true
+    }
+}
+
+/// Executes a command with the specified arguments.
+///
+/// # Purpose
+/// This function executes a command with the provided arguments using the `std::process::Command` struct,
+/// connecting its standard streams to the files named by `redirections`. The command receives exactly the
+/// variables in `environment` rather than inheriting the shell process's own environment. On Windows, a
+/// command that is not an executable is retried as `cmd /C command args...`, so that `cmd` internals such
+/// as `dir` and `copy` work too.
+///
+/// # Parameters
+/// - `command`: A `&str` representing the command to be executed.
+/// - `args`: A slice of `&str` representing the arguments to be passed to the command.
+/// - `redirections`: A reference to the `Redirections` to apply to the command's streams.
+/// - `environment`: A reference to a `HashMap<String, String>` containing the command's environment.
+///
+/// # Returns
+/// This function returns a `Result<i32, Box<dyn Error>>`. It returns `Ok` with the command's exit status once
+/// it has run, whether that status is zero or not; a command killed by a signal reports `128` plus the signal
+/// number, as other shells do. It returns an `Err` containing an error message if a redirection file cannot
+/// be opened or the command cannot be started. A program that does not exist is reported as
+/// `ShellError::CommandNotFound`.
+///
+/// # Examples
+/// ```
+/// use myshell::{execute_command, Redirections, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// if let Err(e) = execute_command("ls", &["-l"], &Redirections::default(), &state.environment) {
+///     eprintln!("Error: {}", e);
+/// }
+///
+/// assert_eq!(execute_command("false", &[], &Redirections::default(), &state.environment).unwrap(), 1);
+///
+/// let e = execute_command("no-such-command-anywhere", &[], &Redirections::default(), &state.environment).unwrap_err();
+/// assert_eq!(e.to_string(), "myshell: command not found: no-such-command-anywhere");
+/// ```
+pub fn execute_command(command: &str, args: &[&str], redirections: &Redirections, environment: &HashMap<String, String>) -> Result<i32, Box<dyn Error>> {
+    let prepare = |program: &str, program_args: &[&str]| -> io::Result<process::Command> {
+        let mut process = process::Command::new(program);
+        process.args(program_args).env_clear().envs(environment);
+        redirections.apply(&mut process)?;
+        Ok(process)
+    };
+
+    let mut child = match spawn_process(command, &mut prepare(command, args)?) {
+        Err(e) if cfg!(windows) && matches!(e.downcast_ref(), Some(ShellError::CommandNotFound(_))) => {
+            let cmd_args: Vec<&str> = ["/C", command].into_iter().chain(args.iter().copied()).collect();
+            spawn_process(command, &mut prepare("cmd", &cmd_args)?)?
+        }
+        result => result?,
+    };
+    redirections.feed(&mut child);
+
+    // The child shares the terminal's process group, so Ctrl+C reaches it directly while the shell's own
+    // handler only records the interruption; the shell just waits for the child to finish either way.
+    let status = child.wait()?;
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        println!(); // Start the next prompt on a fresh line after the echoed ^C.
+    }
+    Ok(exit_code(status))
+}
+
+/// Converts a finished process's status into a shell exit code.
+///
+/// # Purpose
+/// This function turns an `ExitStatus` into the number stored in `$?`. A process that exited normally
+/// reports its own code; on Unix, one killed by a signal reports `128` plus the signal number.
+///
+/// # Parameters
+/// - `status`: The `ExitStatus` of the finished process.
+///
+/// # Returns
+/// This function returns the exit code as an `i32`.
+///
+/// # Examples
+/// ```
+/// use myshell::exit_code;
+/// use std::process::Command;
+///
+/// let status = Command::new("sh").args(["-c", "exit 7"]).status().unwrap();
+/// assert_eq!(exit_code(status), 7);
+/// ```
+pub fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Starts a prepared process.
+///
+/// # Purpose
+/// This function spawns `process`, reporting a program that does not exist as `ShellError::CommandNotFound`
+/// rather than as a raw operating system error.
+///
+/// # Parameters
+/// - `command`: A `&str` representing the program name, used in the error.
+/// - `process`: A mutable reference to the `Command` to spawn.
+///
+/// # Returns
+/// This function returns a `Result<Child, Box<dyn Error>>` with the running child process.
+///
+/// # Examples
+/// ```
+/// use myshell::spawn_process;
+/// use std::process::Command;
+///
+/// let mut child = spawn_process("true", &mut Command::new("true")).unwrap();
+/// assert!(child.wait().unwrap().success());
+///
+/// let e = spawn_process("no-such-command-anywhere", &mut Command::new("no-such-command-anywhere")).unwrap_err();
+/// assert_eq!(e.to_string(), "myshell: command not found: no-such-command-anywhere");
+/// ```
+pub fn spawn_process(command: &str, process: &mut process::Command) -> Result<Child, Box<dyn Error>> {
+    process.spawn().map_err(|e| -> Box<dyn Error> {
+        if e.kind() == io::ErrorKind::NotFound {
+            ShellError::CommandNotFound(command.to_string()).into()
+        } else {
+            e.into()
+        }
+    })
+}
+
+/// A command started in the background with a trailing `&`.
+#[derive(Debug)]
+pub struct Job {
+    /// The job number, used as `%n` by `KILL`.
+    pub id: usize,
+    /// The command line the job was started with, as shown by `JOBS`.
+    pub command: String,
+    /// The running process.
+    pub child: Child,
+}
+
+/// Starts a command in the background.
+///
+/// # Purpose
+/// This function starts an alias or external command without waiting for it, adds it to the job list and
+/// prints its job number and process ID. A background job reads from the null device unless its input is
+/// redirected, and on Unix it is put in its own process group so that Ctrl+C at the terminal does not
+/// reach it.
+///
+/// # Parameters
+/// - `command`: A `&str` representing the alias or program to run.
+/// - `args`: A slice of `&str` representing the arguments to pass to an external program.
+/// - `redirections`: A reference to the `Redirections` to apply to the program's streams.
+/// - `state`: A mutable reference to the `ShellState` holding the aliases, the environment and the job list.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the command cannot be started.
+///
+/// # Examples
+/// ```
+/// use myshell::{start_job, Redirections, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// start_job("sleep", &["1"], &Redirections::default(), &mut state).unwrap();
+/// assert_eq!(state.jobs[0].id, 1);
+/// assert_eq!(state.jobs[0].command, "sleep 1");
+/// # state.jobs[0].child.kill().unwrap();
+/// ```
+pub fn start_job(command: &str, args: &[&str], redirections: &Redirections, state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    let command_line: Vec<&str> = std::iter::once(command).chain(args.iter().copied()).collect();
+    let words: Vec<&str> = match state.aliases.get(command) {
+        Some(alias_command) => alias_command.command.split_whitespace().chain(args.iter().copied()).collect(),
+        None => command_line.clone(),
+    };
+
+    let Some((program, words)) = words.split_first() else {
+        return Err(format!("The alias '{}' has no command", command).into());
+    };
+    let mut process = process::Command::new(program_path(program, state));
+    process.args(words).env_clear().envs(&state.environment).stdin(Stdio::null());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    redirections.apply(&mut process)?;
+    let mut child = spawn_process(program, &mut process)?;
+    redirections.feed(&mut child);
+
+    let id = state.jobs.last().map_or(1, |job| job.id + 1);
+    println!("[{}] {}", id, child.id());
+    state.jobs.push(Job { id, command: command_line.join(" "), child });
+    Ok(())
+}
+
+/// Reports and forgets background jobs that have finished.
+///
+/// # Purpose
+/// This function checks each job without blocking, prints a `Done` line with the exit status for every job
+/// that has finished, and removes those jobs from the list. The interactive loop calls it before each prompt.
+///
+/// # Parameters
+/// - `jobs`: A mutable reference to the `Vec<Job>` job list.
+///
+/// # Examples
+/// ```
+/// use myshell::{reap_jobs, start_job, Redirections, ShellState};
+///
+/// let mut state = ShellState::default();
+/// start_job("true", &[], &Redirections::default(), &mut state).unwrap();
+/// state.jobs[0].child.wait().unwrap();
+///
+/// reap_jobs(&mut state.jobs);
+/// assert!(state.jobs.is_empty());
+/// ```
+pub fn reap_jobs(jobs: &mut Vec<Job>) {
+    jobs.retain_mut(|job| match job.child.try_wait() {
+        Ok(Some(status)) => {
+            println!("[{}] Done ({}) {}", job.id, exit_code(status), job.command);
+            false
+        }
+        Ok(None) => true,
+        Err(e) => {
+            eprintln!("Error checking job {}: {}", job.id, e);
+            false
+        }
+    });
+}
+
+/// Handles the JOBS command.
+///
+/// # Purpose
+/// This function first reports any jobs that have finished (see `reap_jobs`), then prints each remaining
+/// job's number, process ID and command line.
+///
+/// # Parameters
+/// - `jobs`: A mutable reference to the `Vec<Job>` job list.
+///
+/// # Examples
+/// ```
+/// use myshell::{list_jobs, ShellState};
+///
+/// let mut state = ShellState::default();
+///
+/// list_jobs(&mut state.jobs);
+/// ```
+pub fn list_jobs(jobs: &mut Vec<Job>) {
+    reap_jobs(jobs);
+    for job in jobs.iter() {
+        println!("[{}] {} Running {}", job.id, job.child.id(), job.command);
+    }
+}
+
+/// Handles the FG command.
+///
+/// # Purpose
+/// This function waits for a background job to finish, blocking the prompt as a foreground command would,
+/// and removes it from the job list. With no argument the most recently started job is used. Because a
+/// background job has its own process group, Ctrl+C does not reach it from the terminal; instead the shell
+/// passes the interruption on to the job while it waits.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`FG`) and the optional second word is the `%n` job number.
+/// - `jobs`: A mutable reference to the `Vec<Job>` job list.
+///
+/// # Returns
+/// This function returns a `Result<i32, Box<dyn Error>>` with the job's exit status. It returns an `Err` if
+/// there are no jobs or the job number does not exist.
+///
+/// # Examples
+/// ```
+/// use myshell::{foreground_job, start_job, Redirections, ShellState};
+///
+/// let mut state = ShellState::default();
+/// start_job("sh", &["-c", "exit 4"], &Redirections::default(), &mut state).unwrap();
+///
+/// assert_eq!(foreground_job(&["FG"], &mut state.jobs).unwrap(), 4);
+/// assert!(state.jobs.is_empty());
+/// assert!(foreground_job(&["FG", "%1"], &mut state.jobs).is_err());
+/// ```
+pub fn foreground_job(inputs: &[&str], jobs: &mut Vec<Job>) -> Result<i32, Box<dyn Error>> {
+    let index = match inputs.get(1) {
+        Some(spec) => job_index(jobs, spec, "FG")?,
+        None => jobs.len().checked_sub(1).ok_or("FG: no current job")?,
+    };
+
+    let mut job = jobs.remove(index);
+    println!("{}", job.command);
+    let mut forwarded = false;
+    let status = loop {
+        if let Some(status) = job.child.try_wait()? {
+            break status;
+        }
+        if !forwarded && INTERRUPTED.load(Ordering::SeqCst) {
+            forwarded = true;
+            // The job leads its own process group, so signal the whole group as the terminal would.
+            #[cfg(unix)]
+            if let Ok(pgid) = libc::pid_t::try_from(job.child.id()) {
+                // SAFETY: `kill` only takes integers, so there is nothing for it to invalidate.
+                unsafe { libc::kill(-pgid, libc::SIGINT) };
+            }
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    if forwarded {
+        println!(); // Start the next prompt on a fresh line after the echoed ^C.
+    }
+    Ok(exit_code(status))
+}
+
+/// Handles the WAIT command.
+///
+/// # Purpose
+/// This function blocks until every background job has finished, or only the job given as `%n`, printing a
+/// `Done` line for each as `reap_jobs` does and removing it from the job list. Jobs are waited for in the
+/// order they were started. Ctrl+C stops the waiting, leaving the jobs that are still running in the list,
+/// and gives the status 130, as in bash.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`WAIT`) and the optional second word is the `%n` job number.
+/// - `jobs`: A mutable reference to the `Vec<Job>` job list.
+///
+/// # Returns
+/// This function returns a `Result<i32, Box<dyn Error>>` with the exit status of the last job waited for,
+/// or 0 if there were none. It returns an `Err` if the job number does not exist.
+///
+/// # Examples
+/// ```
+/// use myshell::{start_job, wait_jobs, Redirections, ShellState};
+///
+/// let mut state = ShellState::default();
+/// start_job("sh", &["-c", "exit 2"], &Redirections::default(), &mut state).unwrap();
+/// start_job("sh", &["-c", "sleep 0.1; exit 3"], &Redirections::default(), &mut state).unwrap();
+///
+/// assert_eq!(wait_jobs(&["WAIT"], &mut state.jobs).unwrap(), 3);
+/// assert!(state.jobs.is_empty());
+/// assert_eq!(wait_jobs(&["WAIT"], &mut state.jobs).unwrap(), 0);
+/// assert!(wait_jobs(&["WAIT", "%1"], &mut state.jobs).is_err());
+/// ```
+pub fn wait_jobs(inputs: &[&str], jobs: &mut Vec<Job>) -> Result<i32, Box<dyn Error>> {
+    let waiting: Vec<usize> = match inputs {
+        [_] => jobs.iter().map(|job| job.id).collect(),
+        [_, spec] => vec![jobs[job_index(jobs, spec, "WAIT")?].id],
+        _ => return Err("Usage: WAIT [%job]".into()),
+    };
+
+    let mut last_status = 0;
+    for id in waiting {
+        let index = jobs.iter().position(|job| job.id == id).expect("jobs are only removed here");
+        let status = loop {
+            if let Some(status) = jobs[index].child.try_wait()? {
+                break status;
+            }
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                println!(); // Start the next prompt on a fresh line after the echoed ^C.
+                return Ok(128 + 2); // 128 plus SIGINT, as for a command stopped by Ctrl+C.
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        let job = jobs.remove(index);
+        last_status = exit_code(status);
+        println!("[{}] Done ({}) {}", job.id, last_status, job.command);
+    }
+    Ok(last_status)
+}
+
+/// Handles the DISOWN command.
+///
+/// # Purpose
+/// This function removes a background job from the job list without ending it, so that `JOBS`, `WAIT` and
+/// `FG` no longer see it and exiting the shell neither warns about it nor ends it. With no argument the most
+/// recently started job is used. The process is not told anything: on Unix it keeps running in its own
+/// process group, which Ctrl+C at the terminal does not reach, and a thread of the shell collects its exit
+/// status when it finishes so that it does not linger as a zombie process. If the shell exits first, the
+/// process is adopted by the system as any orphan is. On Windows the process likewise runs on until it
+/// finishes by itself.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`DISOWN`) and the optional second word is the `%n` job number.
+/// - `jobs`: A mutable reference to the `Vec<Job>` job list.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if there are no jobs or the job
+/// number does not exist.
+///
+/// # Examples
+/// ```
+/// use myshell::{disown_job, start_job, Redirections, ShellState};
+///
+/// let mut state = ShellState::default();
+/// start_job("sleep", &["0.1"], &Redirections::default(), &mut state).unwrap();
+///
+/// disown_job(&["DISOWN", "%1"], &mut state.jobs).unwrap();
+/// assert!(state.jobs.is_empty());
+/// assert!(disown_job(&["DISOWN"], &mut state.jobs).is_err());
+/// ```
+pub fn disown_job(inputs: &[&str], jobs: &mut Vec<Job>) -> Result<(), Box<dyn Error>> {
+    let index = match inputs {
+        [_] => jobs.len().checked_sub(1).ok_or("DISOWN: no current job")?,
+        [_, spec] => job_index(jobs, spec, "DISOWN")?,
+        _ => return Err("Usage: DISOWN [%job]".into()),
+    };
+
+    let mut job = jobs.remove(index);
+    println!("[{}] {} disowned {}", job.id, job.child.id(), job.command);
+    std::thread::spawn(move || job.child.wait());
+    Ok(())
+}
+
+/// Finds the position in the job list of the job written `%n` (or just `n`), for the command `name`.
+fn job_index(jobs: &[Job], spec: &str, name: &str) -> Result<usize, Box<dyn Error>> {
+    let id: usize = spec
+        .strip_prefix('%')
+        .unwrap_or(spec)
+        .parse()
+        .map_err(|_| format!("{}: '{}' is not a job number", name, spec))?;
+    Ok(jobs.iter().position(|job| job.id == id).ok_or_else(|| format!("{}: no such job %{}", name, id))?)
+}
+
+/// Handles the KILL command.
+///
+/// # Purpose
+/// This function sends a signal to each target, which is either a job number written `%n` or a process ID.
+/// The signal is `SIGTERM` unless the first argument names another, as a number (`-9`) or a name with or
+/// without the `SIG` prefix (`-KILL`, `-SIGHUP`). On Windows, where there are no signals, only killing a job
+/// outright (`-9` or `-KILL`) is supported.
+///
+/// # Parameters
+/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
+///   first word is the command (`KILL`), followed by an optional signal and the targets.
+/// - `jobs`: A mutable reference to the `Vec<Job>` job list used to look up job numbers.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if a signal, job number or
+/// process ID is unknown, after trying every target.
+///
+/// # Examples
+/// ```
+/// use myshell::{kill_command, start_job, Redirections, ShellState};
+///
+/// let mut state = ShellState::default();
+/// start_job("sleep", &["10"], &Redirections::default(), &mut state).unwrap();
+///
+/// kill_command(&["KILL", "-9", "%1"], &mut state.jobs).unwrap();
+/// assert!(!state.jobs[0].child.wait().unwrap().success());
+/// assert!(kill_command(&["KILL", "%7"], &mut state.jobs).is_err());
+/// assert!(kill_command(&["KILL", "-NOPE", "%1"], &mut state.jobs).is_err());
+/// ```
+pub fn kill_command(inputs: &[&str], jobs: &mut [Job]) -> Result<(), Box<dyn Error>> {
+    let (signal, targets) = match inputs.get(1) {
+        Some(flag) if flag.starts_with('-') => (signal_number(&flag[1..])?, &inputs[2..]),
+        _ => (signal_number("TERM")?, &inputs[1..]),
+    };
+    if targets.is_empty() {
+        return Err("Usage: KILL [-signal] <%job | pid>...".into());
+    }
+
+    let mut failed = false;
+    for &target in targets {
+        if let Err(e) = send_signal(target, signal, jobs) {
+            eprintln!("KILL: {}: {}", target, e);
+            failed = true;
+        }
+    }
+    if failed {
+        Err("KILL: not every target could be signalled".into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Looks up a signal by number or name.
+///
+/// # Purpose
+/// This function turns the signal given to `KILL` into its number. Names may be written with or without the
+/// `SIG` prefix; the ones known are those numbered alike on Linux, macOS and the BSDs.
+///
+/// # Parameters
+/// - `name`: A `&str` representing the signal, such as `9`, `KILL` or `SIGKILL`.
+///
+/// # Returns
+/// This function returns a `Result<i32, Box<dyn Error>>` with the signal number, or an `Err` if the name is
+/// not one the shell knows.
+///
+/// # Examples
+/// ```
+/// use myshell::signal_number;
+///
+/// assert_eq!(signal_number("9").unwrap(), 9);
+/// assert_eq!(signal_number("SIGKILL").unwrap(), 9);
+/// assert_eq!(signal_number("TERM").unwrap(), 15);
+/// assert!(signal_number("NOPE").is_err());
+/// ```
+pub fn signal_number(name: &str) -> Result<i32, Box<dyn Error>> {
+    if let Ok(number) = name.parse() {
+        return Ok(number);
+    }
+    match name.strip_prefix("SIG").unwrap_or(name) {
+        "HUP" => Ok(1),
+        "INT" => Ok(2),
+        "QUIT" => Ok(3),
+        "KILL" => Ok(9),
+        "ALRM" => Ok(14),
+        "TERM" => Ok(15),
+        _ => Err(format!("unknown signal '{}'", name).into()),
+    }
+}
+
+/// Sends a signal to a single `KILL` target.
+///
+/// # Purpose
+/// This function resolves a `%n` job number to the job's process ID, or parses a plain process ID, and sends
+/// it the signal. On Windows a job can only be killed outright.
+///
+/// # Parameters
+/// - `target`: A `&str` representing the `%n` job number or process ID.
+/// - `signal`: An `i32` representing the signal number.
+/// - `jobs`: A mutable reference to the `Vec<Job>` job list used to look up job numbers.
+///
+/// # Returns
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the job does not exist, the
+/// target is not a number, or the signal cannot be delivered.
+///
+/// # Examples
+/// ```
+/// use myshell::send_signal;
+///
+/// assert!(send_signal("%1", 15, &mut []).is_err());
+/// assert!(send_signal("not-a-pid", 15, &mut []).is_err());
+/// ```
+pub fn send_signal(target: &str, signal: i32, jobs: &mut [Job]) -> Result<(), Box<dyn Error>> {
+    let pid: u32 = match target.strip_prefix('%') {
+        Some(number) => {
+            let job = number
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| jobs.iter_mut().find(|job| job.id == number))
+                .ok_or("no such job")?;
+            if cfg!(not(unix)) && signal == 9 {
+                return Ok(job.child.kill()?);
+            }
+            job.child.id()
+        }
+        None => target.parse().map_err(|_| "not a job number or process ID")?,
+    };
+
+    #[cfg(unix)]
+    {
+        let pid = libc::pid_t::try_from(pid)?;
+        // SAFETY: `kill` only takes integers, so there is nothing for it to invalidate.
+        if unsafe { libc::kill(pid, signal) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (pid, signal);
+        Err("signals are not supported on this platform".into())
+    }
+}
+
+/// A file that an output stream is redirected to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTarget {
+    /// The path of the file.
+    pub path: String,
+    /// Whether to append to the file (`>>`) instead of truncating it (`>`).
+    pub append: bool,
+}
+
+/// The redirections of a command's standard streams.
+///
+/// # Purpose
+/// This struct records where a command's standard input, output, and error should be connected, as parsed from
+/// the operators `<`, `<<<`, `>`, `>>`, `2>`, `2>>`, `&>`, and `&>>`. Streams without a redirection are
+/// inherited from the shell.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Redirections {
+    /// The file read as standard input (`< file`).
+    pub stdin: Option<String>,
+    /// The text fed to standard input instead of a file (`<<< text`, which gets a newline added).
+    pub stdin_text: Option<String>,
+    /// The file standard output is written to (`> file`, `>> file`).
+    pub stdout: Option<OutputTarget>,
+    /// The file standard error is written to (`2> file`, `2>> file`).
+    pub stderr: Option<OutputTarget>,
+    /// Whether standard error shares the standard output file (`&> file`).
+    pub stderr_to_stdout: bool,
+}
+
+impl Redirections {
+    /// Connects the streams of `process` to the redirection files, opening or creating them. A stream
+    /// redirected to the null device (see `is_null_device`) is connected to `Stdio::null` instead of a file.
+    ///
+    /// With `&>`, the standard output file is opened once and its handle cloned for standard error, so the
+    /// two streams interleave in the file instead of overwriting each other.
+    pub fn apply(&self, process: &mut process::Command) -> io::Result<()> {
+        if let Some(path) = &self.stdin {
+            process.stdin(if is_null_device(path) { Stdio::null() } else { File::open(path)?.into() });
+        } else if self.stdin_text.is_some() {
+            process.stdin(Stdio::piped());
+        }
+
+        let stdout = self.stdout.as_ref().map(OutputTarget::open).transpose()?;
+        if self.stderr_to_stdout {
+            if let Some(file) = &stdout {
+                process.stderr(file.as_ref().map(File::try_clone).transpose()?.map_or_else(Stdio::null, Stdio::from));
+            }
+        } else if let Some(target) = &self.stderr {
+            process.stderr(target.open()?.map_or_else(Stdio::null, Stdio::from));
+        }
+        if let Some(file) = stdout {
+            process.stdout(file.map_or_else(Stdio::null, Stdio::from));
+        }
+
+        Ok(())
+    }
+
+    /// Writes `stdin_text`, if there is any, to the standard input of a child started after `apply`, then closes
+    /// it. The writing happens on a thread of its own so that a child which fills its output pipe before reading
+    /// all of its input cannot block the shell.
+    pub fn feed(&self, child: &mut Child) {
+        if let (Some(text), Some(mut stdin)) = (&self.stdin_text, child.stdin.take()) {
+            let text = text.clone();
+            std::thread::spawn(move || {
+                // A child that exits without reading everything closes the pipe; that is not an error.
+                let _ = stdin.write_all(text.as_bytes());
+            });
+        }
+    }
+}
+
+impl OutputTarget {
+    /// Opens the target file for writing, creating it if needed, or returns `None` for the null device.
+    fn open(&self) -> io::Result<Option<File>> {
+        if is_null_device(&self.path) {
+            return Ok(None);
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(&self.path)
+            .map(Some)
+    }
+}
+
+/// Checks whether a redirection target names the null device.
+///
+/// # Purpose
+/// This function recognizes the names of the device that discards output and reads as empty: `/dev/null` as
+/// on Unix, and `NUL` as on Windows, in any case. Redirections to either are connected to `Stdio::null`
+/// rather than opened as files, so `> /dev/null` works on Windows and `> NUL` does not create a file named
+/// `NUL` on Unix.
+///
+/// # Parameters
+/// - `path`: A `&str` representing the redirection target.
+///
+/// # Returns
+/// This function returns `true` if `path` names the null device.
+///
+/// # Examples
+/// ```
+/// use myshell::is_null_device;
+///
+/// assert!(is_null_device("/dev/null"));
+/// assert!(is_null_device("NUL"));
+/// assert!(is_null_device("nul"));
+/// assert!(!is_null_device("null.txt"));
+/// ```
+pub fn is_null_device(path: &str) -> bool {
+    path == "/dev/null" || path.eq_ignore_ascii_case("NUL")
+}
+
+/// Separates redirection operators from a command's words.
+///
+/// # Purpose
+/// This function removes the redirections from `words` and records them in a `Redirections`. The target file
+/// may follow the operator as a separate word (`> out.txt`) or be attached to it (`>out.txt`). Supported
+/// operators are `<` for standard input, `>` and `>>` for standard output, `2>` and `2>>` for standard error,
+/// and `&>` and `&>>` for both. The here-string operator `<<<` takes the word after it as the text of
+/// standard input, with a newline added as bash does, so that `cat <<< "hello world"` prints `hello world`.
+/// When a stream is redirected more than once, the last redirection wins. A target of `/dev/null` or `NUL`
+/// discards the stream, or reads as empty, on every platform (see `is_null_device`).
+///
+/// # Parameters
+/// - `words`: A slice of `&str` containing the command line split into words.
+///
+/// # Returns
+/// This function returns a `Result<(Vec<&str>, Redirections), Box<dyn Error>>` with the remaining words and
+/// the parsed redirections. It returns an `Err` if an operator is not followed by a file name.
+///
+/// # Examples
+/// ```
+/// use myshell::{parse_redirections, OutputTarget};
+///
+/// let (words, redirections) = parse_redirections(&["ls", "-l", ">", "out.txt"]).unwrap();
+/// assert_eq!(words, vec!["ls", "-l"]);
+/// assert_eq!(redirections.stdout, Some(OutputTarget { path: "out.txt".to_string(), append: false }));
+/// assert_eq!(redirections.stderr, None);
+///
+/// let (_, redirections) = parse_redirections(&["build", "&>", "log.txt"]).unwrap();
+/// assert_eq!(redirections.stdout, Some(OutputTarget { path: "log.txt".to_string(), append: false }));
+/// assert!(redirections.stderr_to_stdout);
+///
+/// let (words, redirections) = parse_redirections(&["build", ">out.txt", "2>>", "err.txt", "<in.txt"]).unwrap();
+/// assert_eq!(words, vec!["build"]);
+/// assert_eq!(redirections.stdout, Some(OutputTarget { path: "out.txt".to_string(), append: false }));
+/// assert_eq!(redirections.stderr, Some(OutputTarget { path: "err.txt".to_string(), append: true }));
+/// assert_eq!(redirections.stdin, Some("in.txt".to_string()));
+///
+/// let (words, redirections) = parse_redirections(&["cat", "<<<", "hello world"]).unwrap();
+/// assert_eq!(words, vec!["cat"]);
+/// assert_eq!(redirections.stdin_text, Some("hello world\n".to_string()));
+/// assert_eq!(redirections.stdin, None);
+///
+/// // A later `>` takes standard output back, leaving standard error in the `&>` file.
+/// let (_, redirections) = parse_redirections(&["build", "&>", "all.txt", ">", "out.txt"]).unwrap();
+/// assert_eq!(redirections.stdout, Some(OutputTarget { path: "out.txt".to_string(), append: false }));
+/// assert_eq!(redirections.stderr, Some(OutputTarget { path: "all.txt".to_string(), append: false }));
+/// assert!(!redirections.stderr_to_stdout);
+///
+/// assert!(parse_redirections(&["ls", ">"]).is_err());
+/// assert!(parse_redirections(&["ls", "2>&1"]).is_err());
+/// ```
+///
+/// The `&>` and `2>` forms capture standard error when the command runs:
+///
+/// ```
+/// use myshell::{execute_command, parse_redirections, ShellState};
+///
+/// let state = ShellState::default();
+/// let log = std::env::temp_dir().join(format!("myshell-redirect-{}.txt", std::process::id()));
+/// let log = log.to_str().unwrap();
+///
+/// let (_, redirections) = parse_redirections(&["&>", log]).unwrap();
+/// execute_command("sh", &["-c", "echo out; echo err >&2"], &redirections, &state.environment).unwrap();
+/// assert_eq!(std::fs::read_to_string(log).unwrap(), "out\nerr\n");
+///
+/// let (_, redirections) = parse_redirections(&["2>", log]).unwrap();
+/// execute_command("sh", &["-c", "echo out; echo err >&2"], &redirections, &state.environment).unwrap();
+/// assert_eq!(std::fs::read_to_string(log).unwrap(), "err\n");
+///
+/// std::fs::remove_file(log).unwrap();
+/// ```
+pub fn parse_redirections<'a>(words: &[&'a str]) -> Result<(Vec<&'a str>, Redirections), Box<dyn Error>> {
+    // Longer operators come first so that `>>` is not read as `>` followed by a file named `>...`.
+    const OPERATORS: [&str; 8] = ["&>>", "&>", "2>>", "2>", ">>", ">", "<<<", "<"];
+
+    let mut remaining = Vec::new();
+    let mut redirections = Redirections::default();
+    let mut iter = words.iter();
+
+    while let Some(&word) = iter.next() {
+        let Some(operator) = OPERATORS.iter().copied().find(|operator| word.starts_with(operator)) else {
+            remaining.push(word);
+            continue;
+        };
+
+        let attached = &word[operator.len()..];
+        let path = if attached.is_empty() {
+            *iter.next().ok_or_else(|| format!("Missing file name after '{}'", operator))?
+        } else {
+            attached
+        };
+        if operator == "<<<" {
+            redirections.stdin_text = Some(format!("{}\n", path));
+            redirections.stdin = None;
+            continue;
+        }
+        if path.starts_with('&') {
+            return Err(format!("Redirecting to a file descriptor ('{}{}') is not supported", operator, path).into());
+        }
+        let target = OutputTarget { path: path.to_string(), append: operator.ends_with(">>") };
+
+        match operator {
+            "<" => {
+                redirections.stdin = Some(path.to_string());
+                redirections.stdin_text = None;
+            }
+            ">" | ">>" => {
+                if redirections.stderr_to_stdout {
+                    // Standard error stays in the file it was sharing with standard output.
+                    redirections.stderr = redirections.stdout.take();
+                    redirections.stderr_to_stdout = false;
+                }
+                redirections.stdout = Some(target);
+            }
+            "2>" | "2>>" => {
+                redirections.stderr = Some(target);
+                redirections.stderr_to_stdout = false;
+            }
+            _ => {
+                redirections.stdout = Some(target);
+                redirections.stderr = None;
+                redirections.stderr_to_stdout = true;
+            }
+        }
+    }
+
+    Ok((remaining, redirections))
+}
+
+/// Checks whether a line is continued on the next one.
+///
+/// # Purpose
+/// This function detects a line ending in an unescaped backslash, which asks for the command to carry on
+/// onto the next line. A backslash is unescaped when the line ends in an odd number of them, so `\\` at the
+/// end of a line is a literal backslash rather than a continuation.
+///
+/// # Parameters
+/// - `line`: A `&str` representing the line as read.
+///
+/// # Returns
+/// This function returns an `Option<&str>` with the line minus its trailing backslash if it is continued,
+/// or `None` if the line is complete.
+///
+/// # Examples
+/// ```
+/// use myshell::strip_continuation;
+///
+/// assert_eq!(strip_continuation("ls -l \\"), Some("ls -l "));
+/// assert_eq!(strip_continuation("ECHO a\\\\"), None);
+/// assert_eq!(strip_continuation("ls"), None);
+/// ```
+pub fn strip_continuation(line: &str) -> Option<&str> {
+    let backslashes = line.chars().rev().take_while(|&c| c == '\\').count();
+    if backslashes % 2 == 1 {
+        Some(&line[..line.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Expands a command line before it is split into words.
+///
+/// # Purpose
+/// This function is the shell's expansion stage. It replaces every command substitution `$(command)` with
+/// the standard output of `command`, with trailing newlines removed as in bash, and every arithmetic
+/// expansion `$((expression))` with the value of the integer expression (see `evaluate_arithmetic`).
+/// Variables written `$name` or `${name}` are replaced by their value (see `lookup_variable`), or by nothing
+/// if unset, and `$?` by the exit status of the last command. The positional parameters `$0` to `$9` take a
+/// single digit, as in POSIX shells, so later ones are written `${10}`; `$#` is the number of arguments.
+/// Expansions may be nested; inner ones are expanded first. Nothing inside single quotes or after a
+/// backslash is expanded. The substituted text is escaped so that `tokenize` keeps any quotes or backslashes
+/// in it as they are; outside double quotes it is still split into words at its whitespace.
+///
+/// # Parameters
+/// - `line`: A `&str` representing the command line as typed.
+/// - `state`: A reference to the `ShellState` used to resolve aliases inside substitutions.
+///
+/// # Returns
+/// This function returns a `Result<String, Box<dyn Error>>` with the expanded line. It returns an `Err` if a
+/// substitution is left unterminated or if a substituted command fails.
+///
+/// # Examples
+/// ```
+/// use myshell::{expand_line, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// assert_eq!(expand_line("echo plain", &state).unwrap(), "echo plain");
+/// assert_eq!(expand_line("echo [$(echo hi)]", &state).unwrap(), "echo [hi]");
+/// assert_eq!(expand_line("echo $(echo $(echo nested))", &state).unwrap(), "echo nested");
+/// assert_eq!(expand_line("echo $((2 + 3 * 4))", &state).unwrap(), "echo 14");
+/// assert_eq!(expand_line("echo $? costs $", &state).unwrap(), "echo 0 costs $");
+/// assert_eq!(expand_line("echo [$MYSHELL_SURELY_UNSET]", &state).unwrap(), "echo []");
+/// assert_eq!(expand_line("echo $0 $# [$1]", &state).unwrap(), "echo myshell-rs 0 []");
+/// assert_eq!(expand_line("echo '$?' \\$? \"$?\"", &state).unwrap(), "echo '$?' \\$? \"0\"");
+/// assert_eq!(expand_line("echo $(echo \\\"hi\\\")", &state).unwrap(), "echo \\\"hi\\\"");
+/// assert!(expand_line("echo $(echo oops", &state).is_err());
+/// assert!(expand_line("echo $((1 + 2)", &state).is_err());
+/// ```
+pub fn expand_line(line: &str, state: &ShellState) -> Result<String, Box<dyn Error>> {
+    let mut expanded = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut in_double_quotes = false;
+
+    while let Some(start) = rest.find(['$', '\'', '"', '\\']) {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if rest[start..].starts_with('"') {
+            in_double_quotes = !in_double_quotes;
+            expanded.push('"');
+            rest = after;
+        } else if rest[start..].starts_with('\\') {
+            // Keep the backslash and the character it escapes for the tokenizer.
+            let escaped_len = after.chars().next().map_or(0, char::len_utf8);
+            expanded.push_str(&rest[start..start + 1 + escaped_len]);
+            rest = &after[escaped_len..];
+        } else if rest[start..].starts_with('\'') {
+            // Inside double quotes a single quote is an ordinary character; outside, skip to its partner.
+            let quoted_len = if in_double_quotes { 0 } else { after.find('\'').map_or(after.len(), |len| len + 1) };
+            expanded.push_str(&rest[start..start + 1 + quoted_len]);
+            rest = &after[quoted_len..];
+        } else if after.starts_with("((") {
+            let expression_start = start + 3;
+            let expression_len = matching_paren(&rest[expression_start..])
+                .filter(|&len| rest[expression_start + len + 1..].starts_with(')'))
+                .ok_or_else(|| format!("Unterminated arithmetic expansion in '{}'", line))?;
+
+            let expression = expand_line(&rest[expression_start..expression_start + expression_len], state)?;
+            expanded.push_str(&evaluate_arithmetic(&expression, state)?.to_string());
+            rest = &rest[expression_start + expression_len + 2..];
+        } else if after.starts_with('(') {
+            let inner_start = start + 2;
+            let inner_len = matching_paren(&rest[inner_start..])
+                .ok_or_else(|| format!("Unterminated command substitution in '{}'", line))?;
+
+            let inner = expand_line(&expand_braces(&rest[inner_start..inner_start + inner_len]), state)?;
+            expanded.push_str(&escape_expansion(&capture_output(&inner, state)?, in_double_quotes));
+            rest = &rest[inner_start + inner_len + 1..];
+        } else if let Some(braced) = after.strip_prefix('{') {
+            let name_len = braced
+                .find('}')
+                .ok_or_else(|| format!("Unterminated variable expansion in '{}'", line))?;
+            let value = lookup_variable(&braced[..name_len], state).unwrap_or_default();
+            expanded.push_str(&escape_expansion(&value, in_double_quotes));
+            rest = &braced[name_len + 1..];
+        } else if let Some(status_rest) = after.strip_prefix('?') {
+            expanded.push_str(&state.last_status.to_string());
+            rest = status_rest;
+        } else if after.starts_with(|c: char| c.is_ascii_digit() || c == '#') {
+            let value = lookup_variable(&after[..1], state).unwrap_or_default();
+            expanded.push_str(&escape_expansion(&value, in_double_quotes));
+            rest = &after[1..];
+        } else {
+            let name_len = variable_name_len(after);
+            if name_len == 0 {
+                expanded.push('$'); // A lone `$` is kept as typed.
+            } else {
+                let value = lookup_variable(&after[..name_len], state).unwrap_or_default();
+                expanded.push_str(&escape_expansion(&value, in_double_quotes));
+            }
+            rest = &after[name_len..];
+        }
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Escapes substituted text so that `tokenize` reads it literally.
+fn escape_expansion(text: &str, in_double_quotes: bool) -> String {
+    let special: &[char] = if in_double_quotes { &['"', '\\', '$', '`'] } else { &['"', '\'', '\\'] };
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Measures the variable name at the start of some text.
+///
+/// # Purpose
+/// This function returns the length of the longest prefix of `text` that is a valid variable name: a letter
+/// or underscore followed by letters, digits, or underscores.
+///
+/// # Parameters
+/// - `text`: A `&str` that may begin with a variable name.
+///
+/// # Returns
+/// This function returns the length of the name in bytes, or `0` if `text` does not begin with one.
+///
+/// # Examples
+/// ```
+/// use myshell::variable_name_len;
+///
+/// assert_eq!(variable_name_len("greeting!"), 8);
+/// assert_eq!(variable_name_len("_x1 rest"), 3);
+/// assert_eq!(variable_name_len("1abc"), 0);
+/// ```
+pub fn variable_name_len(text: &str) -> usize {
+    let mut len = 0;
+    for (index, c) in text.char_indices() {
+        let valid = c == '_' || if index == 0 { c.is_alphabetic() } else { c.is_alphanumeric() };
+        if !valid {
+            break;
+        }
+        len = index + c.len_utf8();
+    }
+    len
+}
+
+/// Evaluates an integer arithmetic expression.
+///
+/// # Purpose
+/// This function evaluates the expression of an arithmetic expansion `$((expression))`. It supports integer
+/// literals, `+ - * / %` with the usual precedence, unary `+` and `-`, and parentheses. A bare variable name
+/// evaluates to the variable's value, or `0` if it is unset, as in bash. Division truncates toward zero.
+///
+/// # Parameters
+/// - `expression`: A `&str` representing the expression to evaluate.
+/// - `state`: A reference to the `ShellState` used to look up variables.
+///
+/// # Returns
+/// This function returns a `Result<i64, Box<dyn Error>>` with the value. It returns an `Err` for malformed
+/// expressions, division by zero, overflow, and variables that do not hold an integer.
+///
+/// # Examples
+/// ```
+/// use myshell::{evaluate_arithmetic, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// assert_eq!(evaluate_arithmetic("2 + 3 * 4", &state).unwrap(), 14);
+/// assert_eq!(evaluate_arithmetic("(2 + 3) * 4", &state).unwrap(), 20);
+/// assert_eq!(evaluate_arithmetic("-7 / 2", &state).unwrap(), -3);
+/// assert_eq!(evaluate_arithmetic("7 % 3 - -1", &state).unwrap(), 2);
+/// assert_eq!(evaluate_arithmetic("MYSHELL_SURELY_UNSET + 1", &state).unwrap(), 1);
+/// assert!(evaluate_arithmetic("1 / 0", &state).is_err());
+/// assert!(evaluate_arithmetic("2 +", &state).is_err());
+/// ```
+pub fn evaluate_arithmetic(expression: &str, state: &ShellState) -> Result<i64, Box<dyn Error>> {
+    let mut parser = ArithmeticParser { chars: expression.chars().peekable(), state };
+    let value = parser.expression()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("Unexpected '{}' in arithmetic expression '{}'", c, expression).into()),
+    }
+}
+
+/// Looks up the value of a variable.
+///
+/// # Purpose
+/// This function resolves a variable name for expansions. A number names a positional parameter, `0` being
+/// the script name, and `#` gives their count, not counting `$0`. Shell variables set with `SET` take
+/// precedence over the environment for any other name.
+///
+/// # Parameters
+/// - `name`: A `&str` representing the variable name.
+/// - `state`: A reference to the `ShellState` holding the shell variables and environment.
+///
+/// # Returns
+/// This function returns an `Option<String>` with the value, or `None` if the variable is unset.
+///
+/// # Examples
+/// ```
+/// use myshell::{lookup_variable, ShellState};
+///
+/// let mut state = ShellState::default();
+/// state.variables.insert("greeting".to_string(), "hello there".to_string());
+///
+/// assert_eq!(lookup_variable("greeting", &state), Some("hello there".to_string()));
+/// assert_eq!(lookup_variable("MYSHELL_SURELY_UNSET", &state), None);
+///
+/// state.positional = ["deploy.sh", "prod"].map(String::from).to_vec();
+/// assert_eq!(lookup_variable("1", &state).as_deref(), Some("prod"));
+/// assert_eq!(lookup_variable("2", &state), None);
+/// assert_eq!(lookup_variable("#", &state).as_deref(), Some("1"));
+/// ```
+pub fn lookup_variable(name: &str, state: &ShellState) -> Option<String> {
+    if name == "#" {
+        return Some(state.positional.len().saturating_sub(1).to_string());
+    }
+    if let Ok(index) = name.parse::<usize>() {
+        return state.positional.get(index).cloned();
+    }
+    state.variables.get(name).or_else(|| state.environment.get(name)).cloned()
+}
+
+/// A recursive-descent parser that evaluates arithmetic expressions as it reads them.
+struct ArithmeticParser<'a> {
+    /// The remaining characters of the expression.
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    /// The shell state used to look up variables.
+    state: &'a ShellState,
+}
+
+impl ArithmeticParser<'_> {
+    /// Skips any whitespace before the next token.
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    /// Parses a sum: terms separated by `+` or `-`.
+    fn expression(&mut self) -> Result<i64, Box<dyn Error>> {
+        let mut value = self.term()?;
+        loop {
+            self.skip_whitespace();
+            let result = match self.chars.peek() {
+                Some('+') => { self.chars.next(); value.checked_add(self.term()?) }
+                Some('-') => { self.chars.next(); value.checked_sub(self.term()?) }
+                _ => return Ok(value),
+            };
+            value = result.ok_or("Arithmetic overflow")?;
+        }
+    }
+
+    /// Parses a product: factors separated by `*`, `/`, or `%`.
+    fn term(&mut self) -> Result<i64, Box<dyn Error>> {
+        let mut value = self.factor()?;
+        loop {
+            self.skip_whitespace();
+            let operator = match self.chars.peek() {
+                Some(&operator @ ('*' | '/' | '%')) => operator,
+                _ => return Ok(value),
+            };
+            self.chars.next();
+
+            let rhs = self.factor()?;
+            if operator != '*' && rhs == 0 {
+                return Err("Division by zero in arithmetic expression".into());
+            }
+            let result = match operator {
+                '*' => value.checked_mul(rhs),
+                '/' => value.checked_div(rhs),
+                _ => value.checked_rem(rhs),
+            };
+            value = result.ok_or("Arithmetic overflow")?;
+        }
+    }
+
+    /// Parses a number, a variable, a parenthesized expression, or a unary `+` or `-` applied to a factor.
+    fn factor(&mut self) -> Result<i64, Box<dyn Error>> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('-') => {
+                self.chars.next();
+                Ok(self.factor()?.checked_neg().ok_or("Arithmetic overflow")?)
+            }
+            Some('+') => {
+                self.chars.next();
+                self.factor()
+            }
+            Some('(') => {
+                self.chars.next();
+                let value = self.expression()?;
+                self.skip_whitespace();
+                match self.chars.next() {
+                    Some(')') => Ok(value),
+                    _ => Err("Missing ')' in arithmetic expression".into()),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(digit) = self.chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                Ok(digits.parse().map_err(|_| format!("Number '{}' is too large", digits))?)
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.next_if(|&c| c.is_alphanumeric() || c == '_') {
+                    name.push(c);
+                }
+                match lookup_variable(&name, self.state) {
+                    None => Ok(0),
+                    Some(value) if value.trim().is_empty() => Ok(0),
+                    Some(value) => value
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Variable '{}' is not an integer: '{}'", name, value).into()),
+                }
+            }
+            Some(c) => Err(format!("Unexpected '{}' in arithmetic expression", c).into()),
+            None => Err("Unexpected end of arithmetic expression".into()),
+        }
+    }
+}
+
+/// Finds the parenthesis that closes an opened group.
+///
+/// # Purpose
+/// This function scans `text`, which starts just after an opening `(`, and locates the `)` that closes it,
+/// skipping over any nested pairs.
+///
+/// # Parameters
+/// - `text`: A `&str` beginning right after the opening parenthesis.
+///
+/// # Returns
+/// This function returns an `Option<usize>` with the byte offset of the closing parenthesis, or `None` if
+/// the group is never closed.
+///
+/// # Examples
+/// ```
+/// use myshell::matching_paren;
+///
+/// assert_eq!(matching_paren("date) rest"), Some(4));
+/// assert_eq!(matching_paren("a (b) c) d"), Some(7));
+/// assert_eq!(matching_paren("never closed"), None);
+/// ```
+pub fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(index),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Runs a program and captures everything it prints.
+///
+/// # Purpose
+/// This function runs a program to completion with `Command::output`, capturing its standard output and
+/// standard error instead of letting them reach the terminal, for programs that embed the shell and for
+/// command substitution. The program's standard input is the null device, and it receives exactly the
+/// variables in `environment`.
+///
+/// # Parameters
+/// - `command`: A `&str` representing the program to run.
+/// - `args`: A slice of `&str` representing the arguments to pass to it.
+/// - `environment`: A reference to a `HashMap<String, String>` containing the program's environment.
+///
+/// # Returns
+/// This function returns a `Result<Output, ShellError>` with the captured streams and exit status, whether
+/// or not the status is zero. It returns `ShellError::CommandNotFound` if there is no such program and
+/// `ShellError::Io` if it cannot be run for another reason.
+///
+/// # Examples
+/// ```
+/// use myshell::{run_command_capture, ShellError, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// let output = run_command_capture("sh", &["-c", "echo out; echo err >&2; exit 3"], &state.environment).unwrap();
+/// assert_eq!(output.stdout, b"out\n");
+/// assert_eq!(output.stderr, b"err\n");
+/// assert_eq!(output.status.code(), Some(3));
+///
+/// let missing = run_command_capture("no-such-command-anywhere", &[], &state.environment);
+/// assert!(matches!(missing, Err(ShellError::CommandNotFound(_))));
+/// ```
+pub fn run_command_capture(command: &str, args: &[&str], environment: &HashMap<String, String>) -> Result<Output, ShellError> {
+    process::Command::new(command)
+        .args(args)
+        .env_clear()
+        .envs(environment)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ShellError::CommandNotFound(command.to_string()),
+            _ => ShellError::Io(e),
+        })
+}
+
+/// Runs a command and captures what it prints.
+///
+/// # Purpose
+/// This function runs the command line of a command substitution and returns its standard output. The first
+/// word is resolved as an alias if one is defined. The command runs through `run_command_capture`, and its
+/// standard error is passed on to the shell's so that its messages are still seen.
+///
+/// # Parameters
+/// - `command_line`: A `&str` representing the command and its arguments.
+/// - `state`: A reference to the `ShellState` used to resolve aliases.
+///
+/// # Returns
+/// This function returns a `Result<String, Box<dyn Error>>` with the captured output, trailing newlines
+/// removed. It returns an `Err` if the command is a built-in, cannot be started, or exits with a non-zero status.
+///
+/// # Examples
+/// ```
+/// use myshell::{capture_output, ShellState};
+///
+/// let state = ShellState::default();
+///
+/// assert_eq!(capture_output("echo hello world", &state).unwrap(), "hello world");
+/// assert!(capture_output("false", &state).is_err());
+/// ```
+pub fn capture_output(command_line: &str, state: &ShellState) -> Result<String, Box<dyn Error>> {
+    let tokens = tokenize(command_line)?;
+    let mut words: Vec<&str> = tokens.iter().map(String::as_str).collect();
+    if let Some(alias_command) = words.first().and_then(|command| state.aliases.get(*command)) {
+        words.splice(..1, alias_command.command.split_whitespace());
+    }
+
+    let Some((&command, args)) = words.split_first() else {
+        return Ok(String::new()); // `$()` expands to nothing.
+    };
+    if BUILTINS.iter().any(|builtin| builtin.name == command) {
+        return Err(format!("Cannot capture the output of built-in command '{}'", command).into());
+    }
+
+    let output = run_command_capture(command, args, &state.environment)?;
+    io::stderr().write_all(&output.stderr)?;
+    if !output.status.success() {
+        return Err(format!("Command substitution '{}' failed with {}", command_line, output.status).into());
+    }
+
+    let mut captured = String::from_utf8_lossy(&output.stdout).into_owned();
+    let trimmed_len = captured.trim_end_matches(&['\n', '\r'][..]).len();
+    captured.truncate(trimmed_len);
+    Ok(captured)
+}
+
+/// Suggests the built-in or alias closest to a mistyped command.
+///
+/// # Purpose
+/// This function looks for the built-in command or alias whose name is closest to `command` by Levenshtein
+/// distance. Only near misses are suggested, so unrelated names are never offered: the distance may be at most
+/// one for every three characters of `command`, and no more than 2. A name shorter than three characters
+/// gets no suggestion at all, since nearly everything short, such as `[`, is that close to it.
+///
+/// # Parameters
+/// - `command`: A `&str` representing the command that could not be found.
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing user-defined aliases.
+///
+/// # Returns
+/// This function returns an `Option<String>` with the closest name, or `None` if nothing is close enough.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{suggest_command, Alias};
+///
+/// let mut aliases = HashMap::new();
+/// aliases.insert("mycopy".to_string(), Alias::new("cp"));
+///
+/// assert_eq!(suggest_command("SETSHELNAME", &aliases), Some("SETSHELLNAME".to_string()));
+/// assert_eq!(suggest_command("mycpy", &aliases), Some("mycopy".to_string()));
+/// assert_eq!(suggest_command("nothing-like-it", &aliases), None);
+/// assert_eq!(suggest_command("x", &aliases), None);
+/// assert_eq!(suggest_command("ECH", &aliases), Some("ECHO".to_string()));
+/// ```
+pub fn suggest_command(command: &str, aliases: &HashMap<String, Alias>) -> Option<String> {
+    let max_distance = (command.chars().count() / 3).min(2);
+    let mut alias_names: Vec<&str> = aliases.keys().map(String::as_str).collect();
+    alias_names.sort();
+
+    BUILTINS
+        .iter()
+        .map(|builtin| builtin.name)
+        .chain(alias_names)
+        .map(|name| (edit_distance(command, name), name))
+        .filter(|&(distance, _)| (1..=max_distance).contains(&distance))
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, name)| name.to_string())
+}
+
+/// Computes the Levenshtein distance between two strings.
+///
+/// # Purpose
+/// This function counts the minimum number of single-character insertions, deletions, and substitutions
+/// needed to turn `a` into `b`. It compares characters rather than bytes, so non-ASCII names are measured correctly.
+///
+/// # Parameters
+/// - `a`: A `&str` representing the first string.
+/// - `b`: A `&str` representing the second string.
+///
+/// # Returns
+/// This function returns the edit distance as a `usize`.
+///
+/// # Examples
+/// ```
+/// use myshell::edit_distance;
+///
+/// assert_eq!(edit_distance("excho", "echo"), 1);
+/// assert_eq!(edit_distance("kitten", "sitting"), 3);
+/// assert_eq!(edit_distance("same", "same"), 0);
+/// ```
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Reads aliases from a file and populates a HashMap.
+///
+/// # Purpose
+/// This function reads aliases from a specified file and populates a mutable `HashMap<String, Alias>`
+/// with the alias-command pairs found in the file, up to a maximum specified by `max_aliases`. Each line holds
+/// an alias name, a tab, and its command, escaped as `save_aliases_to_file` writes it, so the command comes
+/// back exactly as saved. A line without a tab is read in the older format, an alias name, a space, and the
+/// rest of the line as its command. Blank lines and lines starting with `#` are skipped, and any other line
+/// without a separator is reported on standard error with its line number and skipped, so the rest of the
+/// file still loads.
+///
+/// # Parameters
+/// - `file_name`: A `&str` representing the name of the file from which to read aliases.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` that will store the aliases.
+/// - `max_aliases`: An `usize` indicating the maximum number of aliases to read from the file.
+///
+/// # Returns
+/// This function returns a `Result<usize, Box<dyn Error>>` with the number of aliases loaded from the file.
+///
+/// # Errors
+/// It returns an `Err` if the file cannot be opened or read.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::read_aliases_from_file;
+///
+/// let path = std::env::temp_dir().join("myshell-read-aliases-example");
+/// std::fs::write(&path, "# my aliases\nll\tls -l\n\nbroken\ngs git status\npad\t  two\\tparts  \n").unwrap();
+///
+/// let mut aliases = HashMap::new();
+/// let loaded = read_aliases_from_file(&path.to_string_lossy(), &mut aliases, 10).unwrap();
+///
+/// assert_eq!(loaded, 3);
+/// assert_eq!(aliases["gs"].command, "git status");
+/// assert_eq!(aliases["pad"].command, "  two\tparts  ");
+/// assert!(!aliases.contains_key("broken"));
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn read_aliases_from_file(file_name: &str, aliases: &mut HashMap<String, Alias>, max_aliases: usize) -> Result<usize, Box<dyn Error>> {
+    let file = File::open(file_name)?;
+    let reader = BufReader::new(file);
+    let mut loaded = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let fields = match line.split_once('\t') {
+            Some((alias, command)) => Some((alias, unescape_alias_command(command))),
+            None => line.split_once(' ').map(|(alias, command)| (alias, command.to_string())),
+        };
+        let Some((alias, command)) = fields.filter(|(alias, _)| !alias.is_empty()) else {
+            eprintln!("Warning: {}:{}: skipping malformed alias line (expected '<name> <command>'): {}", file_name, index + 1, line);
+            continue;
+        };
+        aliases.insert(alias.to_string(), Alias::new(command));
+        loaded += 1;
+
+        if aliases.len() >= max_aliases {
+            break;
+        }
+    }
+
+    Ok(loaded)
+}
+/// Reads the aliases declared in a bash file.
+///
+/// # Purpose
+/// This function reads `alias name=value` lines, as bash writes them for `alias -p` or as they appear in a
+/// `~/.bash_aliases` file, and adds each alias to `aliases`, up to a maximum specified by `max_aliases`. Each
+/// line is split with `tokenize`, so the value may be unquoted, single-quoted with `'\''` for a single quote, or
+/// double-quoted; the quotes are removed and the value is kept exactly, without expanding variables. One line
+/// may declare several aliases. Blank lines and `#` comments are skipped silently; any other line that is not an
+/// alias declaration, or that declares a name that is not a valid alias name, is reported on standard error with
+/// its line number and skipped.
+///
+/// # Parameters
+/// - `file_name`: A `&str` representing the name of the file from which to read aliases.
+/// - `aliases`: A mutable reference to a `HashMap<String, Alias>` that will store the aliases.
+/// - `max_aliases`: An `usize` indicating the maximum number of aliases to read from the file.
+///
+/// # Returns
+/// This function returns a `Result<usize, Box<dyn Error>>` with the number of aliases loaded from the file.
+///
+/// # Errors
+/// It returns an `Err` if the file cannot be opened or read.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::read_bash_aliases_from_file;
+///
+/// let path = std::env::temp_dir().join("myshell-read-bash-aliases-example");
+/// std::fs::write(&path, "# from bash\nalias ll='ls -l' la=\"ls -A\"\nexport EDITOR=vi\nalias gs=git\\ status\n").unwrap();
+///
+/// let mut aliases = HashMap::new();
+/// let loaded = read_bash_aliases_from_file(&path.to_string_lossy(), &mut aliases, 10).unwrap();
+///
+/// assert_eq!(loaded, 3);
+/// assert_eq!(aliases["ll"].command, "ls -l");
+/// assert_eq!(aliases["la"].command, "ls -A");
+/// assert_eq!(aliases["gs"].command, "git status");
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn read_bash_aliases_from_file(file_name: &str, aliases: &mut HashMap<String, Alias>, max_aliases: usize) -> Result<usize, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(file_name)?);
+    let mut loaded = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let words = match tokenize(&line) {
+            Ok(words) if words.len() > 1 && words[0] == "alias" => words,
+            Ok(_) => {
+                eprintln!("Warning: {}:{}: skipping line that is not an alias declaration: {}", file_name, index + 1, line);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Warning: {}:{}: skipping line: {}", file_name, index + 1, e);
+                continue;
+            }
+        };
+        for word in &words[1..] {
+            let Some((alias, command)) = word.split_once('=').filter(|(alias, _)| validate_alias_name(alias).is_ok()) else {
+                eprintln!("Warning: {}:{}: skipping malformed alias declaration (expected 'name=value'): {}", file_name, index + 1, word);
+                continue;
+            };
+            if aliases.len() >= max_aliases && !aliases.contains_key(alias) {
+                return Ok(loaded);
+            }
+            aliases.insert(alias.to_string(), Alias::new(command));
+            loaded += 1;
+        }
+    }
+
+    Ok(loaded)
+}
+
+/// Appends a command line to the command log.
+///
+/// # Purpose
+/// This function opens the log file for appending, creating it if needed, and writes the command line after a
+/// local timestamp.
+///
+/// # Parameters
+/// - `path`: A reference to the `Path` of the log file.
+/// - `line`: A `&str` representing the command line to log.
+///
+/// # Errors
+/// This function returns a `Result<(), Box<dyn Error>>`. It can return an error if the file cannot be opened
+/// or written.
+///
+/// # Examples
+/// ```
+/// use myshell::log_command;
+///
+/// let path = std::env::temp_dir().join("myshell-log-example.log");
+/// # let _ = std::fs::remove_file(&path);
+///
+/// log_command(&path, "ls -l").unwrap();
+///
+/// assert!(std::fs::read_to_string(&path).unwrap().ends_with(" ls -l\n"));
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn log_command(path: &Path, line: &str) -> Result<(), Box<dyn Error>> {
+    let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "{} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), line)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Saves the aliases to a file.
+///
+/// # Purpose
+/// This function is responsible for saving a HashMap of aliases to a specified file. Each alias is written on
+/// its own line, sorted by name, as the name, a tab, and the command with its backslashes, tabs, and newlines
+/// written as `\\`, `\t`, and `\n`, so that `read_aliases_from_file` reads back exactly the same commands.
+/// The file is replaced with `write_file_atomically`, so a save that fails leaves the old file as it was.
+///
+/// # Parameters
+/// - `file_name`: A `&str` representing the name of the file where the aliases will be saved.
+/// - `aliases`: A reference to a `HashMap<String, Alias>` containing the aliases to be saved.
+///
+/// # Errors
+/// This function returns a `Result<(), Box<dyn Error>>`. It can return an error if there are issues
+/// with file creation or writing to the file.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use myshell::{read_aliases_from_file, save_aliases_to_file, Alias};
+///
+/// let path = std::env::temp_dir().join("myshell-save-aliases-example");
+/// let mut aliases = HashMap::new();
+/// aliases.insert("myalias".to_string(), Alias::new("ls -l"));
+/// aliases.insert("spaced".to_string(), Alias::new(" echo  a\tb \\n "));
+///
+/// save_aliases_to_file(&path.to_string_lossy(), &aliases).unwrap();
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), "myalias\tls -l\nspaced\t echo  a\\tb \\\\n \n");
+///
+/// let mut loaded = HashMap::new();
+/// read_aliases_from_file(&path.to_string_lossy(), &mut loaded, 10).unwrap();
+/// assert_eq!(loaded, aliases);
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn save_aliases_to_file(file_name: &str, aliases: &HashMap<String, Alias>) -> Result<(), Box<dyn Error>> {
+    write_file_atomically(Path::new(file_name), |writer| {
+        // Descriptions are not part of this format; settings files keep them.
+        for (name, alias) in find_aliases(aliases, "") {
+            writeln!(writer, "{}\t{}", name, escape_alias_command(&alias.command))?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Replaces a file without ever leaving it half written.
+///
+/// # Purpose
+/// This function writes the new contents with `write` to a temporary file in the same directory as `path`,
+/// flushes it to disk, and only then renames it over `path`, which replaces the file in one step on most
+/// platforms. If `write` or any other step fails, the temporary file is removed and `path` keeps its old
+/// contents, or stays missing if it did not exist.
+///
+/// # Parameters
+/// - `path`: A `&Path` representing the file to replace or create.
+/// - `write`: A closure writing the new contents to the writer it is given.
+///
+/// # Errors
+/// This function returns an `io::Result<()>`. It returns the error of `write`, or of creating, flushing, or
+/// renaming the temporary file.
+///
+/// # Examples
+/// ```
+/// use std::io::{self, Write};
+/// use myshell::write_file_atomically;
+///
+/// let path = std::env::temp_dir().join("myshell-write-atomically-example");
+/// write_file_atomically(&path, |writer| writeln!(writer, "first")).unwrap();
+///
+/// let failed = write_file_atomically(&path, |writer| {
+///     writeln!(writer, "half")?;
+///     Err(io::Error::other("disk full"))
+/// });
+/// assert_eq!(failed.unwrap_err().to_string(), "disk full");
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\n");
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn write_file_atomically<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let file_name = path.file_name().ok_or_else(|| io::Error::other(format!("{}: not a file name", path.display())))?;
+    let mut temporary_name = std::ffi::OsString::from(".");
+    temporary_name.push(file_name);
+    temporary_name.push(format!(".tmp{}", process::id()));
+    let temporary = path.with_file_name(temporary_name);
+
+    let written = File::create(&temporary).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        fs::rename(&temporary, path)
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
+
+/// Escapes backslashes, tabs, and newlines in an alias command so it fits on one line of an alias file.
+fn escape_alias_command(command: &str) -> String {
+    command.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+/// Reverses `escape_alias_command`, keeping a backslash that starts no escape as it is.
+fn unescape_alias_command(text: &str) -> String {
+    let mut command = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            command.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => command.push('\t'),
+            Some('n') => command.push('\n'),
+            Some('\\') => command.push('\\'),
+            Some(other) => {
+                command.push('\\');
+                command.push(other);
+            }
+            None => command.push('\\'),
+        }
+    }
+    command
+}
+
+/// The part of a `ShellState` that is saved to and loaded from a settings file.
+///
+/// Every field is optional, so a hand-written `~/.myshellrc` only needs the settings it changes.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// The name of the shell.
+    pub shellname: Option<String>,
+    /// The prompt terminator.
+    pub terminator: Option<String>,
+    /// The color name the terminator is shown in, such as `green`.
+    pub terminator_color: Option<String>,
+    /// The prompt template.
+    pub prompt: Option<String>,
+    /// The continuation prompt.
+    pub prompt2: Option<String>,
+    /// The maximum number of aliases read from a file.
+    pub max_aliases: Option<usize>,
+    /// Whether the prompt is colored.
+    pub color: Option<bool>,
+    /// Aliases, kept sorted so saved files are stable.
+    pub aliases: BTreeMap<String, Alias>,
+    /// Suffix aliases, mapping a file extension to the command that runs such files, kept sorted.
+    pub suffix_aliases: BTreeMap<String, String>,
+    /// Global aliases, mapping a word to the text that replaces it anywhere in a command line, kept sorted.
+    pub global_aliases: BTreeMap<String, String>,
+    /// Shell variables, kept sorted so saved files are stable.
+    pub variables: BTreeMap<String, String>,
+    /// Whether aliases are autosaved on exit and reloaded at startup.
+    pub autosave: Option<bool>,
+    /// The file aliases are autosaved to.
+    pub autosave_path: Option<PathBuf>,
+    /// The file command lines are logged to.
+    pub log_path: Option<PathBuf>,
+    /// Whether a failing command line stops a script.
+    pub errexit: Option<bool>,
+    /// Whether each command is printed before it runs.
+    pub xtrace: Option<bool>,
+    /// Whether built-in command names are matched in any case.
+    pub case_insensitive_builtins: Option<bool>,
+    /// Whether a command line repeating the previous one is left out of the history.
+    pub hist_ignore_dups: Option<bool>,
+    /// Whether command lines typed with a leading space are left out of the history.
+    pub hist_ignore_space: Option<bool>,
+    /// Extra names for built-in commands, mapping each name to the built-in it runs, such as `exit = "STOP"`.
+    pub keywords: BTreeMap<String, String>,
+    /// The welcome banner printed when an interactive session starts; empty to print none.
+    pub banner: Option<String>,
+    /// The line printed when an interactive session ends; empty to print none.
+    pub farewell: Option<String>,
+}
+
+impl Settings {
+    /// Captures the configuration of a running shell.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{Settings, ShellState};
+    ///
+    /// let state = ShellState::default();
+    ///
+    /// assert_eq!(Settings::from_state(&state).shellname.as_deref(), Some("My Shell"));
+    /// ```
+    pub fn from_state(state: &ShellState) -> Settings {
+        let defaults = default_keywords();
+        Settings {
+            shellname: Some(state.shellname.clone()),
+            terminator: Some(state.terminator.clone()),
+            terminator_color: state.terminator_color.clone(),
+            prompt: Some(state.prompt_template.clone()),
+            prompt2: Some(state.prompt2.clone()),
+            max_aliases: Some(state.max_aliases),
+            color: Some(state.color),
+            aliases: state.aliases.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            suffix_aliases: state.suffix_aliases.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            global_aliases: state.global_aliases.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            variables: state.variables.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            autosave: Some(state.autosave),
+            autosave_path: state.autosave_path.clone(),
+            log_path: state.log_path.clone(),
+            errexit: Some(state.errexit),
+            xtrace: Some(state.xtrace),
+            case_insensitive_builtins: Some(state.case_insensitive_builtins),
+            hist_ignore_dups: Some(state.hist_ignore_dups),
+            hist_ignore_space: Some(state.hist_ignore_space),
+            keywords: state
+                .keywords
+                .iter()
+                .filter(|(name, kind)| defaults.get(*name) != Some(*kind))
+                .map(|(name, kind)| (name.clone(), kind.name().to_string()))
+                .collect(),
+            banner: Some(state.banner.clone()),
+            farewell: Some(state.farewell.clone()),
+        }
+    }
+
+    /// Applies these settings to a running shell. Settings that are not given leave the shell's value
+    /// alone, and the aliases and variables are merged into the existing ones.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{Settings, ShellState};
+    ///
+    /// let mut state = ShellState::default();
+    /// let settings = Settings { terminator: Some("$".to_string()), ..Settings::default() };
+    ///
+    /// settings.apply_to(&mut state);
+    ///
+    /// assert_eq!(state.terminator, "$");
+    /// assert_eq!(state.shellname, "My Shell");
+    /// ```
+    pub fn apply_to(self, state: &mut ShellState) {
+        if let Some(shellname) = self.shellname {
+            state.shellname = shellname;
+        }
+        if let Some(terminator) = self.terminator {
+            state.terminator = terminator;
+        }
+        if let Some(terminator_color) = self.terminator_color {
+            state.terminator_color = Some(terminator_color.to_ascii_lowercase());
+        }
+        if let Some(prompt) = self.prompt {
+            state.prompt_template = prompt;
+        }
+        if let Some(prompt2) = self.prompt2 {
+            state.prompt2 = prompt2;
+        }
+        if let Some(banner) = self.banner {
+            state.banner = banner;
+        }
+        if let Some(farewell) = self.farewell {
+            state.farewell = farewell;
+        }
+        if let Some(max_aliases) = self.max_aliases {
+            state.max_aliases = max_aliases;
+        }
+        if let Some(color) = self.color {
+            state.color = color;
+        }
+        if let Some(autosave) = self.autosave {
+            state.autosave = autosave;
+        }
+        if let Some(autosave_path) = self.autosave_path {
+            state.autosave_path = Some(autosave_path);
+        }
+        if let Some(log_path) = self.log_path {
+            state.log_path = Some(log_path);
+        }
+        if let Some(errexit) = self.errexit {
+            state.errexit = errexit;
+        }
+        if let Some(xtrace) = self.xtrace {
+            state.xtrace = xtrace;
+        }
+        if let Some(case_insensitive_builtins) = self.case_insensitive_builtins {
+            state.case_insensitive_builtins = case_insensitive_builtins;
+        }
+        if let Some(hist_ignore_dups) = self.hist_ignore_dups {
+            state.hist_ignore_dups = hist_ignore_dups;
+        }
+        if let Some(hist_ignore_space) = self.hist_ignore_space {
+            state.hist_ignore_space = hist_ignore_space;
+        }
+        for (name, builtin) in self.keywords {
+            // `read_settings_file` rejects unknown built-ins, so only hand-built settings can have them.
+            if let Some(kind) = BuiltinKind::from_name(&builtin) {
+                state.keywords.insert(name, kind);
+            }
+        }
+        for (name, mut alias) in self.aliases {
+            // Loading an alias again does not forget how often this session has run it.
+            if let Some(existing) = state.aliases.get(&name) {
+                alias.usage_count = alias.usage_count.max(existing.usage_count);
+            }
+            state.aliases.insert(name, alias);
+        }
+        state.suffix_aliases.extend(self.suffix_aliases);
+        state.global_aliases.extend(self.global_aliases);
+        state.variables.extend(self.variables);
+    }
+}
+
+/// Finds the startup configuration file.
+///
+/// # Purpose
+/// This function returns the path of `.myshellrc` in the user's home directory, taken from `HOME` or, on
+/// Windows, `USERPROFILE`. The file need not exist.
+///
+/// # Returns
+/// This function returns an `Option<PathBuf>`, or `None` if no home directory is known.
+///
+/// # Examples
+/// ```
+/// use myshell::default_config_path;
+///
+/// if let Some(path) = default_config_path() {
+///     assert!(path.ends_with(".myshellrc"));
+/// }
+/// ```
+pub fn default_config_path() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(".myshellrc"))
+}
+
+/// Finds the user's home directory.
+///
+/// # Purpose
+/// This function returns the home directory named by `HOME` or, on Windows, `USERPROFILE`.
+///
+/// # Returns
+/// This function returns an `Option<PathBuf>`, or `None` if neither variable is set.
+///
+/// # Examples
+/// ```
+/// use myshell::home_dir;
+///
+/// if let Some(home) = home_dir() {
+///     println!("home is {}", home.display());
+/// }
+/// ```
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+/// Loads the autosaved aliases at startup.
+///
+/// # Purpose
+/// This function reads the aliases saved in `state.autosave_path` by the previous session, if autosave is
+/// enabled and the file exists. Reading stops at the alias limit, as with `READNEWNAMES`.
+///
+/// # Parameters
+/// - `state`: A mutable reference to the `ShellState` to add the aliases to.
+///
+/// # Errors
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the file exists but cannot
+/// be read.
+///
+/// # Examples
+/// ```
+/// use myshell::{autoload_aliases, ShellState};
+///
+/// let path = std::env::temp_dir().join("myshell-autoload-example");
+/// std::fs::write(&path, "ll ls -l\n").unwrap();
+///
+/// let mut state = ShellState { autosave: true, autosave_path: Some(path.clone()), ..ShellState::default() };
+/// autoload_aliases(&mut state).unwrap();
+///
+/// assert_eq!(state.aliases["ll"].command, "ls -l");
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn autoload_aliases(state: &mut ShellState) -> Result<(), Box<dyn Error>> {
+    match &state.autosave_path {
+        Some(path) if state.autosave && path.exists() => {
+            read_aliases_from_file(&path.to_string_lossy(), &mut state.aliases, state.max_aliases).map(|_| ())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Saves the aliases on exit.
+///
+/// # Purpose
+/// This function writes the current aliases to `state.autosave_path` if autosave is enabled, so the next
+/// session starts with them. It runs both when `STOP` is used and when input ends with Ctrl+D.
+///
+/// # Parameters
+/// - `state`: A reference to the `ShellState` whose aliases are saved.
+///
+/// # Errors
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` if the file cannot be written.
+///
+/// # Examples
+/// ```
+/// use myshell::{autosave_aliases, Alias, ShellState};
+///
+/// let path = std::env::temp_dir().join("myshell-autosave-example");
+///
+/// let mut state = ShellState { autosave: true, autosave_path: Some(path.clone()), ..ShellState::default() };
+/// state.aliases.insert("ll".to_string(), Alias::new("ls -l"));
+/// autosave_aliases(&state).unwrap();
+///
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), "ll\tls -l\n");
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn autosave_aliases(state: &ShellState) -> Result<(), Box<dyn Error>> {
+    match &state.autosave_path {
+        Some(path) if state.autosave => save_aliases_to_file(&path.to_string_lossy(), &state.aliases),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a settings file.
+///
+/// # Purpose
+/// This function parses a TOML settings file, such as `~/.myshellrc` or one written by `SAVESETTINGS`.
+/// It is the loader shared by startup and `LOADSETTINGS`.
+///
+/// # Parameters
+/// - `path`: A reference to the `Path` of the file to read.
+///
+/// # Errors
+/// This function returns a `Result<Settings, Box<dyn Error>>`. It returns an `Err` naming the file if it
+/// cannot be read, is not valid settings TOML, maps a keyword to a built-in command that does not exist, or
+/// names a terminator color that `ansi_color_code` does not know.
+///
+/// # Examples
+/// ```
+/// use myshell::read_settings_file;
+///
+/// let path = std::env::temp_dir().join("myshell-read-settings-example.toml");
+/// std::fs::write(&path, "max_aliases = 20\n").unwrap();
+///
+/// assert_eq!(read_settings_file(&path).unwrap().max_aliases, Some(20));
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn read_settings_file(path: &Path) -> Result<Settings, Box<dyn Error>> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let settings: Settings = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    for (name, builtin) in &settings.keywords {
+        if BuiltinKind::from_name(builtin).is_none() {
+            return Err(format!("{}: keyword '{}' names '{}', which is not a built-in command", path.display(), name, builtin).into());
+        }
+    }
+    if let Some(color) = settings.terminator_color.as_deref().filter(|color| ansi_color_code(color).is_none()) {
+        return Err(format!("{}: unknown terminator color '{}'", path.display(), color).into());
+    }
+    Ok(settings)
+}
+
+/// Writes a settings file.
+///
+/// # Purpose
+/// This function serializes settings as TOML and writes them to a file, replacing any existing contents.
+///
+/// # Parameters
+/// - `path`: A reference to the `Path` of the file to write.
+/// - `settings`: A reference to the `Settings` to save.
+///
+/// # Errors
+/// This function returns a `Result<(), Box<dyn Error>>`. It returns an `Err` naming the file if it cannot
+/// be written.
+///
+/// # Examples
+/// ```
+/// use myshell::{read_settings_file, write_settings_file, Settings, ShellState};
+///
+/// let path = std::env::temp_dir().join("myshell-write-settings-example.toml");
+/// let settings = Settings::from_state(&ShellState::default());
+///
+/// write_settings_file(&path, &settings).unwrap();
+///
+/// assert_eq!(read_settings_file(&path).unwrap(), settings);
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn write_settings_file(path: &Path, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let text = toml::to_string(settings)?;
+    write_file_atomically(path, |writer| writer.write_all(text.as_bytes())).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
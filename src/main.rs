@@ -1,5 +1,37 @@
-use input_macro::input;
-use std::{error::Error, process::{exit, Command}, collections::HashMap, fs::File, io::{BufReader, BufRead, BufWriter, Write}};
+use myshell::{autoload_aliases, handle_interrupt, home_dir, join_continuations, print_banner, read_settings_file, run_lines, run_loop, run_with_input, shutdown, source_file, strip_ansi, strip_continuation, ShellHelper, ShellState, INTERRUPTED};
+use rustyline::{config::Config, error::ReadlineError, history::{DefaultHistory, History}, CompletionType, EditMode, Editor, Prompt};
+use std::{env, error::Error, io::{self, BufRead, IsTerminal, Read}, process::exit, sync::atomic::Ordering};
+
+/// How to invoke the shell, printed by `--help`.
+const USAGE: &str = "\
+Usage: myshell-rs [options]                    start an interactive shell
+       myshell-rs [options] -c <command line>  run one command line and exit
+       myshell-rs [options] <script> [args...] run the commands in a script file and exit
+
+Options:
+  -c <command line>  Run the command line, which may contain several lines, instead of reading input.
+  --no-color         Leave ANSI color sequences out of the prompt.
+  -h, --help         Print this help and exit.
+  -V, --version      Print the version and exit.
+
+Settings are read at startup from ~/.myshellrc, a TOML file as written by SAVESETTINGS.
+Interactive sessions open with its banner setting, which an empty string turns off.
+Non-interactive runs exit with the status of the last command, or stop at the first failing
+command line after SETOPT errexit on. Type HELP in the shell to list its built-in commands.";
+
+/// What the command-line arguments ask the shell to do.
+enum Mode {
+    /// Read commands from the terminal.
+    Interactive,
+    /// Print `USAGE` and exit.
+    Help,
+    /// Print the version and exit.
+    Version,
+    /// Run the command line given with `-c` and exit.
+    Command(String),
+    /// Run a script file with its arguments and exit.
+    Script(String, Vec<String>),
+}
 
 /// The main function of the MyShell program.
 ///
@@ -13,461 +45,204 @@ use std::{error::Error, process::{exit, Command}, collections::HashMap, fs::File
 //
 /// ```
 fn main() -> Result<(), Box<dyn Error + 'static>> {
-    let mut shellname = format!("My Shell"); // Default shell name
-    let mut terminator = format!(">"); // Default terminator
-    let mut aliases: HashMap<String, String> = HashMap::new(); // Initialize alias HashMap
-    let max_aliases = 10;
+    let (mode, no_color) = match parse_args(env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+    match mode {
+        Mode::Help => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Mode::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        _ => {}
+    }
 
-    loop {
-        let input: String = {
-            let input = input!("{}{} ", shellname, terminator); // Take input
-            input.to_string()
-        }; // Workaround to ensure input lives long enough.
+    // The binary autosaves to the home directory; the configuration below can move the file or turn it off.
+    let mut state = ShellState {
+        autosave: true,
+        autosave_path: home_dir().map(|home| home.join(".myshell_aliases")),
+        ..ShellState::default()
+    };
+    if let Some(path) = state.config_path.clone().filter(|path| path.exists()) {
+        match read_settings_file(&path) {
+            Ok(settings) => settings.apply_to(&mut state),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+    if let Err(e) = autoload_aliases(&mut state) {
+        eprintln!("Error loading aliases: {}", e);
+    }
+    if no_color {
+        state.color = false;
+    }
 
-        let inputs: Vec<_> = input
-            .split_whitespace() // Split inputs by the empty spaces between them.
-            .collect(); // Collects split elements into Vector.
+    ctrlc::set_handler(handle_interrupt)?; // Keep the shell alive on Ctrl+C.
 
-            if let Err(e) = match_inputs(&inputs, &mut shellname, &mut terminator, &mut aliases, max_aliases) {
+    match mode {
+        Mode::Command(command_line) => {
+            run_lines(&join_continuations(command_line.lines().map(String::from)), &mut state);
+            exit(shutdown(&state, state.exit_status.unwrap_or(state.last_status)));
+        }
+        Mode::Script(path, args) => {
+            state.verbose = false; // A script runs quietly unless it turns `verbose` back on.
+            let inputs: Vec<&str> = ["SOURCE", &path].into_iter().chain(args.iter().map(String::as_str)).collect();
+            if let Err(e) = source_file(&inputs, &mut state) {
                 eprintln!("Error: {}", e);
             }
-    } // End of Shell's loop
-}
-
-/// Matches and handles user input commands.
-///
-/// # Purpose
-/// This function is responsible for matching and handling user input commands. It performs actions
-/// based on the provided input, such as setting the shell name, changing the terminator, managing aliases,
-/// and executing commands.
-///
-/// # Parameters
-/// - `inputs`: A slice of `&str` representing the user input split into individual words.
-/// - `shellname`: A mutable reference to a `String` containing the name of the shell.
-/// - `terminator`: A mutable reference to a `String` containing the current terminator.
-/// - `aliases`: A mutable reference to a `HashMap<String, String>` containing user-defined aliases.
-/// - `max_aliases`: An `usize` indicating the maximum number of aliases allowed.
-///
-/// # Returns
-/// This function returns a `Result<(), Box<dyn Error>>`. It returns `Ok(())` if the command is executed
-/// successfully and returns an `Err` containing an error message if any errors occur during execution.
-///
-/// # Examples
-/// ```
-/// use std::collections::HashMap;
-/// use myshell::match_inputs;
-///
-/// let mut shellname = "My Shell".to_string();
-/// let mut terminator = ">".to_string();
-/// let mut aliases = HashMap::new();
-///
-/// let inputs = vec!["SETSHELLNAME", "Custom Shell Name"];
-///
-/// if let Err(e) = match_inputs(&inputs, &mut shellname, &mut terminator, &mut aliases, 10) {
-///     eprintln!("Error: {}", e);
-/// }
-/// ```
-fn match_inputs(inputs: &[&str],shellname: &mut String,terminator: &mut String,aliases: &mut HashMap<String, String>,max_aliases: usize) -> Result<(), Box<dyn Error>> {
-    match inputs.get(0) {
-        Some(&"STOP") 
-            => exit(0),
-        Some(&"SETSHELLNAME") 
-            => set_shell_name(inputs, shellname),
-        Some(&"SETTERMINATOR") 
-            =>  set_terminator(inputs, terminator),
-        Some(&"NEWNAME") 
-            => set_new_name(inputs, aliases),
-        Some(&"READNEWNAMES") 
-            => read_new_names(inputs, aliases, max_aliases),
-        Some(&"LISTNEWNAMES") 
-            => list_new_names(aliases),
-        Some(&"SAVENEWNAMES") 
-            => save_new_names(inputs, aliases),
-        Some(command) => {
-            if let Some(alias_command) = aliases.get(command.to_string().as_str()) {
-                // Execute the alias command if it exists
-                let alias_args: Vec<&str> = alias_command.split_whitespace().collect();
-                if let Err(e) = execute_command(alias_args[0], &alias_args[1..]) {
-                    eprintln!("Error executing alias command: {}", e);
-                }
-            } else {
-                if let Err(e) = execute_command(command, &inputs[1..]) {
-                    eprintln!("Error executing command: {}", e);
-                }
-            }
+            exit(shutdown(&state, state.exit_status.unwrap_or(state.last_status)));
         }
-        None => {}
+        _ => {}
     }
 
-    Ok(())
-}
-
-/// Sets the name of the shell.
-///
-/// # Purpose
-/// This function sets the name of the shell to a new value based on user input. It collects and joins
-/// the input words into a single string and updates the `shellname` reference accordingly.
-///
-/// # Parameters
-/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
-///   first word is the command (`SETSHELLNAME`) and the rest are the words for the new shell name.
-/// - `shellname`: A mutable reference to a `String` containing the current name of the shell.
-///
-/// # Returns
-/// This function does not return any value. It updates the `shellname` reference in-place.
-///
-/// # Examples
-/// ```
-/// use myshell::set_shell_name;
-///
-/// let mut shellname = "My Shell".to_string();
-/// let inputs = vec!["SETSHELLNAME", "Custom Shell Name"];
-///
-/// set_shell_name(&inputs, &mut shellname);
-///
-/// assert_eq!(shellname, "Custom Shell Name");
-/// ```
-fn set_shell_name(inputs: &[&str], shellname: &mut String) {
-    let new_name = inputs
-        .iter()
-        .skip(1)
-        .map(|&s| s)
-        .collect::<Vec<_>>()
-        .join(" ")
-        .to_string();
-        *shellname = new_name;
-    println!("Shell name set to: {}", shellname);
-}
+    state.interactive = io::stdin().is_terminal();
+    if state.interactive {
+        print_banner(&state);
+
+        // The editor keeps duplicates so Up/Down walk exactly the entries `HISTORY` lists. The Emacs bindings
+        // give Ctrl+R, a reverse incremental search over the same entries whose match Enter runs.
+        let config = Config::builder()
+            .edit_mode(EditMode::Emacs)
+            .completion_type(CompletionType::List)
+            .history_ignore_dups(false)?
+            .build();
+        let mut editor: Editor<ShellHelper, DefaultHistory> = Editor::with_config(config)?;
+        editor.set_helper(Some(ShellHelper::default()));
+        for entry in &state.history {
+            editor.add_history_entry(entry.as_str())?;
+        }
 
-/// Sets the terminator for the shell.
-///
-/// # Purpose
-/// This function sets the terminator for the shell based on user input. If a new terminator is provided,
-/// it updates the `terminator` reference with the new value. If no terminator is specified in the input,
-/// it keeps the current terminator as is.
-///
-/// # Parameters
-/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
-///   first word is the command (`SETTERMINATOR`) and the second word (if present) is the new terminator.
-/// - `terminator`: A mutable reference to a `String` containing the current terminator for the shell.
-///
-/// # Returns
-/// This function does not return any value. It updates the `terminator` reference in-place and prints
-/// a message indicating the new terminator value.
-///
-/// # Examples
-/// ```
-/// use myshell::set_terminator;
-///
-/// let mut terminator = ">".to_string();
-/// let inputs = vec!["SETTERMINATOR", "<"];
-///
-/// set_terminator(&inputs, &mut terminator);
-///
-/// assert_eq!(terminator, "<");
-/// ```
-fn set_terminator(inputs: &[&str], terminator: &mut String) {
-    if let Some(new_terminator) = inputs.get(1) {
-        *terminator = new_terminator.to_string();
-        println!("Terminator set to: {}", terminator);
+        run_loop(&mut state, |state, prompt, buffer| {
+            if let Some(helper) = editor.helper_mut() {
+                helper.aliases = state.aliases.keys().cloned().collect();
+            }
+            if state.history.is_empty() && !editor.history().is_empty() {
+                editor.clear_history()?; // CLEARHISTORY empties the editor's copy too.
+            }
+            // The editor measures the plain prompt, so color sequences don't throw off the cursor position.
+            match read_line(&mut editor, &(strip_ansi(prompt), prompt), state)? {
+                Some(line) => {
+                    *buffer = line; // The editor hands over a new line each time, so there is nothing to reuse.
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })?;
     } else {
-        println!("No terminator specified. Using the default terminator: {}", terminator);
+        run_with_input(StdinLines::default(), &mut io::stdout(), &mut state)?;
     }
+
+    // The end of input shuts down just as STOP does.
+    exit(shutdown(&state, state.exit_status.unwrap_or(state.last_status)))
 }
 
-/// Manages the alias list.
+/// Reads one command line from the editor.
 ///
 /// # Purpose
-/// This function manages the alias list based on user input. It can perform three different operations:
-///
-/// 1. If no arguments are provided, it prints the current alias list.
-/// 2. If one argument is provided, it deletes the alias with the given name if it exists.
-/// 3. If two arguments are provided, it defines or updates an alias with the first argument as the new alias name
-///    and the second argument as the command associated with the alias.
-///
-/// # Parameters
-/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
-///   first word is the command (`NEWNAME`) and the rest are arguments.
-/// - `aliases`: A mutable reference to a `HashMap<String, String>` containing user-defined aliases.
+/// This function reads a line, joining on following lines for as long as it ends in a trailing backslash, and
+/// adds the result to the editor's history unless `ShellState::keeps_in_history` leaves it out. Ctrl+C sets `INTERRUPTED` and, like the end of input, returns `None`.
 ///
 /// # Returns
-/// This function does not return any value. It manages the `aliases` reference in-place and prints messages
-/// to indicate the result of the operation.
-///
-/// # Examples
-/// ```
-/// use std::collections::HashMap;
-/// use myshell::set_new_name;
-///
-/// let mut aliases = HashMap::new();
-/// let inputs = vec!["NEWNAME", "myalias", "mycommand"];
-///
-/// set_new_name(&inputs, &mut aliases);
-///
-/// assert_eq!(aliases.get("myalias"), Some(&"mycommand".to_string()));
-/// ```
-fn set_new_name(inputs: &[&str], aliases: &mut HashMap<String, String>) {
-    if inputs.len() == 1 {
-        // No arguments provided, print the alias list
-        println!("Aliases:");
-        for (alias, command) in aliases {
-            println!("{} - {}", alias, command);
-        }
-    } else if inputs.len() == 2 {
-        // Delete the alias if it exists
-        let alias_to_delete = inputs[1];
-        if aliases.contains_key(alias_to_delete) {
-            aliases.remove(alias_to_delete);
-            println!("Alias '{}' deleted.", alias_to_delete);
-        } else {
-            println!("Alias '{}' does not exist.", alias_to_delete);
+/// This function returns a `Result<Option<String>, Box<dyn Error>>` with the line, `None` if it was abandoned
+/// or input ended, or an `Err` if the terminal could not be read.
+fn read_line<P: Prompt + ?Sized>(editor: &mut Editor<ShellHelper, DefaultHistory>, prompt: &P, state: &ShellState) -> Result<Option<String>, Box<dyn Error>> {
+    let mut input = match editor.readline(prompt) {
+        Ok(input) => input,
+        Err(ReadlineError::Interrupted) => {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+            return Ok(None);
         }
-    } else if inputs.len() == 3 {
-        // Create or update an alias
-        let new_alias = inputs[1];
-        let old_command = inputs[2];
-        aliases.insert(new_alias.to_string(), old_command.to_string());
-        println!("Alias '{}' defined for '{}'.", new_alias, old_command);
-    } else {
-        println!("Invalid usage of NEWNAME command.");
-    }
-}
+        Err(ReadlineError::Eof) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
 
-/// Reads aliases from a file and populates the alias list.
-///
-/// # Purpose
-/// This function reads aliases from a specified file and populates the `aliases` map with the aliases
-/// found in the file, up to the specified maximum number of aliases (`max_aliases`).
-///
-/// # Parameters
-/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
-///   first word is the command (`READNEWNAMES`) and the second word is the name of the file to read
-///   aliases from.
-/// - `aliases`: A mutable reference to a `HashMap<String, String>` containing user-defined aliases.
-/// - `max_aliases`: An `usize` indicating the maximum number of aliases allowed.
-///
-/// # Returns
-/// This function does not return any value. It populates the `aliases` map with aliases read from the file
-/// and handles any errors that may occur during file reading.
-///
-/// # Examples
-/// ```
-/// use std::collections::HashMap;
-/// use myshell::{read_new_names, read_aliases_from_file};
-///
-/// let mut aliases = HashMap::new();
-/// let inputs = vec!["READNEWNAMES", "aliases.txt"];
-/// let max_aliases = 10;
-///
-/// read_new_names(&inputs, &mut aliases, max_aliases);
-/// ```
-fn read_new_names(inputs: &[&str], aliases: &mut HashMap<String, String>, max_aliases: usize) {
-    if inputs.len() != 2 {
-        println!("Usage: READNEWNAMES <file_name>");
-        return;
+    // A trailing backslash joins the next line onto this one.
+    while let Some(head) = strip_continuation(&input) {
+        let head = head.to_string();
+        match editor.readline(state.prompt2.as_str()) {
+            Ok(next) => input = head + &next,
+            Err(ReadlineError::Interrupted) => {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+                return Ok(None);
+            }
+            Err(ReadlineError::Eof) => {
+                eprintln!("Error: unexpected end of input after a trailing '\\'");
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        }
     }
 
-    let file_name = inputs[1];
-    if let Err(e) = read_aliases_from_file(file_name, aliases, max_aliases) {
-        eprintln!("Error reading aliases from file: {}", e);
+    if state.keeps_in_history(&input) {
+        editor.add_history_entry(input.trim())?;
     }
+    Ok(Some(input))
 }
 
-/// Lists all the aliases that have been defined.
+/// Reads standard input one line at a time.
 ///
-/// # Purpose
-/// This function lists all the aliases that have been defined and stored in the `aliases` map.
-///
-/// # Parameters
-/// - `aliases`: A reference to a `HashMap<String, String>` containing user-defined aliases.
-///
-/// # Returns
-/// This function does not return any value. It prints the list of aliases.
-///
-/// # Examples
-/// ```
-/// use std::collections::HashMap;
-/// use myshell::list_new_names;
-///
-/// let mut aliases = HashMap::new();
-/// aliases.insert("mycd".to_string(), "cd".to_string());
-/// aliases.insert("mycopy".to_string(), "cp".to_string());
-///
-/// list_new_names(&aliases);
-/// ```
-fn list_new_names(aliases: &HashMap<String, String>) {
-    println!("Aliases:");
-    for (alias, command) in aliases {
-        println!("{} - {}", alias, command);
-    }
+/// Taking a line only when asked, through the shared standard input buffer, leaves the rest of the input for
+/// commands such as `READ`, which read standard input too.
+#[derive(Default)]
+struct StdinLines {
+    /// The line being handed out.
+    line: String,
+    /// How much of `line` has been consumed.
+    consumed: usize,
 }
 
-/// Handles the SAVENEWNAMES command.
-///
-/// # Purpose
-/// This function handles the SAVENEWNAMES command, which saves the aliases stored in the `aliases` map to a file.
-///
-/// # Parameters
-/// - `inputs`: A slice of `&str` representing the user input split into individual words, where the
-///   first word is the command (`SAVENEWNAMES`) and the second word is the name of the file to save
-///   aliases to.
-/// - `aliases`: A reference to a `HashMap<String, String>` containing user-defined aliases.
-///
-/// # Returns
-/// This function does not return any value. It prints a message to indicate the result of the operation
-/// and handles any errors that may occur during file writing.
-///
-/// # Examples
-/// ```
-/// use std::collections::HashMap;
-/// use myshell::save_new_names;
-///
-/// let mut aliases = HashMap::new();
-/// aliases.insert("mycd".to_string(), "cd".to_string());
-/// aliases.insert("mycopy".to_string(), "cp".to_string());
-///
-/// let inputs = vec!["SAVENEWNAMES", "aliases.txt"];
-///
-/// save_new_names(&inputs, &aliases);
-/// ```
-fn save_new_names(inputs: &[&str], aliases: &HashMap<String, String>) {
-    if inputs.len() != 2 {
-        println!("Usage: SAVENEWNAMES <file_name>");
-        return;
+impl Read for StdinLines {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
     }
+}
 
-    let file_name = inputs[1];
-    if let Err(e) = save_aliases_to_file(file_name, aliases) {
-        eprintln!("Error saving aliases to file: {}", e);
-    } else {
-        println!("Aliases saved to file: {}", file_name);
+impl BufRead for StdinLines {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed == self.line.len() {
+            self.line.clear();
+            self.consumed = 0;
+            io::stdin().read_line(&mut self.line)?;
+        }
+        Ok(&self.line.as_bytes()[self.consumed..])
     }
-}
 
-/// Executes a command with the specified arguments.
-///
-/// # Purpose
-/// This function executes a command with the provided arguments using the `std::process::Command` struct.
-///
-/// # Parameters
-/// - `command`: A `&str` representing the command to be executed.
-/// - `args`: A slice of `&str` representing the arguments to be passed to the command.
-///
-/// # Returns
-/// This function returns a `Result<(), Box<dyn Error>>`. It returns `Ok(())` if the command is executed
-/// successfully and returns an `Err` containing an error message if the command returns a non-zero exit status.
-///
-/// # Examples
-/// ```
-/// use myshell::execute_command;
-///
-/// if let Err(e) = execute_command("ls", &["-l"]) {
-///     eprintln!("Error: {}", e);
-/// }
-/// ```
-fn execute_command(command: &str, args: &[&str]) -> Result<(), Box<dyn Error>> {
-    let status = Command::new(command)
-        .args(args)
-        .status()?;
-    
-    if status.success() {
-        Ok(()) // Program ended successfully.
-    } else {
-        Err(format!("Command '{}' returned a non-zero exit status", command).into()) // Return error that command ended with.
+    fn consume(&mut self, amount: usize) {
+        self.consumed += amount;
     }
 }
 
-/// Reads aliases from a file and populates a HashMap.
-///
-/// # Purpose
-/// This function reads aliases from a specified file and populates a mutable `HashMap<String, String>`
-/// with the alias-command pairs found in the file, up to a maximum specified by `max_aliases`.
-///
-/// # Parameters
-/// - `file_name`: A `&str` representing the name of the file from which to read aliases.
-/// - `aliases`: A mutable reference to a `HashMap<String, String>` that will store the aliases.
-/// - `max_aliases`: An `usize` indicating the maximum number of aliases to read from the file.
+/// Works out the mode and options from the command-line arguments.
 ///
-/// # Errors
-/// This function returns a `Result<(), Box<dyn Error>>`. It can return an error if there are issues
-/// with file reading or parsing.
-///
-/// # Examples
-/// ```
-/// use std::collections::HashMap;
-/// use myshell::read_aliases_from_file;
-///
-/// let mut aliases = HashMap::new();
-///
-/// if let Err(e) = read_aliases_from_file("aliases.txt", &mut aliases, 10) {
-///     eprintln!("Error: {}", e);
-/// }
-/// ```
-fn read_aliases_from_file(file_name: &str, aliases: &mut HashMap<String, String>, max_aliases: usize) -> Result<(), Box<dyn Error>> {
-    let file = File::open(file_name)?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines() {
-        let line = line?;
-        let parts: Vec<&str> = line.splitn(2, ' ').collect();
-
-        if parts.len() == 2 {
-            let alias = parts[0];
-            let command = parts[1];
-            aliases.insert(alias.to_string(), command.to_string());
-
-            if aliases.len() >= max_aliases {
-                break;
+/// # Returns
+/// This function returns a `Result<(Mode, bool), Box<dyn Error>>` with the mode and whether `--no-color` was
+/// given, or an `Err` for an unknown option or a `-c` without a command line. Arguments after a script's
+/// name belong to the script, which sees them as `$1`, `$2` and so on, and are not parsed.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<(Mode, bool), Box<dyn Error>> {
+    let mut args = args.into_iter();
+    let mut no_color = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-color" => no_color = true,
+            "-h" | "--help" => return Ok((Mode::Help, no_color)),
+            "-V" | "--version" => return Ok((Mode::Version, no_color)),
+            "-c" => {
+                let command_line = args.next().ok_or("-c needs a command line")?;
+                return Ok((Mode::Command(command_line), no_color));
             }
+            option if option.starts_with('-') => return Err(format!("unknown option '{}'", option).into()),
+            _ => return Ok((Mode::Script(arg, args.collect()), no_color)),
         }
     }
-
-    Ok(())
+    Ok((Mode::Interactive, no_color))
 }
-/// Saves the aliases to a file.
-///
-/// # Purpose
-/// This function is responsible for saving a HashMap of aliases to a specified file.
-///
-/// # Parameters
-/// - `file_name`: A `&str` representing the name of the file where the aliases will be saved.
-/// - `aliases`: A reference to a `HashMap<String, String>` containing the aliases to be saved.
-///
-/// # Errors
-/// This function returns a `Result<(), Box<dyn Error>>`. It can return an error if there are issues
-/// with file creation or writing to the file.
-///
-/// # Examples
-/// ```
-/// use std::collections::HashMap;
-/// use myshell::save_aliases_to_file;
-///
-/// let mut aliases = HashMap::new();
-/// aliases.insert("myalias".to_string(), "ls -l".to_string());
-///
-/// if let Err(e) = save_aliases_to_file("aliases.txt", &aliases) {
-///     eprintln!("Error: {}", e);
-/// }
-/// ```
-fn save_aliases_to_file(file_name: &str, aliases: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
-    let file = match File::create(file_name) {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("Error creating file: {}", e);
-            return Err(Box::new(e));
-        }
-    };
-
-    let mut writer = BufWriter::new(file);
-    for (alias, command) in aliases {
-        match writeln!(writer, "{} {}", alias, command) {
-            Ok(_) => continue,
-            Err(e) => {
-                eprintln!("Error writing to file: {}", e);
-                break;
-            }
-        }
-
-    }
-    Ok(())
-}
\ No newline at end of file
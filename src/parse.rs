@@ -0,0 +1,276 @@
+//! Turning the words of a command line into a `Command` tree.
+//!
+//! The grammar is a small subset of the POSIX shell's. Operators must be separate words, as in `ls | wc`:
+//!
+//! ```text
+//! line     := and_or ((';' | '&') and_or)* [';' | '&']
+//! and_or   := pipeline (('&&' | '||') pipeline)*
+//! pipeline := simple ('|' simple)*
+//! simple   := word+ with any redirections mixed in (see `parse_redirections`)
+//! ```
+
+use crate::{parse_redirections, BuiltinKind, Redirections, ShellError, BUILTINS};
+
+/// Block keywords that are not commands of their own but are still answered by the shell rather than run.
+const KEYWORDS: [&str; 5] = ["THEN", "ELSE", "FI", "DO", "DONE"];
+
+/// A parsed command line, ready for `execute`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// A command handled by the shell itself.
+    Builtin {
+        /// The command name, such as `ECHO`.
+        name: String,
+        /// The words after the name.
+        args: Vec<String>,
+    },
+    /// An alias or a program to run.
+    External {
+        /// The alias or program name.
+        name: String,
+        /// The words after the name.
+        args: Vec<String>,
+    },
+    /// A simple command whose streams are connected to files.
+    Redirect {
+        /// The `Builtin` or `External` command being redirected.
+        command: Box<Command>,
+        /// The files its streams are connected to.
+        redirections: Redirections,
+    },
+    /// Commands joined by `|`, each one's standard output feeding the next one's standard input.
+    Pipeline(Vec<Command>),
+    /// Commands separated by `;` or `&`, run one after another.
+    Sequence(Vec<Command>),
+    /// Two commands joined by `&&`; the second runs only if the first succeeds.
+    And(Box<Command>, Box<Command>),
+    /// Two commands joined by `||`; the second runs only if the first fails.
+    Or(Box<Command>, Box<Command>),
+    /// A command followed by `&`, started without waiting for it.
+    Background(Box<Command>),
+}
+
+impl Command {
+    /// Looks every command name up with `resolve`, such as `ShellState::builtin_named`, so that names from the
+    /// keyword table run the built-in commands they stand for under those commands' own names. A built-in name
+    /// that `resolve` does not know becomes an external command; block keywords such as `THEN` are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{parse, tokenize, BuiltinKind, Command};
+    ///
+    /// let command = parse(&tokenize("exit 3 || ECHO hi").unwrap()).unwrap();
+    /// let command = command.resolve_builtins(&|name| if name == "exit" { BuiltinKind::from_name("STOP") } else { None });
+    ///
+    /// let Command::Or(left, right) = command else { unreachable!() };
+    /// assert!(matches!(*left, Command::Builtin { ref name, .. } if name == "STOP"));
+    /// assert!(matches!(*right, Command::External { ref name, .. } if name == "ECHO"));
+    /// ```
+    pub fn resolve_builtins(self, resolve: &dyn Fn(&str) -> Option<BuiltinKind>) -> Command {
+        self.map_simple(&|command| match command {
+            Command::Builtin { name, args } | Command::External { name, args } if !KEYWORDS.contains(&name.as_str()) => {
+                match resolve(&name) {
+                    Some(kind) => Command::Builtin { name: kind.name().to_string(), args },
+                    None => Command::External { name, args },
+                }
+            }
+            command => command,
+        })
+    }
+
+    /// Rewrites every external command whose name `resolve` finds a suffix alias for, such as `python` for
+    /// `./script.py`, so that the command it names runs with the original name as its first argument. The
+    /// handler may hold several words, as in `python3 -u`. Built-in commands are left alone.
+    ///
+    /// # Examples
+    /// ```
+    /// use myshell::{parse, tokenize, Command};
+    ///
+    /// let command = parse(&tokenize("./build.py --fast").unwrap()).unwrap();
+    /// let command = command.resolve_suffix_aliases(&|name| name.ends_with(".py").then(|| "python3 -u".to_string()));
+    ///
+    /// let args = ["-u", "./build.py", "--fast"].map(String::from).to_vec();
+    /// assert_eq!(command, Command::External { name: "python3".to_string(), args });
+    /// ```
+    pub fn resolve_suffix_aliases(self, resolve: &dyn Fn(&str) -> Option<String>) -> Command {
+        self.map_simple(&|command| match command {
+            Command::External { name, args } => {
+                let Some(handler) = resolve(&name) else {
+                    return Command::External { name, args };
+                };
+                let mut words = handler.split_whitespace().map(String::from);
+                let Some(program) = words.next() else {
+                    return Command::External { name, args };
+                };
+                Command::External { name: program, args: words.chain(std::iter::once(name)).chain(args).collect() }
+            }
+            command => command,
+        })
+    }
+
+    /// Rebuilds the tree with `f` applied to every `Builtin` and `External` command in it.
+    fn map_simple(self, f: &dyn Fn(Command) -> Command) -> Command {
+        let map_inner = |command: Box<Command>| Box::new(command.map_simple(f));
+        match self {
+            Command::Builtin { .. } | Command::External { .. } => f(self),
+            Command::Redirect { command, redirections } => Command::Redirect { command: map_inner(command), redirections },
+            Command::Pipeline(stages) => Command::Pipeline(stages.into_iter().map(|stage| stage.map_simple(f)).collect()),
+            Command::Sequence(commands) => Command::Sequence(commands.into_iter().map(|command| command.map_simple(f)).collect()),
+            Command::And(left, right) => Command::And(map_inner(left), map_inner(right)),
+            Command::Or(left, right) => Command::Or(map_inner(left), map_inner(right)),
+            Command::Background(command) => Command::Background(map_inner(command)),
+        }
+    }
+}
+
+/// Parses the words of a command line.
+///
+/// # Purpose
+/// This function builds the `Command` tree for a line that `tokenize` has split into words, without running
+/// anything. `;` and `&` separate commands, `&&` and `||` chain them from left to right with equal
+/// precedence, and `|` joins them into pipelines, which bind tightest. A command name found in the built-in
+/// command table becomes a `Command::Builtin`; any other name, such as an alias or a program, becomes a
+/// `Command::External`. A single command is returned as it is rather than as a one-element sequence, and an
+/// empty line is an empty `Command::Sequence`.
+///
+/// # Parameters
+/// - `tokens`: A slice of `String` representing the words of the line.
+///
+/// # Returns
+/// This function returns a `Result<Command, ShellError>` with the parsed line. It returns `ShellError::Parse`
+/// if an operator is missing the command on either side of it, or a redirection is malformed.
+///
+/// # Examples
+/// ```
+/// use myshell::{parse, tokenize, Command};
+///
+/// let words = |name: &str, args: &[&str]| (name.to_string(), args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>());
+///
+/// let (name, args) = words("ECHO", &["hi"]);
+/// assert_eq!(parse(&tokenize("ECHO hi").unwrap()).unwrap(), Command::Builtin { name, args });
+///
+/// let (ls, ls_args) = words("ls", &["-l"]);
+/// let (wc, wc_args) = words("wc", &[]);
+/// let (echo, echo_args) = words("echo", &["done"]);
+/// assert_eq!(
+///     parse(&tokenize("ls -l | wc && echo done").unwrap()).unwrap(),
+///     Command::And(
+///         Box::new(Command::Pipeline(vec![
+///             Command::External { name: ls, args: ls_args },
+///             Command::External { name: wc, args: wc_args },
+///         ])),
+///         Box::new(Command::External { name: echo, args: echo_args }),
+///     )
+/// );
+///
+/// assert!(matches!(parse(&tokenize("sleep 1 & ls ;").unwrap()).unwrap(), Command::Sequence(commands) if commands.len() == 2));
+/// assert!(matches!(parse(&tokenize("ls > out.txt").unwrap()).unwrap(), Command::Redirect { .. }));
+/// assert_eq!(parse(&[]).unwrap(), Command::Sequence(Vec::new()));
+///
+/// assert!(parse(&tokenize("ls |").unwrap()).is_err());
+/// assert!(parse(&tokenize("&& ls").unwrap()).is_err());
+/// assert!(parse(&tokenize("ls ; ; ls").unwrap()).is_err());
+/// ```
+pub fn parse(tokens: &[String]) -> Result<Command, ShellError> {
+    let mut commands = Vec::new();
+    let mut start = 0;
+    let mut separator = None;
+
+    for (index, token) in tokens.iter().enumerate() {
+        if token != ";" && token != "&" {
+            continue;
+        }
+        let and_or = parse_and_or(&tokens[start..index], separator, token)?;
+        commands.push(if token == "&" { Command::Background(Box::new(and_or)) } else { and_or });
+        start = index + 1;
+        separator = Some(token.as_str());
+    }
+    if start < tokens.len() {
+        commands.push(parse_and_or(&tokens[start..], separator, "end of line")?);
+    }
+
+    if commands.len() == 1 {
+        Ok(commands.remove(0))
+    } else {
+        Ok(Command::Sequence(commands))
+    }
+}
+
+/// Parses pipelines joined by `&&` and `||`; `previous` and `next` name the operators around them, for errors.
+fn parse_and_or(tokens: &[String], previous: Option<&str>, next: &str) -> Result<Command, ShellError> {
+    let mut command: Option<Command> = None;
+    let mut operator: Option<&str> = None;
+    let mut start = 0;
+
+    for index in 0..=tokens.len() {
+        let token = tokens.get(index).map(String::as_str);
+        if index < tokens.len() && token != Some("&&") && token != Some("||") {
+            continue;
+        }
+        let pipeline = parse_pipeline(&tokens[start..index], operator.or(previous), token.unwrap_or(next))?;
+        command = Some(match (command, operator) {
+            (Some(left), Some("&&")) => Command::And(Box::new(left), Box::new(pipeline)),
+            (Some(left), _) => Command::Or(Box::new(left), Box::new(pipeline)),
+            (None, _) => pipeline,
+        });
+        operator = token;
+        start = index + 1;
+    }
+
+    // The loop always runs at least once, so a command has been parsed or an error returned.
+    Ok(command.expect("at least one pipeline"))
+}
+
+/// Parses simple commands joined by `|`; `previous` and `next` name the operators around them, for errors.
+fn parse_pipeline(tokens: &[String], previous: Option<&str>, next: &str) -> Result<Command, ShellError> {
+    let stage_count = tokens.split(|token| token == "|").count();
+    let mut stages = tokens
+        .split(|token| token == "|")
+        .enumerate()
+        .map(|(index, stage)| {
+            let before = if index == 0 { previous } else { Some("|") };
+            parse_simple(stage, before, if index + 1 < stage_count { "|" } else { next })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if stages.len() == 1 {
+        Ok(stages.remove(0))
+    } else {
+        Ok(Command::Pipeline(stages))
+    }
+}
+
+/// Parses one command with its redirections; `previous` and `next` name the operators around it, for errors.
+fn parse_simple(tokens: &[String], previous: Option<&str>, next: &str) -> Result<Command, ShellError> {
+    let words: Vec<&str> = tokens.iter().map(String::as_str).collect();
+    let (words, redirections) = parse_redirections(&words).map_err(|e| ShellError::Parse(e.to_string()))?;
+    let Some((name, args)) = words.split_first() else {
+        return Err(ShellError::Parse(match previous {
+            Some(operator) => format!("expected a command after '{}'", operator),
+            None => format!("expected a command before {}", describe(next)),
+        }));
+    };
+
+    let name = name.to_string();
+    let args = args.iter().map(|arg| arg.to_string()).collect();
+    let command = if BUILTINS.iter().any(|builtin| builtin.name == name) || KEYWORDS.contains(&name.as_str()) {
+        Command::Builtin { name, args }
+    } else {
+        Command::External { name, args }
+    };
+
+    if redirections == Redirections::default() {
+        Ok(command)
+    } else {
+        Ok(Command::Redirect { command: Box::new(command), redirections })
+    }
+}
+
+/// Quotes an operator for an error message, leaving `end of line` as it is.
+fn describe(operator: &str) -> String {
+    if operator == "end of line" {
+        operator.to_string()
+    } else {
+        format!("'{}'", operator)
+    }
+}
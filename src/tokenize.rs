@@ -0,0 +1,102 @@
+//! Splitting command lines into words.
+//!
+//! The rules follow the POSIX shell: whitespace separates words, single quotes keep everything up to the
+//! next single quote, double quotes keep everything but let a backslash escape `$`, `` ` ``, `"`, `\`, and
+//! a newline, and an unquoted backslash keeps the character after it.
+//!
+//! The line is read a `char` at a time, so a backslash escapes a whole character, however many bytes it
+//! takes in UTF-8, and words such as `café` or `🎉` are never split inside a character.
+
+use crate::ShellError;
+
+/// Splits a command line into words.
+///
+/// # Purpose
+/// This function is the shell's tokenizer. It breaks `input` into words at runs of unquoted whitespace and
+/// removes the quoting as it goes, so that `echo "two  spaces"` yields the words `echo` and `two  spaces`.
+/// Quotes may begin or end in the middle of a word (`pre"fix"ed` is one word, `prefixed`), and an empty
+/// pair of quotes is an empty word. A backslash in front of a newline joins the two lines, as in a script.
+/// A backslash at the very end of the input is kept as typed.
+///
+/// # Parameters
+/// - `input`: A `&str` representing the command line, after expansion.
+///
+/// # Returns
+/// This function returns a `Result<Vec<String>, ShellError>` with the words in order. It returns
+/// `ShellError::Parse` if a single or double quote is left unterminated.
+///
+/// # Examples
+/// ```
+/// use myshell::tokenize;
+///
+/// assert_eq!(tokenize("echo  hello   world").unwrap(), vec!["echo", "hello", "world"]);
+/// assert_eq!(tokenize("echo 'it''s' \"a  b\"").unwrap(), vec!["echo", "its", "a  b"]);
+/// assert_eq!(tokenize("echo it\\'s \"\"").unwrap(), vec!["echo", "it's", ""]);
+/// assert!(tokenize("echo \"unterminated").is_err());
+/// ```
+pub fn tokenize(input: &str) -> Result<Vec<String>, ShellError> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false; // Distinguishes an empty quoted word from no word at all.
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    tokens.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(ShellError::Parse("unterminated single quote".to_string())),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c @ ('$' | '`' | '"' | '\\')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(ShellError::Parse("unterminated double quote".to_string())),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(ShellError::Parse("unterminated double quote".to_string())),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some(c) => {
+                    in_word = true;
+                    current.push(c);
+                }
+                None => {
+                    in_word = true;
+                    current.push('\\');
+                }
+            },
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,53 @@
+//! A micro-benchmark of how many allocations the read-dispatch loop makes per prompt. It lives in its own test
+//! binary, with a single test, so that the counting allocator sees no other test's allocations.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use myshell::{render_prompt, run_with_input, ShellState};
+
+/// The system allocator, counting every allocation made through it.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: The caller's guarantees about `layout` are passed on unchanged.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` was allocated by `System` with this `layout`, in `alloc` above.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Counts the allocations `f` makes.
+fn allocations_in(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn reading_a_line_reuses_the_input_buffer() {
+    const LINES: usize = 1000;
+    let mut state = ShellState { color: false, ..ShellState::default() };
+    let script = " \n".repeat(LINES);
+
+    // One more prompt is shown for the end of input.
+    let prompts = allocations_in(|| (0..=LINES).for_each(|_| drop(render_prompt(&state))));
+    let total = allocations_in(|| run_with_input(script.as_bytes(), &mut io::sink(), &mut state).unwrap());
+
+    // Apart from a few allocations to set up, rendering the prompts is all that allocates: reading each line
+    // into the reused buffer does not.
+    assert!(total <= prompts + 16, "{} allocations in the loop, {} of them for prompts", total, prompts);
+}
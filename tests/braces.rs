@@ -0,0 +1,41 @@
+//! Table-driven checks of `expand_braces`.
+
+use myshell::expand_braces;
+
+#[test]
+fn expands_unquoted_groups_and_ranges() {
+    let cases = [
+        ("echo plain words", "echo plain words"),
+        ("echo file.{txt,md,rs}", "echo file.txt file.md file.rs"),
+        ("echo {a,b}", "echo a b"),
+        ("echo x{,y}", "echo x xy"),
+        ("echo {a,b}{1,2}", "echo a1 a2 b1 b2"),
+        ("echo {1..3} {3..1}", "echo 1 2 3 3 2 1"),
+        ("echo {-1..1}", "echo -1 0 1"),
+        ("echo v{1..2}.{a,b}", "echo v1.a v1.b v2.a v2.b"),
+        ("echo {x} {} {a..b} {1..}", "echo {x} {} {a..b} {1..}"),
+        ("echo {1..99999999999999} {0..10000}", "echo {1..99999999999999} {0..10000}"),
+        ("echo {-9223372036854775808..9223372036854775807}", "echo {-9223372036854775808..9223372036854775807}"),
+        ("echo {open a,b", "echo {open a,b"),
+        ("echo {x}{a,b}", "echo {x}a {x}b"),
+        ("echo  spaced   {a,b}  ", "echo  spaced   a b  "),
+        ("echo '{a,b}' \"{a,b}\" \\{a,b}", "echo '{a,b}' \"{a,b}\" \\{a,b}"),
+        ("echo {'a b',c}", "echo 'a b' c"),
+        ("echo {a,'x,y'}", "echo a 'x,y'"),
+        ("echo ${HOME} $(echo {a,b})", "echo ${HOME} $(echo {a,b})"),
+        ("echo {a,{b,c}}", "echo {a,{b,c}}"),
+        ("echo caf{é,e}", "echo café cafe"),
+        ("echo {🎉,日本}{1..2}", "echo 🎉1 🎉2 日本1 日本2"),
+        ("echo 'ü{a,b}' \\é{x,y}", "echo 'ü{a,b}' \\éx \\éy"),
+    ];
+
+    for (line, expected) in cases {
+        assert_eq!(expand_braces(line), expected, "expanding {:?}", line);
+    }
+}
+
+#[test]
+fn ranges_expand_up_to_their_limit() {
+    assert_eq!(expand_braces("{1..10000}").split(' ').count(), 10_000);
+    assert_eq!(expand_braces("{1..10001}"), "{1..10001}");
+}
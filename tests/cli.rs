@@ -0,0 +1,426 @@
+//! End-to-end checks that drive the `myshell` binary through piped standard input.
+
+use std::{env, fs, io::Write, process::{self, Command, Output, Stdio}, sync::atomic::{AtomicUsize, Ordering}};
+
+/// Runs the shell with `args` and `script` as its standard input, and returns everything it printed. The shell gets a
+/// fresh home directory so the user's own configuration and autosaved aliases are neither read nor written.
+fn run_shell(args: &[&str], script: &str) -> Output {
+    static NEXT_HOME: AtomicUsize = AtomicUsize::new(0);
+    let home = env::temp_dir().join(format!("myshell-test-home-{}-{}", process::id(), NEXT_HOME.fetch_add(1, Ordering::SeqCst)));
+    fs::create_dir_all(&home).unwrap();
+
+    let mut shell = Command::new(env!("CARGO_BIN_EXE_myshell-rs"))
+        .arg("--no-color")
+        .args(args)
+        .env("HOME", &home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the shell binary should start");
+    // The shell may exit without reading its input, as it does for `--version`, so a broken pipe is fine.
+    let _ = shell.stdin.take().unwrap().write_all(script.as_bytes());
+    let output = shell.wait_with_output().unwrap();
+    fs::remove_dir_all(&home).unwrap();
+    output
+}
+
+#[test]
+fn non_zero_exit_sets_status_without_error_output() {
+    let output = run_shell(&[], "false\nECHO status=$?\n");
+
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("status=1"));
+}
+
+#[test]
+fn version_flag_prints_version_without_starting_the_loop() {
+    for flag in ["--version", "-V"] {
+        let output = run_shell(&[flag], "ECHO should not run\n");
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("myshell-rs {}\n", env!("CARGO_PKG_VERSION"))
+        );
+    }
+}
+
+#[test]
+fn help_flag_describes_every_mode() {
+    let output = run_shell(&["--help"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    for expected in ["-c <command line>", "<script>", "--version", "~/.myshellrc"] {
+        assert!(stdout.contains(expected), "missing {:?} in:\n{}", expected, stdout);
+    }
+}
+
+#[test]
+fn command_flag_runs_the_line_and_exits_with_its_status() {
+    let output = run_shell(&["-c", "ECHO from -c\nfalse"], "ECHO should not run\n");
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "from -c\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn whitespace_only_line_is_a_no_op() {
+    let output = run_shell(&[], "false\n   \t  \nECHO status=$?\nHISTORY\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(stdout.contains("status=1"), "{}", stdout);
+    assert!(stdout.contains("    1  false\n    2  ECHO status=$?\n    3  HISTORY\n"), "{}", stdout);
+}
+
+#[test]
+fn operators_chain_and_pipe_commands() {
+    let output = run_shell(&[], "false && ECHO skipped || ECHO fallback ; echo one two | wc -w\n");
+
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("fallback") && !stdout.contains("skipped"));
+    assert!(stdout.lines().any(|line| line.trim_end().ends_with('2')));
+}
+
+#[test]
+fn errexit_stops_a_script_at_the_first_failure() {
+    let output = run_shell(&["-c", "SETOPT errexit on\nIF false\nTHEN\nECHO unreachable\nFI\nECHO before\nfalse\nECHO after"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("before"));
+    assert!(!stdout.contains("after") && !stdout.contains("unreachable"));
+}
+
+#[test]
+fn time_keeps_the_status_of_the_timed_command() {
+    let output = run_shell(&["-c", "TIME false\nECHO s=$?\nSETOPT errexit on\nTIME false\nECHO after"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("s=1\n"), "{}", stdout);
+    assert!(!stdout.contains("after"), "{}", stdout);
+}
+
+#[test]
+fn xtrace_prints_expanded_commands_to_stderr() {
+    let output = run_shell(&["-c", "SET greeting hi\nSETOPT xtrace on\nECHO $greeting 'a b'\necho x | tr x y\nNEWNAME say echo\nsay\nsay 'hi there' x"], "");
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "+ ECHO hi 'a b'\n+ echo x\n+ tr x y\n+ NEWNAME say echo\n+ echo\n+ echo 'hi there' x\n"
+    );
+}
+
+#[test]
+fn exit_stops_the_shell_with_the_given_status() {
+    for command in ["exit 3", "quit 3", "logout 3", "STOP 3"] {
+        let output = run_shell(&[], &format!("{}\nECHO unreachable\n", command));
+
+        assert_eq!(output.status.code(), Some(3), "running {:?}", command);
+        assert!(!String::from_utf8_lossy(&output.stdout).contains("unreachable"));
+    }
+
+    // A `-c` command line stops at `STOP` too, skipping the rest of its line.
+    let output = run_shell(&["-c", "STOP 4 ; ECHO unreachable\nECHO unreachable"], "");
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[test]
+fn malformed_alias_lines_are_reported_and_skipped() {
+    let path = env::temp_dir().join(format!("myshell-test-aliases-{}", process::id()));
+    fs::write(&path, "# saved aliases\nll ls -l\n\nbroken\n   \nhi echo hello there\n").unwrap();
+    let output = run_shell(&[], &format!("READNEWNAMES {}\nhi\n", path.display()));
+    fs::remove_file(&path).unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("Warning: {}:4: skipping malformed alias line (expected '<name> <command>'): broken\n", path.display())
+    );
+    assert!(stdout.contains(&format!("Loaded 2 aliases from {}.", path.display())), "{}", stdout);
+    assert!(stdout.contains("hello there"), "{}", stdout);
+}
+
+#[test]
+fn exported_aliases_can_be_sourced_by_bash() {
+    let path = env::temp_dir().join(format!("myshell-test-export-{}.sh", process::id()));
+    let output = run_shell(&[], &format!("NEWNAME tricky \"echo it's \\$HOME  'twice'\"\nEXPORTALIASES {}\n", path.display()));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+
+    let bash = Command::new("bash")
+        .arg("--norc")
+        .arg("-c")
+        .arg(format!(". '{}' && alias tricky", path.display()))
+        .output()
+        .expect("bash should be installed");
+    fs::remove_file(&path).unwrap();
+
+    assert!(bash.status.success(), "{}", String::from_utf8_lossy(&bash.stderr));
+    assert_eq!(String::from_utf8_lossy(&bash.stdout), "alias tricky='echo it'\\''s $HOME  '\\''twice'\\'''\n");
+}
+
+#[test]
+fn suffix_aliases_run_files_with_their_command() {
+    let path = env::temp_dir().join(format!("myshell-test-suffix-{}.sh", process::id()));
+    fs::write(&path, "echo \"ran with $1\"\n").unwrap();
+    let output = run_shell(&[], &format!("SUFFIXALIAS .sh sh\n{} first\nSUFFIXALIAS\n", path.display()));
+    fs::remove_file(&path).unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(stdout.contains("ran with first"), "{}", stdout);
+    assert!(stdout.contains(".sh - sh"), "{}", stdout);
+}
+
+#[test]
+fn here_strings_feed_standard_input() {
+    let output = run_shell(&[], "SET name world\ncat <<< \"hello  $name\"\nwc -c <<<abc\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(stdout.contains("hello  world\n"), "{}", stdout);
+    assert!(stdout.contains("4\n"), "{}", stdout);
+}
+
+#[test]
+fn null_device_redirections_discard_output_without_creating_files() {
+    let dir = env::temp_dir().join(format!("myshell-test-null-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = format!(
+        "CD {}\nsh -c 'echo out; echo err >&2' > NUL 2> /dev/null\nsh -c 'echo both; echo both >&2' &> nul\nwc -c < NUL\n",
+        dir.display()
+    );
+    let output = run_shell(&[], &script);
+    let created: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(!stdout.contains("out") && !stdout.contains("both"), "{}", stdout);
+    assert!(stdout.contains('0'), "{}", stdout);
+    assert!(created.is_empty(), "{:?}", created);
+}
+
+#[test]
+fn newname_without_arguments_lists_aliases_like_listnewnames() {
+    let output = run_shell(&[], "NEWNAME zz ls\nNEWNAME aa pwd\nNEWNAME mm date\nNEWNAME\nLISTNEWNAMES\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("Aliases:\naa - pwd\nmm - date\nzz - ls\n").count(), 2, "{}", stdout);
+}
+
+#[test]
+fn alias_descriptions_show_in_listings() {
+    let output = run_shell(&[], "NEWNAME ll \"ls -l\" --desc \"long listing\"\nNEWNAME la \"ls -A\"\nLISTNEWNAMES\nSEARCHALIAS ll\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Aliases:\nla - ls -A\nll - ls -l  # long listing\n"), "{}", stdout);
+    assert_eq!(stdout.matches("ll - ls -l  # long listing\n").count(), 2, "{}", stdout);
+}
+
+#[test]
+fn mostused_lists_aliases_by_how_often_they_ran() {
+    let output = run_shell(&[], "NEWNAME yes1 true\nNEWNAME twice true\nNEWNAME never true\ntwice\nyes1\ntwice | twice\nMOSTUSED\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(stdout.contains("    3  twice - true\n    1  yes1 - true\n    0  never - true\n"), "{}", stdout);
+}
+
+#[test]
+fn global_aliases_expand_anywhere_but_in_quotes() {
+    let output = run_shell(&[], "GLOBALALIAS C \"| wc -l\"\nprintf 'a\\nb\\n' C\nECHO \"C\" 'C' C2\nGLOBALALIAS C\nECHO C\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(stdout.contains("2\n"), "{}", stdout);
+    assert!(stdout.contains("C C C2\n"), "{}", stdout);
+    assert!(stdout.ends_with("C\nMy Shell> "), "{}", stdout);
+}
+
+#[test]
+fn quotes_of_the_other_kind_stay_literal_through_expansion() {
+    let output = run_shell(&[], "SET x 1\nECHO \"it's $x\" 'say \"$x\"' \"{a,b}'\"\n");
+
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("it's 1 say \"$x\" {a,b}'\n"));
+}
+
+#[test]
+fn wait_blocks_until_background_jobs_finish() {
+    let output = run_shell(&[], "sh -c 'sleep 0.2; exit 3' &\nsh -c 'sleep 0.4; exit 5' &\nWAIT %1\nECHO first=$?\nWAIT\nECHO rest=$?\nJOBS\nWAIT %1\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[1] Done (3) sh -c sleep 0.2; exit 3\n"), "{}", stdout);
+    assert!(stdout.contains("first=3\n"), "{}", stdout);
+    assert!(stdout.contains("rest=5\n"), "{}", stdout);
+    assert!(!stdout.contains("Running"), "{}", stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: WAIT: no such job %1\n");
+}
+
+#[test]
+fn scripts_see_their_name_and_arguments_as_positional_parameters() {
+    let path = env::temp_dir().join(format!("myshell-test-positional-{}", process::id()));
+    fs::write(&path, "ECHO \"$# args: [$1] [$2] [$3]\"\nECHO ${10}$10\n").unwrap();
+    let path = path.to_str().unwrap();
+    let output = run_shell(&[path, "one", "two words", "3", "4", "5", "6", "7", "8", "9", "ten"], "");
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10 args: [one] [two words] [3]\ntenone0\n");
+}
+
+#[test]
+fn shift_drops_positional_parameters_in_a_script() {
+    let path = env::temp_dir().join(format!("myshell-test-shift-{}", process::id()));
+    fs::write(&path, "SHIFT\nECHO $0 $# $1\nSHIFT 2\nECHO $# [$1]\nSHIFT\nECHO status=$?\n").unwrap();
+    let path = path.to_str().unwrap();
+    let output = run_shell(&[path, "a", "b", "c"], "");
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("{} 2 b\n0 []\nstatus=1\n", path));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), format!("{}:5: Error: SHIFT: cannot shift 1 of 0 positional parameters\n", path));
+}
+
+#[test]
+fn bash_style_alias_definitions_define_and_list_aliases() {
+    let output = run_shell(&[], "alias ll='ls -l' say=\"echo it's\"\nalias\nsay\nalias nope\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("alias ll='ls -l'\nalias say='echo it'\\''s'\n"), "{}", stdout);
+    assert!(stdout.contains("> it's\n"), "{}", stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: ALIAS: nope: not found\n");
+}
+
+#[test]
+fn built_in_output_follows_redirections() {
+    let out = env::temp_dir().join(format!("myshell-test-builtin-out-{}", process::id()));
+    let err = env::temp_dir().join(format!("myshell-test-builtin-err-{}", process::id()));
+    let script = format!(
+        "ECHO one > {0}\nECHO two >> {0}\nSHIFT 5 2> {1}\nECHO status $?\nREAD line < {0}\nECHO shown\n",
+        out.display(),
+        err.display()
+    );
+    let output = run_shell(&[], &script);
+    let written = fs::read_to_string(&out).unwrap();
+    let errors = fs::read_to_string(&err).unwrap();
+    fs::remove_file(&out).unwrap();
+    fs::remove_file(&err).unwrap();
+
+    assert_eq!(written, "one\ntwo\n");
+    assert_eq!(errors, "Error: SHIFT: cannot shift 5 of 0 positional parameters\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("> status 1\n") && stdout.contains("> shown\n") && !stdout.contains("one"), "{}", stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: The input of the built-in command 'READ' cannot be redirected\n");
+}
+
+#[test]
+fn alias_arguments_follow_the_ones_the_alias_gives() {
+    let output = run_shell(&[], "NEWNAME greet 'echo hello'\ngreet world\ngreet there | cat\n");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("> hello world\n"), "{}", stdout);
+    assert!(stdout.contains("> hello there\n"), "{}", stdout);
+}
+
+#[test]
+fn aliases_with_a_blank_command_are_rejected() {
+    let output = run_shell(&[], "NEWNAME x \"\"\nalias y='  '\nx\nECHO still here\n");
+
+    assert!(String::from_utf8_lossy(&output.stdout).contains("> still here\n"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("Error: The command for alias 'x' cannot be empty.\nError: The command for alias 'y' cannot be empty.\n"), "{}", stderr);
+}
+
+#[test]
+fn savenewnames_writes_the_format_asked_for_or_named_by_the_extension() {
+    let json = env::temp_dir().join(format!("myshell-test-save-{}.json", process::id()));
+    let toml = env::temp_dir().join(format!("myshell-test-save-{}.conf", process::id()));
+    let script = format!(
+        "NEWNAME greet \"echo \\\"hi\\\"\"\nSAVENEWNAMES {}\nSAVENEWNAMES --format toml {}\nUNALIAS greet\nLOADSETTINGS {1}\ngreet\nSAVENEWNAMES --format yaml {1}\n",
+        json.display(),
+        toml.display()
+    );
+    let output = run_shell(&[], &script);
+    let written = fs::read_to_string(&json).unwrap();
+    fs::remove_file(&json).unwrap();
+    fs::remove_file(&toml).unwrap();
+
+    assert_eq!(written, "{\n  \"greet\": \"echo \\\"hi\\\"\"\n}\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("> \"hi\"\n"), "{}", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: SAVENEWNAMES: unknown format 'yaml' (expected text, json, toml or bash)\n"
+    );
+}
+
+#[test]
+fn errors_in_sourced_scripts_name_the_file_and_line() {
+    let path = env::temp_dir().join(format!("myshell-test-lines-{}.mysh", process::id()));
+    let script = "ECHO start \\\n  continued\nno-such-command-for-lines\nIF true\nTHEN\n\nSHIFT 5\nFI\nFOR word IN a\nDO\nSET 'bad name' $word\nDONE\nFOR word IN b; DO SET 'worse name' $word; DONE\n";
+    fs::write(&path, script).unwrap();
+    let output = run_shell(&[], &format!("SOURCE {}\nSHIFT 5\n", path.display()));
+    fs::remove_file(&path).unwrap();
+
+    let file = path.display();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!(
+            "{0}:3: myshell: command not found: no-such-command-for-lines\n\
+             {0}:7: Error: SHIFT: cannot shift 5 of 0 positional parameters\n\
+             {0}:11: Error: 'bad name' is not a valid variable name\n\
+             {0}:13: Error: 'worse name' is not a valid variable name\n\
+             Error: SHIFT: cannot shift 5 of 0 positional parameters\n",
+            file
+        )
+    );
+}
+
+#[test]
+fn quiet_mode_leaves_out_confirmations() {
+    let output = run_shell(&[], "SETSHELLNAME Loud\nSETOPT quiet on\nSETSHELLNAME Hushed\nNEWNAME ll 'ls -l'\nUNALIAS ll\nNEWNAME ll\nSETOPT quiet\nSETOPT verbose\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "My Shell> Shell name set to: Loud\nLoud> Loud> Hushed> Hushed> Hushed> Alias 'll' does not exist.\nHushed> quiet on\nHushed> verbose off\nHushed> "
+    );
+
+    let path = env::temp_dir().join(format!("myshell-test-quiet-{}", process::id()));
+    fs::write(&path, "SETSHELLNAME Scripted\nNEWNAME ll 'ls -l'\nSETOPT verbose on\nSETSHELLNAME Chatty\n").unwrap();
+    let output = run_shell(&[path.to_str().unwrap()], "");
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Shell name set to: Chatty\n");
+}
+
+#[test]
+fn dryrun_prints_programs_instead_of_running_them() {
+    let marker = env::temp_dir().join(format!("myshell-test-dryrun-{}", process::id()));
+    let script = format!(
+        "NEWNAME q 'echo A'\nSETOPT dryrun on\nSET target {}\ntouch $target\nfalse\nECHO status=$?\nls -l | wc -l > \"$target\"\nsleep 5 &\nq E F\nSETOPT dryrun off\n",
+        marker.display()
+    );
+    let output = run_shell(&[], &script);
+
+    assert!(!marker.exists());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!("touch {0}\nMy Shell> false\nMy Shell> status=0\nMy Shell> ls -l | wc -l > {0}\nMy Shell> sleep 5 &\nMy Shell> echo A E F\n", marker.display());
+    assert!(stdout.contains(&expected), "{}", stdout);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn non_ascii_names_values_and_arguments_pass_through_intact() {
+    let script = "NEWNAME café 'echo ☕ crème'\ncafé\nSET naïve 🎉\nECHO $naïve ${naïve}é \"ü$naïve\" 'ß' \\日\nalias ñ='echo ñandú'\nñ\nFUNCTION grüß { ECHO hallo $1 }\ngrüß wélt\nGLOBALALIAS Ω '| cat'\necho ça va Ω\n";
+    let output = run_shell(&[], script);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for expected in ["> ☕ crème\n", "> 🎉 🎉é ü🎉 ß 日\n", "> ñandú\n", "> hallo wélt\n", "> ça va\n"] {
+        assert!(stdout.contains(expected), "{:?} not in {}", expected, stdout);
+    }
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
@@ -0,0 +1,43 @@
+//! Checks that program paths are remembered between lookups and forgotten when `PATH` changes.
+
+use std::{env, fs, path::PathBuf, process};
+
+use myshell::{export_variables, hash_command, ShellState};
+
+/// Creates a fresh directory holding an executable named `probe`, and returns the directory.
+fn directory_with_probe(label: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("myshell-hash-{}-{}", label, process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let probe = dir.join("probe");
+    fs::write(&probe, "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&probe, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn second_lookup_skips_the_path_search() {
+    let (first, second) = (directory_with_probe("first"), directory_with_probe("second"));
+    let mut state = ShellState::default();
+    state.environment.insert("PATH".to_string(), first.display().to_string());
+    assert_eq!(state.command_path("probe"), Some(first.join("probe")));
+
+    // Changing the environment behind the shell's back shows whether PATH is searched again: it is not.
+    let both = env::join_paths([&second, &first]).unwrap().into_string().unwrap();
+    state.environment.insert("PATH".to_string(), both.clone());
+    assert_eq!(state.command_path("probe"), Some(first.join("probe")));
+
+    hash_command(&["HASH", "-r"], &state).unwrap();
+    assert_eq!(state.command_path("probe"), Some(second.join("probe")));
+
+    let first_only = format!("PATH={}", first.display());
+    export_variables(&["EXPORT", &first_only], &mut state).unwrap();
+    assert!(state.command_paths.borrow().is_empty());
+    assert_eq!(state.command_path("probe"), Some(first.join("probe")));
+
+    fs::remove_dir_all(first).unwrap();
+    fs::remove_dir_all(second).unwrap();
+}
@@ -0,0 +1,157 @@
+//! Checks of the read-dispatch loop fed from an in-memory script through `run_with_input`.
+
+use myshell::{run_with_input, BuiltinKind, ShellState};
+
+/// Runs `script` through the loop on a fresh state, returning the state and the prompts that were written.
+fn run_script(script: &str) -> (ShellState, String) {
+    let mut state = ShellState { color: false, ..ShellState::default() };
+    let mut transcript = Vec::new();
+    run_with_input(script.as_bytes(), &mut transcript, &mut state).unwrap();
+    (state, String::from_utf8(transcript).unwrap())
+}
+
+#[test]
+fn blank_lines_keep_the_status_and_stay_out_of_history() {
+    let (state, transcript) = run_script("false\n\n   \n");
+
+    assert_eq!(state.last_status, 1);
+    assert_eq!(state.history, vec!["false"]);
+    assert_eq!(transcript.matches("My Shell> ").count(), 4);
+}
+
+#[test]
+fn later_lines_see_what_earlier_lines_set() {
+    let (state, _) = run_script("SETSHELLNAME Scripted\nSET n 2\nSET m $(($n * 3))\nSET greeting \"hello  $n\"\n");
+
+    assert_eq!(state.shellname, "Scripted");
+    assert_eq!(state.variables["m"], "6");
+    assert_eq!(state.variables["greeting"], "hello  2");
+}
+
+#[test]
+fn unterminated_block_stops_without_running_it() {
+    let (state, _) = run_script("IF true\nTHEN\nSET ran yes\n");
+
+    assert!(!state.variables.contains_key("ran"));
+    assert_eq!(state.history, vec!["IF true", "THEN", "SET ran yes"]);
+}
+
+#[test]
+fn running_jobs_hold_back_the_first_exit() {
+    let mut state = ShellState { color: false, interactive: true, ..ShellState::default() };
+    run_with_input("sleep 10 &\nSTOP\nSET after yes\n".as_bytes(), &mut Vec::new(), &mut state).unwrap();
+
+    // STOP only warned, and the end of input exited on its second attempt, ending the job.
+    assert_eq!(state.variables["after"], "yes");
+    assert!(state.jobs.is_empty());
+}
+
+#[test]
+fn disowned_jobs_keep_running_without_holding_back_exit() {
+    let marker = std::env::temp_dir().join(format!("myshell-test-disown-{}", std::process::id()));
+    let script = format!("sh -c 'sleep 0.3; touch {}' &\nDISOWN\n", marker.display());
+    let mut state = ShellState { color: false, interactive: true, autosave: false, ..ShellState::default() };
+    run_with_input(script.as_bytes(), &mut Vec::new(), &mut state).unwrap();
+
+    // The end of input exited without a warning, and the job finished by itself afterwards.
+    assert!(!state.exit_warned);
+    std::thread::sleep(std::time::Duration::from_millis(600));
+    assert!(marker.exists());
+    std::fs::remove_file(marker).unwrap();
+}
+
+#[test]
+fn history_options_leave_out_repeats_and_space_prefixed_lines() {
+    let script = "SETOPT hist_ignore_dups on\nSETOPT hist_ignore_space on\ntrue\ntrue\n true\n SET secret 1\nfalse\ntrue\n";
+    let (state, _) = run_script(script);
+
+    assert_eq!(state.history, vec!["SETOPT hist_ignore_dups on", "SETOPT hist_ignore_space on", "true", "false", "true"]);
+    assert_eq!(state.variables["secret"], "1");
+}
+
+#[test]
+fn history_keeps_everything_by_default() {
+    let (state, _) = run_script("true\ntrue\n true\n");
+
+    assert_eq!(state.history, vec!["true", "true", "true"]);
+}
+
+#[test]
+fn here_documents_feed_the_lines_up_to_their_delimiter() {
+    let path = std::env::temp_dir().join(format!("myshell-test-heredoc-{}.txt", std::process::id()));
+    let script = format!(
+        "SET name world\ncat <<EOF > {0}\nhello $name\n\tkept \"quotes\"\nEOF\ncat <<-'END' >> {0}\n\t$name\n\tEND\nSET after yes\n",
+        path.display()
+    );
+    let (state, _) = run_script(&script);
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(written, "hello world\n\tkept \"quotes\"\n$name\n");
+    assert_eq!(state.variables["after"], "yes");
+}
+
+#[test]
+fn continuation_prompt_follows_setprompt2() {
+    let (state, transcript) = run_script("SETPROMPT2 '... '\nSET joined one \\\ntwo\n");
+
+    assert_eq!(state.variables["joined"], "one two");
+    assert_eq!(transcript, "My Shell> My Shell> ... My Shell> ");
+}
+
+#[test]
+fn while_loops_repeat_their_body_until_the_condition_fails() {
+    let script = "SET n 0\nWHILE [ $n != 3 ]\nSET n $(($n + 1))\nIF [ $n = 2 ]\nTHEN\nSET seen $n\nFI\nDONE\nSET after $?\n";
+    let (state, transcript) = run_script(script);
+
+    assert_eq!(state.variables["n"], "3");
+    assert_eq!(state.variables["seen"], "2");
+    assert_eq!(state.variables["after"], "0");
+    // The whole block was read before any of it ran.
+    assert_eq!(transcript.matches("> ").count(), 10);
+}
+
+#[test]
+fn for_loops_nest_and_skip_an_empty_list() {
+    let script = "SET empty ''\nFOR outer IN a b\nDO\nFOR inner IN 1 2; DO\nSET pairs \"$pairs $outer$inner\"\nDONE\nDONE\nFOR never IN $empty\nDO\nSET ran yes\nDONE\nSET after $?\n";
+    let (state, _) = run_script(script);
+
+    assert_eq!(state.variables["pairs"], " a1 a2 b1 b2");
+    assert!(!state.variables.contains_key("ran"));
+    assert!(!state.variables.contains_key("never"));
+    assert_eq!(state.variables["after"], "0");
+}
+
+#[test]
+fn for_loops_fit_on_one_line_and_need_do() {
+    let script = "FOR var IN a b; DO SET seen \"$seen$var\"; SET count \"$count.\"; DONE\nFOR x IN 1 2; DO FOR y IN p q; DO SET pairs \"$pairs $x$y\"; DONE; DONE\nFOR z IN 1\nSET ran yes\nDONE\nSET after $?\n";
+    let (state, _) = run_script(script);
+
+    assert_eq!(state.variables["seen"], "ab");
+    assert_eq!(state.variables["count"], "..");
+    assert_eq!(state.variables["pairs"], " 1p 1q 2p 2q");
+    // Without DO the loop is an error and its body never runs.
+    assert!(!state.variables.contains_key("ran"));
+    assert_eq!(state.variables["after"], "1");
+}
+
+#[test]
+fn functions_cannot_take_a_keyword_name() {
+    let mut state = ShellState { color: false, ..ShellState::default() };
+    state.keywords.insert("prompt".to_string(), BuiltinKind::from_name("SETSHELLNAME").unwrap());
+    let script = "FUNCTION exit { SET ran exit }\nFUNCTION Quit { SET ran quit }\nFUNCTION prompt { SET ran prompt }\nFUNCTION fine { SET ran fine }\n";
+    run_with_input(script.as_bytes(), &mut Vec::new(), &mut state).unwrap();
+
+    assert_eq!(state.functions.keys().collect::<Vec<_>>(), ["fine"]);
+}
+
+#[test]
+fn functions_run_their_body_with_positional_parameters() {
+    let script = "FUNCTION greet { SET greeting \"hello $1\" }\nFUNCTION count {\nSET counted $#\nfalse\n}\ngreet world\ncount a b c\nSET status $? zero $0\n";
+    let (state, transcript) = run_script(script);
+
+    assert_eq!(state.variables["greeting"], "hello world");
+    assert_eq!(state.variables["counted"], "3");
+    assert_eq!(state.variables["status"], "1 zero myshell-rs");
+    assert_eq!(transcript.matches("My Shell> ").count(), 6);
+}
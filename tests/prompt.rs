@@ -0,0 +1,54 @@
+//! Table-driven checks of how `render_prompt_at` expands prompt templates, with a fixed clock.
+
+use chrono::{Local, TimeZone};
+use myshell::{render_prompt_at, ShellState};
+
+#[test]
+fn expands_placeholders_against_a_fixed_clock() {
+    let now = Local.with_ymd_and_hms(2024, 1, 5, 23, 59, 1).unwrap();
+    let cases = [
+        ("\\t", "23:59:01"),
+        ("\\d", "Fri Jan 05"),
+        ("[\\d \\t] \\s\\> ", "[Fri Jan 05 23:59:01] My Shell> "),
+        ("\\\\t \\q", "\\t \\q"),
+    ];
+
+    for (template, expected) in cases {
+        let state = ShellState { prompt_template: template.to_string(), color: false, ..ShellState::default() };
+        assert_eq!(render_prompt_at(&state, now), expected, "rendering {:?}", template);
+    }
+}
+
+#[test]
+fn reads_the_clock_for_every_render() {
+    let state = ShellState { prompt_template: "\\t".to_string(), ..ShellState::default() };
+    let morning = Local.with_ymd_and_hms(2024, 1, 5, 8, 0, 0).unwrap();
+
+    assert_eq!(render_prompt_at(&state, morning), "08:00:00");
+    assert_eq!(render_prompt_at(&state, morning + chrono::Duration::seconds(90)), "08:01:30");
+}
+
+#[test]
+fn status_placeholders_follow_the_last_command() {
+    let now = Local::now();
+    let template = "\\G\\R[\\?]\\e[0m\\> ";
+    let cases = [(0, "\x1b[32m[0]\x1b[0m> "), (1, "\x1b[31m[1]\x1b[0m> "), (127, "\x1b[31m[127]\x1b[0m> ")];
+
+    for (last_status, expected) in cases {
+        let state = ShellState { prompt_template: template.to_string(), color: true, last_status, ..ShellState::default() };
+        assert_eq!(render_prompt_at(&state, now), expected, "rendering after status {}", last_status);
+
+        let plain = ShellState { color: false, ..state };
+        assert_eq!(render_prompt_at(&plain, now), format!("[{}]> ", last_status));
+    }
+}
+
+#[test]
+fn terminator_color_wraps_only_the_terminator() {
+    let now = Local::now();
+    let state = ShellState { terminator_color: Some("green".to_string()), color: true, ..ShellState::default() };
+    assert_eq!(render_prompt_at(&state, now), "My Shell\x1b[32m>\x1b[0m ");
+
+    let plain = ShellState { color: false, ..state };
+    assert_eq!(render_prompt_at(&plain, now), "My Shell> ");
+}
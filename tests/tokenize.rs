@@ -0,0 +1,72 @@
+//! Table-driven checks of how `tokenize` splits and unquotes command lines.
+
+use myshell::{tokenize, ShellError};
+
+#[test]
+fn splits_and_unquotes_words() {
+    let cases: &[(&str, &[&str])] = &[
+        ("", &[]),
+        ("   \t  ", &[]),
+        ("ls", &["ls"]),
+        ("ls -l /tmp", &["ls", "-l", "/tmp"]),
+        ("  leading and trailing  ", &["leading", "and", "trailing"]),
+        ("tabs\tand\nnewlines", &["tabs", "and", "newlines"]),
+        ("echo 'single quoted'", &["echo", "single quoted"]),
+        ("echo \"double quoted\"", &["echo", "double quoted"]),
+        ("echo \"  kept  spacing  \"", &["echo", "  kept  spacing  "]),
+        ("echo ''", &["echo", ""]),
+        ("echo \"\" ''", &["echo", "", ""]),
+        ("pre'fix'ed", &["prefixed"]),
+        ("a\"b c\"d", &["ab cd"]),
+        ("'adjacent''quotes'", &["adjacentquotes"]),
+        ("echo 'a \"double\" inside'", &["echo", "a \"double\" inside"]),
+        ("echo \"a 'single' inside\"", &["echo", "a 'single' inside"]),
+        ("echo \"it's fine\"", &["echo", "it's fine"]),
+        ("echo '\"'", &["echo", "\""]),
+        ("echo \"'\"", &["echo", "'"]),
+        ("echo \"a 'b' c\" 'x \"y\" z'", &["echo", "a 'b' c", "x \"y\" z"]),
+        ("'\"'\"'\"", &["\"'"]),
+        ("echo 'don'\"'\"'t'", &["echo", "don't"]),
+        ("echo '$HOME'", &["echo", "$HOME"]),
+        ("echo 'back\\slash'", &["echo", "back\\slash"]),
+        ("echo escaped\\ space", &["echo", "escaped space"]),
+        ("echo \\'not quoted\\'", &["echo", "'not", "quoted'"]),
+        ("echo \\\\", &["echo", "\\"]),
+        ("echo \"\\\"\"", &["echo", "\""]),
+        ("echo \"\\$HOME\"", &["echo", "$HOME"]),
+        ("echo \"\\\\\"", &["echo", "\\"]),
+        ("echo \"\\n stays\"", &["echo", "\\n stays"]),
+        ("echo one\\\ntwo", &["echo", "onetwo"]),
+        ("echo trailing\\", &["echo", "trailing\\"]),
+        ("echo ünïcödé 'ß'", &["echo", "ünïcödé", "ß"]),
+        ("café 🎉 日本語", &["café", "🎉", "日本語"]),
+        ("echo 'ça va' \"naïve 'ß'\"", &["echo", "ça va", "naïve 'ß'"]),
+        ("echo \\é grüß\\ dich", &["echo", "é", "grüß dich"]),
+        ("echo \"\\🎉\" é\\", &["echo", "\\🎉", "é\\"]),
+        ("echo 'é'\"ü\"ß", &["echo", "éüß"]),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(tokenize(input).unwrap(), *expected, "tokenizing {:?}", input);
+    }
+}
+
+#[test]
+fn reports_unterminated_quotes() {
+    let cases = [
+        ("echo 'open", "unterminated single quote"),
+        ("echo \"open", "unterminated double quote"),
+        ("echo \"escaped end\\\"", "unterminated double quote"),
+        ("echo \"it's", "unterminated double quote"),
+        ("'", "unterminated single quote"),
+        ("echo 'café", "unterminated single quote"),
+        ("echo \"🎉\\\"", "unterminated double quote"),
+    ];
+
+    for (input, message) in cases {
+        match tokenize(input) {
+            Err(ShellError::Parse(found)) => assert_eq!(found, message, "tokenizing {:?}", input),
+            other => panic!("tokenizing {:?} gave {:?}", input, other),
+        }
+    }
+}